@@ -0,0 +1,1123 @@
+use crate::config::{Backend, LlmConfig, OutputFormat, PromptTransport};
+use crate::process;
+use crate::templates;
+use crate::usage::{self, StreamLine, Usage};
+use colored::*;
+use eyre::{Context, Result};
+use handlebars::Handlebars;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How often the spawn loop polls the child for exit / timeout.
+const POLL_INTERVAL_MS: u64 = 500;
+
+/// Default time the agent's process group gets between SIGTERM and SIGKILL.
+pub const DEFAULT_KILL_GRACE_SECS: u64 = 10;
+
+/// How [`run_agent`] supervises one invocation.
+#[derive(Debug, Clone)]
+pub struct Limits<'a> {
+    /// Hard cap on the whole invocation.
+    pub timeout: Duration,
+    /// Kill after this long without a line of output; `None` disables it.
+    pub stall_timeout: Option<Duration>,
+    /// Time the process group gets between SIGTERM and SIGKILL.
+    pub grace: Duration,
+    /// When set (Ctrl-C), the agent's process group is stopped early and the
+    /// output captured so far is returned.
+    pub cancel: Option<&'a AtomicBool>,
+}
+
+impl Limits<'_> {
+    /// Just a timeout: no stall watchdog, default grace, not cancellable.
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            stall_timeout: None,
+            grace: Duration::from_secs(DEFAULT_KILL_GRACE_SECS),
+            cancel: None,
+        }
+    }
+}
+
+/// One agent invocation: which model to use, the rendered prompt, where to run
+/// it, and whether the agent should run with its permission prompts bypassed.
+#[derive(Debug, Clone)]
+pub struct AgentRequest<'a> {
+    pub model: &'a str,
+    pub prompt: &'a str,
+    pub work_dir: &'a Path,
+    /// Where [`run_agent`] writes the prompt before every invocation: the
+    /// debugging artifact, the `file` transport's payload, and the
+    /// `{{prompt_file}}` of an `llm.command` template. Lives in the session
+    /// dir, never the work_dir, so the agent cannot commit it.
+    pub prompt_file: &'a Path,
+    pub skip_permissions: bool,
+    pub output_format: OutputFormat,
+    pub transport: PromptTransport,
+}
+
+impl AgentRequest<'_> {
+    /// The prompt argument for backends that take it positionally: the prompt
+    /// itself (`argv`), a pointer to the prompt file (`file`), or nothing
+    /// (`stdin`).
+    pub fn argv_prompt(&self) -> Option<String> {
+        match self.transport {
+            PromptTransport::Argv => Some(self.prompt.to_string()),
+            PromptTransport::Stdin => None,
+            PromptTransport::File => Some(format!(
+                "Your complete instructions for this run are in the file {}. \
+                 Read that file in full first, then follow it exactly.",
+                self.prompt_file.display()
+            )),
+        }
+    }
+}
+
+/// Captured output of an agent process that ran to completion.
+#[derive(Debug, Clone, Default)]
+pub struct AgentOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// Process exit code (`None` when terminated by a signal).
+    pub exit_code: Option<i32>,
+    /// Reported spend, captured only in `stream-json` mode.
+    pub usage: Option<Usage>,
+    /// Processes stopped in the agent's group after it exited (or was
+    /// cancelled): background servers and watchers it left behind.
+    pub reaped: Vec<String>,
+    /// The run was stopped early through [`Limits::cancel`].
+    pub cancelled: bool,
+}
+
+impl AgentOutput {
+    /// stdout and stderr joined - the text the promise / verdict scans run over.
+    pub fn combined(&self) -> String {
+        format!("{}\n{}", self.stdout, self.stderr)
+    }
+}
+
+/// The agent went quiet: no stdout or stderr line for the stall timeout.
+///
+/// Returned (wrapped in the `eyre::Report`) by [`run_agent`] after the child
+/// has been killed, so callers can tell a hung agent apart from a hard
+/// failure with `downcast_ref::<Stalled>()`.
+#[derive(Debug, Clone)]
+pub struct Stalled {
+    pub backend: String,
+    /// How long the agent had been silent when it was killed.
+    pub idle: Duration,
+    /// The agent's process group at the time it was stopped.
+    pub killed: Vec<String>,
+    /// What the agent printed before it went quiet.
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl std::fmt::Display for Stalled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} stalled: no output for {}",
+            self.backend,
+            describe_timeout(self.idle)
+        )
+    }
+}
+
+impl std::error::Error for Stalled {}
+
+/// The agent ran past the iteration timeout and was killed.
+///
+/// Like [`Stalled`], returned inside the `eyre::Report` so the retry policy can
+/// recognise it with `downcast_ref::<TimedOut>()`.
+#[derive(Debug, Clone)]
+pub struct TimedOut {
+    pub backend: String,
+    pub after: Duration,
+    /// The agent's process group at the time it was stopped.
+    pub killed: Vec<String>,
+    /// What the agent printed before it was killed.
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl std::fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} timed out after {}", self.backend, describe_timeout(self.after))
+    }
+}
+
+impl std::error::Error for TimedOut {}
+
+/// What an agent killed by the timeout or the stall watchdog printed before
+/// it was stopped; `None` for any other error.
+pub fn partial_output(error: &eyre::Report) -> Option<AgentOutput> {
+    let (stdout, stderr) = match (error.downcast_ref::<TimedOut>(), error.downcast_ref::<Stalled>()) {
+        (Some(timed_out), _) => (&timed_out.stdout, &timed_out.stderr),
+        (_, Some(stalled)) => (&stalled.stdout, &stalled.stderr),
+        _ => return None,
+    };
+    Some(AgentOutput {
+        stdout: stdout.clone(),
+        stderr: stderr.clone(),
+        ..AgentOutput::default()
+    })
+}
+
+/// A swappable coding-agent CLI.
+///
+/// A backend only knows how to turn an [`AgentRequest`] into a `Command`. The
+/// spawn / drain / timeout loop lives in [`run_agent`] so every backend runs
+/// inside the same envelope (worktree, protected-path guard, validation, judge).
+pub trait AgentBackend {
+    /// Short name for logs and the banner (matches `llm.backend`).
+    fn name(&self) -> &str;
+
+    /// Build the command for one invocation. Working directory and stdio are
+    /// set by [`run_agent`]; backends only contribute the program and argv.
+    /// [`run_agent`] checks the built command's program is on `PATH`.
+    fn command(&self, request: &AgentRequest) -> Result<Command>;
+}
+
+/// Anthropic's Claude Code: `claude --print --model <m> [--dangerously-skip-permissions] <prompt>`.
+///
+/// Under the `stdin` transport the positional prompt is omitted and `--print`
+/// reads it from stdin.
+///
+/// In `stream-json` mode `--output-format stream-json --verbose` is added
+/// (`--verbose` is mandatory for stream-json under `--print`).
+pub struct ClaudeBackend;
+
+impl AgentBackend for ClaudeBackend {
+    fn name(&self) -> &str {
+        "claude"
+    }
+
+    fn command(&self, request: &AgentRequest) -> Result<Command> {
+        let mut cmd = Command::new("claude");
+        cmd.arg("--print").arg("--model").arg(request.model);
+        if request.output_format == OutputFormat::StreamJson {
+            cmd.arg("--output-format").arg("stream-json").arg("--verbose");
+        }
+        if request.skip_permissions {
+            cmd.arg("--dangerously-skip-permissions");
+        }
+        // With no positional prompt, `--print` reads it from stdin.
+        cmd.args(request.argv_prompt());
+        Ok(cmd)
+    }
+}
+
+/// OpenAI's Codex CLI in non-interactive mode: `codex exec --model <m> <prompt>`.
+///
+/// Under the `stdin` transport the prompt argument is `-`.
+///
+/// `--skip-git-repo-check` is always passed because `isolation: none` may run
+/// outside a git repo; permission bypass maps to
+/// `--dangerously-bypass-approvals-and-sandbox`.
+pub struct CodexBackend;
+
+impl AgentBackend for CodexBackend {
+    fn name(&self) -> &str {
+        "codex"
+    }
+
+    fn command(&self, request: &AgentRequest) -> Result<Command> {
+        let mut cmd = Command::new("codex");
+        cmd.arg("exec")
+            .arg("--model")
+            .arg(request.model)
+            .arg("--skip-git-repo-check");
+        if request.skip_permissions {
+            cmd.arg("--dangerously-bypass-approvals-and-sandbox");
+        }
+        // `-` tells `codex exec` to read the prompt from stdin.
+        cmd.arg(request.argv_prompt().unwrap_or_else(|| "-".to_string()));
+        Ok(cmd)
+    }
+}
+
+/// Aider in one-shot mode: `aider --model <m> --message <prompt>`.
+///
+/// The `file` transport maps to `--message-file`; aider cannot take its
+/// message on stdin.
+///
+/// `--no-auto-commits` is always passed: `rwl` owns the commit cadence (after
+/// the protected-path guard), so aider must not commit behind its back.
+/// Permission bypass maps to `--yes-always`.
+pub struct AiderBackend;
+
+impl AgentBackend for AiderBackend {
+    fn name(&self) -> &str {
+        "aider"
+    }
+
+    fn command(&self, request: &AgentRequest) -> Result<Command> {
+        let mut cmd = Command::new("aider");
+        cmd.arg("--model")
+            .arg(request.model)
+            .arg("--no-auto-commits")
+            .arg("--no-check-update");
+        if request.skip_permissions {
+            cmd.arg("--yes-always");
+        }
+        match request.transport {
+            PromptTransport::Argv => cmd.arg("--message").arg(request.prompt),
+            PromptTransport::File => cmd.arg("--message-file").arg(request.prompt_file),
+            PromptTransport::Stdin => {
+                return Err(eyre::eyre!(
+                    "llm.prompt-transport: stdin is not supported by the aider backend (use file)"
+                ));
+            }
+        };
+        Ok(cmd)
+    }
+}
+
+/// Any CLI agent, driven by the user-supplied `llm.command` argv template.
+///
+/// Each argv element is rendered with the shared [`templates::engine`] in
+/// strict mode, with these variables in scope:
+///
+/// * `model` - `llm.model` (after `--model` overrides)
+/// * `prompt` - the fully rendered prompt text
+/// * `prompt_file` - path to a file holding the same prompt (always written)
+/// * `work_dir` - the directory the agent runs in (worktree or CWD)
+/// * `skip_permissions` - `llm.dangerously_skip_permissions`, for `{{#if}}`
+///
+/// e.g. `["my-agent", "--model", "{{model}}", "--prompt-file", "{{prompt_file}}"]`.
+/// Strict mode turns a typo such as `{{promt}}` into an error instead of an
+/// empty argument.
+pub struct CommandBackend {
+    argv: Vec<String>,
+    handlebars: Handlebars<'static>,
+}
+
+impl CommandBackend {
+    /// Compile the argv template. Fails on an empty argv or any element that
+    /// does not parse, so a broken `llm.command` is reported before the loop.
+    pub fn new(argv: Vec<String>) -> Result<Self> {
+        if argv.is_empty() || argv[0].trim().is_empty() {
+            return Err(eyre::eyre!("llm.command is empty; it must name the agent program"));
+        }
+
+        let mut handlebars = templates::engine();
+        handlebars.set_strict_mode(true);
+        for (i, arg) in argv.iter().enumerate() {
+            handlebars
+                .register_template_string(&i.to_string(), arg)
+                .with_context(|| format!("Invalid llm.command template at argument {}: {:?}", i, arg))?;
+        }
+
+        Ok(Self { argv, handlebars })
+    }
+
+    /// Render every argv element for one request.
+    fn render(&self, request: &AgentRequest) -> Result<Vec<String>> {
+        let mut data = HashMap::new();
+        data.insert("model", serde_json::Value::from(request.model));
+        data.insert("prompt", serde_json::Value::from(request.prompt));
+        data.insert(
+            "prompt_file",
+            serde_json::Value::from(request.prompt_file.display().to_string()),
+        );
+        data.insert(
+            "work_dir",
+            serde_json::Value::from(request.work_dir.display().to_string()),
+        );
+        data.insert("skip_permissions", serde_json::Value::from(request.skip_permissions));
+
+        (0..self.argv.len())
+            .map(|i| {
+                self.handlebars
+                    .render(&i.to_string(), &data)
+                    .with_context(|| format!("Failed to render llm.command argument {:?}", self.argv[i]))
+            })
+            .collect()
+    }
+}
+
+impl AgentBackend for CommandBackend {
+    fn name(&self) -> &str {
+        "command"
+    }
+
+    fn command(&self, request: &AgentRequest) -> Result<Command> {
+        let rendered = self.render(request)?;
+        log::debug!("CommandBackend::command: argv={:?}", rendered);
+        let mut cmd = Command::new(&rendered[0]);
+        cmd.args(&rendered[1..]);
+        Ok(cmd)
+    }
+}
+
+/// Resolve the configured `llm.backend` into a backend instance.
+///
+/// A non-empty `llm.command` selects the command backend on its own (the
+/// built-in backends never read it), so `backend: command` is optional.
+pub fn backend_for(llm: &LlmConfig) -> Result<Box<dyn AgentBackend>> {
+    log::debug!(
+        "backend_for: backend={:?} model={} command_len={}",
+        llm.backend,
+        llm.model,
+        llm.command.len()
+    );
+    if llm.output_format == OutputFormat::StreamJson && (llm.backend != Backend::Claude || !llm.command.is_empty()) {
+        return Err(eyre::eyre!(
+            "llm.output-format: stream-json is only supported by the claude backend"
+        ));
+    }
+    if llm.prompt_transport == PromptTransport::Stdin && llm.backend == Backend::Aider && llm.command.is_empty() {
+        return Err(eyre::eyre!(
+            "llm.prompt-transport: stdin is not supported by the aider backend (use file)"
+        ));
+    }
+    if !llm.command.is_empty() {
+        if !matches!(llm.backend, Backend::Claude | Backend::Command) {
+            log::warn!(
+                "backend_for: llm.command is set, ignoring llm.backend={:?}",
+                llm.backend
+            );
+        }
+        return Ok(Box::new(CommandBackend::new(llm.command.clone())?));
+    }
+    Ok(match llm.backend {
+        Backend::Claude => Box::new(ClaudeBackend),
+        Backend::Codex => Box::new(CodexBackend),
+        Backend::Aider => Box::new(AiderBackend),
+        Backend::Command => return Err(eyre::eyre!("llm.backend is 'command' but llm.command is empty")),
+    })
+}
+
+/// Human-readable timeout for error messages ("10 minutes", "90 seconds").
+fn describe_timeout(timeout: Duration) -> String {
+    let secs = timeout.as_secs();
+    if secs > 0 && secs.is_multiple_of(60) {
+        format!("{} minutes", secs / 60)
+    } else {
+        format!("{} seconds", secs)
+    }
+}
+
+/// Spawn the agent in `work_dir`, drain stdout/stderr, and enforce `timeout`.
+///
+/// stdout and stderr are drained concurrently on background threads so a full
+/// pipe buffer can never deadlock the child. When `echo` is set each line is
+/// also printed dimmed as it arrives (the live loop UX); the judge runs quietly.
+/// A timeout kills the child and returns `Err` downcastable to [`TimedOut`].
+///
+/// Every line either reader sees (stream-json events included) counts as
+/// activity. With `stall_timeout` set, a child silent for that long is killed
+/// and the returned error downcasts to [`Stalled`].
+///
+/// The agent runs in its own process group. Every way out - timeout, stall,
+/// cancel, or a normal exit that leaves children behind - stops the whole
+/// group: SIGTERM, `grace`, then SIGKILL.
+pub fn run_agent(
+    backend: &dyn AgentBackend,
+    request: &AgentRequest,
+    limits: &Limits,
+    echo: bool,
+) -> Result<AgentOutput> {
+    log::debug!(
+        "run_agent: backend={} model={} prompt_len={} work_dir={} timeout_secs={} stall_timeout_secs={:?}",
+        backend.name(),
+        request.model,
+        request.prompt.len(),
+        request.work_dir.display(),
+        limits.timeout.as_secs(),
+        limits.stall_timeout.map(|d| d.as_secs())
+    );
+
+    // The prompt file is written for every transport: it is cheap, and it
+    // doubles as a record of exactly what the agent was sent.
+    if let Some(dir) = request.prompt_file.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    std::fs::write(request.prompt_file, request.prompt)
+        .with_context(|| format!("Failed to write prompt file {}", request.prompt_file.display()))?;
+
+    let via_stdin = request.transport == PromptTransport::Stdin;
+    let mut cmd = backend.command(request)?;
+    // Checked on the built command, so a templated `llm.command` program
+    // (`{{work_dir}}/bin/agent`) is looked up as rendered.
+    let program = cmd.get_program().to_owned();
+    which::which_in(&program, std::env::var_os("PATH"), request.work_dir).with_context(|| {
+        format!(
+            "{} not found on PATH (required by llm.backend: {})",
+            program.to_string_lossy(),
+            backend.name()
+        )
+    })?;
+    process::isolate_group(&mut cmd);
+    cmd.current_dir(request.work_dir)
+        .stdin(if via_stdin { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("Failed to spawn {} command", backend.name()))?;
+    let pgid = child.id();
+
+    // Feed the prompt on its own thread so a prompt larger than the pipe
+    // buffer cannot deadlock against an agent that is busy writing output.
+    // Dropping stdin afterwards signals EOF.
+    if let Some(mut stdin) = child.stdin.take() {
+        let prompt = request.prompt.to_string();
+        std::thread::spawn(move || {
+            if let Err(e) = stdin.write_all(prompt.as_bytes()) {
+                log::warn!("run_agent: failed to write prompt to stdin: {}", e);
+            }
+        });
+    }
+
+    // Last time either reader saw a line; the poll loop's stall watchdog.
+    let last_activity = Arc::new(Mutex::new(Instant::now()));
+
+    // In stream-json mode stdout is JSONL: the reader reconstructs the
+    // assistant prose so both the live echo and the promise scan see text, and
+    // lifts the spend out of the terminal `result` event.
+    let stream_json = request.output_format == OutputFormat::StreamJson;
+    let child_stdout = child.stdout.take();
+    let stdout_activity = Arc::clone(&last_activity);
+    let stdout_handle = std::thread::spawn(move || {
+        let mut captured = String::new();
+        let mut usage = None;
+        if let Some(stdout) = child_stdout {
+            let reader = BufReader::new(stdout);
+            for line in reader.lines() {
+                let Ok(line) = line else { break };
+                touch(&stdout_activity);
+                let text = if stream_json {
+                    match usage::parse_stream_line(&line) {
+                        StreamLine::Text(text) | StreamLine::Raw(text) => text,
+                        StreamLine::Result(reported) => {
+                            usage = Some(reported);
+                            continue;
+                        }
+                        StreamLine::Event => continue,
+                    }
+                } else {
+                    line
+                };
+                for line in text.lines() {
+                    if echo {
+                        println!("  {}", line.dimmed());
+                    }
+                    captured.push_str(line);
+                    captured.push('\n');
+                }
+            }
+        }
+        (captured, usage)
+    });
+
+    let child_stderr = child.stderr.take();
+    let stderr_activity = Arc::clone(&last_activity);
+    let stderr_handle = std::thread::spawn(move || {
+        let mut captured = String::new();
+        if let Some(stderr) = child_stderr {
+            let reader = BufReader::new(stderr);
+            for line in reader.lines() {
+                match line {
+                    Ok(line) => {
+                        touch(&stderr_activity);
+                        if echo {
+                            eprintln!("  {}", line.dimmed());
+                        }
+                        captured.push_str(&line);
+                        captured.push('\n');
+                    }
+                    Err(_) => break,
+                }
+            }
+        }
+        captured
+    });
+
+    let start = Instant::now();
+    loop {
+        let status = child
+            .try_wait()
+            .with_context(|| format!("Failed to check {} process status", backend.name()))?;
+
+        let cancelled = status.is_none() && limits.cancel.is_some_and(|c| c.load(Ordering::SeqCst));
+        if status.is_some() || cancelled {
+            // Stop whatever is still running in the group before joining the
+            // readers: a leftover holding the pipes open would block them.
+            let reaped = if cancelled {
+                log::warn!(
+                    "run_agent: backend={} cancelled, stopping process group",
+                    backend.name()
+                );
+                process::terminate_group(&mut child, limits.grace)
+            } else {
+                process::reap_leftovers(pgid, limits.grace)
+            };
+            let exit_code = status.and_then(|s| s.code());
+            let (stdout, usage) = stdout_handle.join().unwrap_or_default();
+            let stderr = stderr_handle.join().unwrap_or_default();
+            if stream_json && usage.is_none() && !cancelled {
+                log::warn!(
+                    "run_agent: backend={} stream-json run ended without a result event; usage not captured",
+                    backend.name()
+                );
+            }
+            log::debug!(
+                "run_agent: backend={} exited code={:?} cancelled={} reaped={} stdout_len={} stderr_len={}",
+                backend.name(),
+                exit_code,
+                cancelled,
+                reaped.len(),
+                stdout.len(),
+                stderr.len()
+            );
+            return Ok(AgentOutput {
+                stdout,
+                stderr,
+                exit_code,
+                usage,
+                reaped,
+                cancelled,
+            });
+        }
+
+        if start.elapsed() >= limits.timeout {
+            log::warn!("run_agent: backend={} timed out", backend.name());
+            let killed = process::terminate_group(&mut child, limits.grace);
+            // The group is gone and the pipes with it: keep what it printed.
+            let (stdout, _) = stdout_handle.join().unwrap_or_default();
+            let stderr = stderr_handle.join().unwrap_or_default();
+            return Err(TimedOut {
+                backend: backend.name().to_string(),
+                after: limits.timeout,
+                killed,
+                stdout,
+                stderr,
+            }
+            .into());
+        }
+        if let Some(stall_timeout) = limits.stall_timeout {
+            let idle = last_activity.lock().map(|t| t.elapsed()).unwrap_or_default();
+            if idle >= stall_timeout {
+                log::warn!(
+                    "run_agent: backend={} stalled idle_secs={}",
+                    backend.name(),
+                    idle.as_secs()
+                );
+                let killed = process::terminate_group(&mut child, limits.grace);
+                let (stdout, _) = stdout_handle.join().unwrap_or_default();
+                let stderr = stderr_handle.join().unwrap_or_default();
+                return Err(Stalled {
+                    backend: backend.name().to_string(),
+                    idle: stall_timeout,
+                    killed,
+                    stdout,
+                    stderr,
+                }
+                .into());
+            }
+        }
+        std::thread::sleep(Duration::from_millis(POLL_INTERVAL_MS));
+    }
+}
+
+/// Record reader activity for the stall watchdog.
+fn touch(last_activity: &Mutex<Instant>) {
+    if let Ok(mut last) = last_activity.lock() {
+        *last = Instant::now();
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn request<'a>(prompt: &'a str, dir: &'a Path, prompt_file: &'a Path, skip_permissions: bool) -> AgentRequest<'a> {
+        AgentRequest {
+            model: "sonnet",
+            prompt,
+            work_dir: dir,
+            prompt_file,
+            skip_permissions,
+            output_format: OutputFormat::Text,
+            transport: PromptTransport::Argv,
+        }
+    }
+
+    /// A request for the pure argv-building tests, which never touch disk.
+    fn dry_request(prompt: &str, skip_permissions: bool) -> AgentRequest<'_> {
+        request(
+            prompt,
+            Path::new("/repo"),
+            Path::new("/session/prompt.md"),
+            skip_permissions,
+        )
+    }
+
+    fn argv(cmd: &Command) -> Vec<String> {
+        std::iter::once(cmd.get_program())
+            .chain(cmd.get_args())
+            .map(|a| a.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_claude_command_with_bypass() {
+        let cmd = ClaudeBackend.command(&dry_request("do it", true)).unwrap();
+        assert_eq!(
+            argv(&cmd),
+            vec![
+                "claude",
+                "--print",
+                "--model",
+                "sonnet",
+                "--dangerously-skip-permissions",
+                "do it"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_claude_command_without_bypass() {
+        let cmd = ClaudeBackend.command(&dry_request("do it", false)).unwrap();
+        assert!(!argv(&cmd).contains(&"--dangerously-skip-permissions".to_string()));
+    }
+
+    #[test]
+    fn test_claude_command_stream_json() {
+        let request = AgentRequest {
+            output_format: OutputFormat::StreamJson,
+            ..dry_request("do it", false)
+        };
+        let args = argv(&ClaudeBackend.command(&request).unwrap());
+        assert!(
+            args.windows(3)
+                .any(|w| w == ["--output-format", "stream-json", "--verbose"])
+        );
+    }
+
+    #[test]
+    fn test_codex_command() {
+        let cmd = CodexBackend.command(&dry_request("do it", true)).unwrap();
+        let args = argv(&cmd);
+        assert_eq!(&args[..2], &["codex", "exec"]);
+        assert!(args.contains(&"--dangerously-bypass-approvals-and-sandbox".to_string()));
+        assert_eq!(args.last().unwrap(), "do it");
+    }
+
+    #[test]
+    fn test_aider_command_never_auto_commits() {
+        let cmd = AiderBackend.command(&dry_request("do it", false)).unwrap();
+        let args = argv(&cmd);
+        assert!(args.contains(&"--no-auto-commits".to_string()));
+        assert!(!args.contains(&"--yes-always".to_string()));
+        assert_eq!(&args[args.len() - 2..], &["--message", "do it"]);
+    }
+
+    #[test]
+    fn test_command_backend_renders_template() {
+        let dir = tempdir().unwrap();
+        let prompt_file = dir.path().join("prompt.md");
+        let backend = CommandBackend::new(vec![
+            "my-agent".to_string(),
+            "--model".to_string(),
+            "{{model}}".to_string(),
+            "{{prompt}}".to_string(),
+        ])
+        .unwrap();
+        let cmd = backend
+            .command(&request(
+                "emit <promise>COMPLETE</promise> & exit",
+                dir.path(),
+                &prompt_file,
+                true,
+            ))
+            .unwrap();
+        assert_eq!(
+            argv(&cmd),
+            vec![
+                "my-agent",
+                "--model",
+                "sonnet",
+                "emit <promise>COMPLETE</promise> & exit"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_command_backend_writes_prompt_file() {
+        let dir = tempdir().unwrap();
+        let prompt_file = dir.path().join("prompt.md");
+        let backend = CommandBackend::new(vec![
+            "my-agent".to_string(),
+            "--prompt-file".to_string(),
+            "{{prompt_file}}".to_string(),
+            "--cwd={{work_dir}}".to_string(),
+            "{{#if skip_permissions}}--yolo{{/if}}".to_string(),
+        ])
+        .unwrap();
+        let cmd = backend
+            .command(&request("the prompt", dir.path(), &prompt_file, true))
+            .unwrap();
+        let args = argv(&cmd);
+        assert_eq!(args[2], prompt_file.display().to_string());
+        assert_eq!(args[3], format!("--cwd={}", dir.path().display()));
+        assert_eq!(args[4], "--yolo");
+    }
+
+    #[test]
+    fn test_stdin_transport_omits_positional_prompt() {
+        let request = AgentRequest {
+            transport: PromptTransport::Stdin,
+            ..dry_request("do it", false)
+        };
+        let claude = argv(&ClaudeBackend.command(&request).unwrap());
+        assert!(!claude.contains(&"do it".to_string()));
+        assert_eq!(claude.last().unwrap(), "sonnet");
+        let codex = argv(&CodexBackend.command(&request).unwrap());
+        assert_eq!(codex.last().unwrap(), "-");
+        assert!(AiderBackend.command(&request).is_err());
+    }
+
+    #[test]
+    fn test_file_transport_points_at_prompt_file() {
+        let request = AgentRequest {
+            transport: PromptTransport::File,
+            ..dry_request("a very long prompt", false)
+        };
+        let claude = argv(&ClaudeBackend.command(&request).unwrap());
+        let pointer = claude.last().unwrap();
+        assert!(pointer.contains("/session/prompt.md"));
+        assert!(!pointer.contains("a very long prompt"));
+        let aider = argv(&AiderBackend.command(&request).unwrap());
+        assert_eq!(&aider[aider.len() - 2..], &["--message-file", "/session/prompt.md"]);
+    }
+
+    #[test]
+    fn test_backend_for_rejects_aider_over_stdin() {
+        let llm = LlmConfig {
+            backend: Backend::Aider,
+            prompt_transport: PromptTransport::Stdin,
+            ..LlmConfig::default()
+        };
+        assert!(backend_for(&llm).is_err());
+    }
+
+    #[test]
+    fn test_run_agent_pipes_prompt_on_stdin_and_keeps_artifact() {
+        let dir = tempdir().unwrap();
+        let backend = CommandBackend::new(vec!["cat".to_string()]).unwrap();
+        let prompt_file = dir.path().join("iterations").join("001").join("prompt.md");
+        let request = AgentRequest {
+            transport: PromptTransport::Stdin,
+            ..request("line one\nline two", dir.path(), &prompt_file, false)
+        };
+        let output = run_agent(&backend, &request, &Limits::new(Duration::from_secs(30)), false).unwrap();
+        assert_eq!(output.stdout, "line one\nline two\n");
+        assert_eq!(fs::read_to_string(&prompt_file).unwrap(), "line one\nline two");
+    }
+
+    #[test]
+    fn test_command_backend_strict_rejects_unknown_variable() {
+        let dir = tempdir().unwrap();
+        let prompt_file = dir.path().join("prompt.md");
+        let backend = CommandBackend::new(vec!["my-agent".to_string(), "{{promt}}".to_string()]).unwrap();
+        let err = backend
+            .command(&request("p", dir.path(), &prompt_file, false))
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("{{promt}}"));
+    }
+
+    #[test]
+    fn test_command_backend_rejects_unparsable_template() {
+        let err = CommandBackend::new(vec!["my-agent".to_string(), "{{#if model}}".to_string()])
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("argument 1"));
+    }
+
+    #[test]
+    fn test_command_backend_rejects_empty_argv() {
+        assert!(CommandBackend::new(Vec::new()).is_err());
+        assert!(CommandBackend::new(vec![" ".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_backend_for_defaults_to_claude() {
+        let backend = backend_for(&LlmConfig::default()).unwrap();
+        assert_eq!(backend.name(), "claude");
+    }
+
+    #[test]
+    fn test_backend_for_command_set_selects_command_backend() {
+        let llm = LlmConfig {
+            command: vec!["my-agent".to_string(), "{{prompt}}".to_string()],
+            ..LlmConfig::default()
+        };
+        let backend = backend_for(&llm).unwrap();
+        assert_eq!(backend.name(), "command");
+    }
+
+    #[test]
+    fn test_backend_for_command_without_argv_errors() {
+        let llm = LlmConfig {
+            backend: Backend::Command,
+            ..LlmConfig::default()
+        };
+        assert!(backend_for(&llm).is_err());
+    }
+
+    #[test]
+    fn test_run_agent_captures_output() {
+        let dir = tempdir().unwrap();
+        let backend = CommandBackend::new(vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "echo {{prompt}}; echo oops >&2; exit 3".to_string(),
+        ])
+        .unwrap();
+        let prompt_file = dir.path().join("prompt.md");
+        let output = run_agent(
+            &backend,
+            &request("hello", dir.path(), &prompt_file, false),
+            &Limits::new(Duration::from_secs(30)),
+            false,
+        )
+        .unwrap();
+        assert_eq!(output.stdout, "hello\n");
+        assert_eq!(output.stderr, "oops\n");
+        assert_eq!(output.exit_code, Some(3));
+        assert!(output.combined().contains("hello"));
+    }
+
+    #[test]
+    fn test_run_agent_resolves_templated_program() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let script = dir.path().join("agent.sh");
+        fs::write(&script, "#!/bin/sh\necho ran\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        let backend = CommandBackend::new(vec!["{{work_dir}}/agent.sh".to_string()]).unwrap();
+        let prompt_file = dir.path().join("prompt.md");
+        let output = run_agent(
+            &backend,
+            &request("hello", dir.path(), &prompt_file, false),
+            &Limits::new(Duration::from_secs(30)),
+            false,
+        )
+        .unwrap();
+        assert_eq!(output.stdout, "ran\n");
+
+        let missing = CommandBackend::new(vec!["{{work_dir}}/missing.sh".to_string()]).unwrap();
+        let err = run_agent(
+            &missing,
+            &request("hello", dir.path(), &prompt_file, false),
+            &Limits::new(Duration::from_secs(30)),
+            false,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("missing.sh not found on PATH"), "{}", err);
+    }
+
+    #[test]
+    fn test_backend_for_stream_json_requires_claude() {
+        let llm = LlmConfig {
+            backend: Backend::Codex,
+            output_format: OutputFormat::StreamJson,
+            ..LlmConfig::default()
+        };
+        assert!(backend_for(&llm).is_err());
+    }
+
+    #[test]
+    fn test_run_agent_stream_json_reconstructs_text_and_usage() {
+        let dir = tempdir().unwrap();
+        let script = dir.path().join("events.jsonl");
+        fs::write(
+            &script,
+            concat!(
+                "Sandbox disabled\n",
+                r#"{"type":"system","subtype":"init"}"#,
+                "\n",
+                r#"{"type":"assistant","message":{"content":[{"type":"text","text":"done\n<promise>COMPLETE</promise>"}]}}"#,
+                "\n",
+                r#"{"type":"result","total_cost_usd":0.5,"usage":{"input_tokens":7,"output_tokens":3}}"#,
+                "\n",
+            ),
+        )
+        .unwrap();
+        let backend = CommandBackend::new(vec!["cat".to_string(), script.display().to_string()]).unwrap();
+        let prompt_file = dir.path().join("prompt.md");
+        let request = AgentRequest {
+            output_format: OutputFormat::StreamJson,
+            ..request("", dir.path(), &prompt_file, false)
+        };
+        let output = run_agent(&backend, &request, &Limits::new(Duration::from_secs(30)), false).unwrap();
+        assert_eq!(output.stdout, "Sandbox disabled\ndone\n<promise>COMPLETE</promise>\n");
+        let usage = output.usage.unwrap();
+        assert_eq!(usage.cost_usd, 0.5);
+        assert_eq!(usage.total_tokens(), 10);
+    }
+
+    #[test]
+    fn test_run_agent_times_out() {
+        let dir = tempdir().unwrap();
+        let backend = CommandBackend::new(vec!["sleep".to_string(), "5".to_string()]).unwrap();
+        let prompt_file = dir.path().join("prompt.md");
+        let err = run_agent(
+            &backend,
+            &request("", dir.path(), &prompt_file, false),
+            &Limits::new(Duration::from_secs(1)),
+            false,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("timed out after 1 seconds"));
+        assert!(err.downcast_ref::<TimedOut>().is_some());
+        assert!(err.downcast_ref::<Stalled>().is_none());
+    }
+
+    #[test]
+    fn test_run_agent_keeps_output_of_killed_agent() {
+        let dir = tempdir().unwrap();
+        let backend = CommandBackend::new(vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "echo half done; echo warning >&2; while :; do sleep 0.2; done".to_string(),
+        ])
+        .unwrap();
+        let prompt_file = dir.path().join("prompt.md");
+        let err = run_agent(
+            &backend,
+            &request("", dir.path(), &prompt_file, false),
+            &Limits::new(Duration::from_secs(1)),
+            false,
+        )
+        .unwrap_err();
+        let output = partial_output(&err).unwrap();
+        assert_eq!(output.stdout, "half done\n");
+        assert_eq!(output.stderr, "warning\n");
+        assert_eq!(output.exit_code, None);
+    }
+
+    #[test]
+    fn test_run_agent_kills_stalled_agent() {
+        let dir = tempdir().unwrap();
+        let backend = CommandBackend::new(vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "echo working; exec sleep 30".to_string(),
+        ])
+        .unwrap();
+        let prompt_file = dir.path().join("prompt.md");
+        let started = Instant::now();
+        let err = run_agent(
+            &backend,
+            &request("", dir.path(), &prompt_file, false),
+            &Limits {
+                stall_timeout: Some(Duration::from_secs(1)),
+                ..Limits::new(Duration::from_secs(30))
+            },
+            false,
+        )
+        .unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(10));
+        let stalled = err.downcast_ref::<Stalled>().unwrap();
+        assert_eq!(stalled.idle, Duration::from_secs(1));
+        assert_eq!(stalled.stdout, "working\n");
+        assert!(err.to_string().contains("stalled: no output for 1 seconds"));
+    }
+
+    #[test]
+    fn test_run_agent_steady_output_is_not_a_stall() {
+        let dir = tempdir().unwrap();
+        let backend = CommandBackend::new(vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "for i in 1 2 3 4; do echo tick >&2; sleep 0.5; done".to_string(),
+        ])
+        .unwrap();
+        let prompt_file = dir.path().join("prompt.md");
+        let output = run_agent(
+            &backend,
+            &request("", dir.path(), &prompt_file, false),
+            &Limits {
+                stall_timeout: Some(Duration::from_secs(1)),
+                ..Limits::new(Duration::from_secs(30))
+            },
+            false,
+        )
+        .unwrap();
+        assert_eq!(output.stderr.lines().count(), 4);
+    }
+
+    #[test]
+    fn test_run_agent_reaps_leftover_background_process() {
+        let dir = tempdir().unwrap();
+        let backend = CommandBackend::new(vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "sleep 30 & echo started".to_string(),
+        ])
+        .unwrap();
+        let prompt_file = dir.path().join("prompt.md");
+        let started = Instant::now();
+        let output = run_agent(
+            &backend,
+            &request("", dir.path(), &prompt_file, false),
+            &Limits::new(Duration::from_secs(30)),
+            false,
+        )
+        .unwrap();
+        // The background sleep holds stdout open; without the group kill the
+        // reader join would wait for it.
+        assert!(started.elapsed() < Duration::from_secs(15));
+        assert_eq!(output.stdout, "started\n");
+        assert_eq!(output.reaped.len(), 1, "reaped: {:?}", output.reaped);
+        assert!(output.reaped[0].contains("sleep 30"));
+        assert!(!output.cancelled);
+    }
+
+    #[test]
+    fn test_run_agent_cancel_stops_the_group() {
+        let dir = tempdir().unwrap();
+        let backend = CommandBackend::new(vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "echo working; sleep 30".to_string(),
+        ])
+        .unwrap();
+        let prompt_file = dir.path().join("prompt.md");
+        let cancel = AtomicBool::new(true);
+        let output = run_agent(
+            &backend,
+            &request("", dir.path(), &prompt_file, false),
+            &Limits {
+                cancel: Some(&cancel),
+                ..Limits::new(Duration::from_secs(30))
+            },
+            false,
+        )
+        .unwrap();
+        assert!(output.cancelled);
+        assert_eq!(output.exit_code, None);
+        assert!(!output.reaped.is_empty());
+    }
+
+    #[test]
+    fn test_describe_timeout() {
+        assert_eq!(describe_timeout(Duration::from_secs(600)), "10 minutes");
+        assert_eq!(describe_timeout(Duration::from_secs(90)), "90 seconds");
+    }
+}
@@ -0,0 +1,253 @@
+use crate::agent::AgentOutput;
+use chrono::{DateTime, Utc};
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Session subdirectory holding one directory per iteration.
+pub const ITERATIONS_DIR: &str = "iterations";
+
+/// Timings and exit codes for one iteration, written as `meta.json`.
+///
+/// Rewritten at each step of the iteration, so it reflects how far the
+/// iteration got even when the run is stopped or fails part-way.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IterationMeta {
+    pub iteration: u32,
+    pub model: String,
+    pub started: String,
+    pub finished: String,
+    pub duration_secs: u64,
+    /// Times the agent ran (more than 1 when `llm.retry` or a rate-limit
+    /// pause re-ran it).
+    pub agent_attempts: u32,
+    /// Exit code of the final attempt; `None` when killed by a signal or the
+    /// watchdog.
+    pub agent_exit_code: Option<i32>,
+    pub agent_secs: u64,
+    #[serde(default)]
+    pub stalled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_exit_code: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_passed: Option<bool>,
+    #[serde(default)]
+    pub validation_secs: u64,
+    #[serde(default)]
+    pub promise_found: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub quality_gates: Vec<GateMeta>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub judge_passed: Option<bool>,
+}
+
+/// One quality gate's verdict in [`IterationMeta`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GateMeta {
+    pub name: String,
+    pub passed: bool,
+    /// The gate's output file, relative to the iteration directory.
+    pub log: String,
+}
+
+/// The artifact directory of one iteration: `session_dir/iterations/NNN/`.
+///
+/// Holds `prompt.md`, `agent.stdout`, `agent.stderr`, `validation.log`, one
+/// `gate-<name>.log` per quality gate, `judge-prompt.md` and `judge.log`,
+/// `diff.patch` and `meta.json`, so a post-mortem can go straight to one
+/// iteration instead of searching the interleaved `session.log`.
+#[derive(Debug, Clone)]
+pub struct IterationArtifacts {
+    dir: PathBuf,
+    started: DateTime<Utc>,
+    meta: IterationMeta,
+}
+
+impl IterationArtifacts {
+    pub fn new(session_dir: &Path, iteration: u32, model: &str) -> Self {
+        let started = Utc::now();
+        Self {
+            dir: Self::dir_for(session_dir, iteration),
+            started,
+            meta: IterationMeta {
+                iteration,
+                model: model.to_string(),
+                started: started.to_rfc3339(),
+                ..IterationMeta::default()
+            },
+        }
+    }
+
+    pub fn dir_for(session_dir: &Path, iteration: u32) -> PathBuf {
+        session_dir.join(ITERATIONS_DIR).join(format!("{:03}", iteration))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    /// Path of `name` relative to the session dir, for pointers in `session.log`.
+    pub fn display(&self, name: &str) -> String {
+        format!("{}/{:03}/{}", ITERATIONS_DIR, self.meta.iteration, name)
+    }
+
+    pub fn meta(&mut self) -> &mut IterationMeta {
+        &mut self.meta
+    }
+
+    pub fn write(&self, name: &str, contents: &str) -> Result<()> {
+        fs::create_dir_all(&self.dir).context("Failed to create iteration directory")?;
+        fs::write(self.path(name), contents).with_context(|| format!("Failed to write {}", self.display(name)))
+    }
+
+    /// Write one agent attempt's stdout and stderr. The attempt that counts is
+    /// `agent.stdout` / `agent.stderr`; runs that were re-run (under
+    /// `llm.retry` or after a rate-limit pause) are kept alongside as
+    /// `agent.attempt-N.stdout` / `.stderr`, N numbering every run.
+    pub fn write_agent_output(&self, output: &AgentOutput, retried_attempt: Option<u32>) -> Result<String> {
+        let stem = match retried_attempt {
+            Some(attempt) => format!("agent.attempt-{}", attempt),
+            None => "agent".to_string(),
+        };
+        let stdout = format!("{}.stdout", stem);
+        self.write(&stdout, &output.stdout)?;
+        self.write(&format!("{}.stderr", stem), &output.stderr)?;
+        Ok(self.display(&stdout))
+    }
+
+    /// File name for a quality gate's output: `gate-<name>.log`, with the name
+    /// reduced to `[a-z0-9-]`.
+    pub fn gate_file(name: &str) -> String {
+        let mut slug = String::with_capacity(name.len());
+        for c in name.to_lowercase().chars() {
+            if c.is_ascii_alphanumeric() {
+                slug.push(c);
+            } else if !slug.ends_with('-') {
+                slug.push('-');
+            }
+        }
+        let slug = slug.trim_matches('-');
+        format!("gate-{}.log", if slug.is_empty() { "unnamed" } else { slug })
+    }
+
+    /// Stamp the finish time and (re)write `meta.json`.
+    pub fn write_meta(&mut self) -> Result<()> {
+        let finished = Utc::now();
+        self.meta.finished = finished.to_rfc3339();
+        self.meta.duration_secs = finished.signed_duration_since(self.started).num_seconds().max(0) as u64;
+        let json = serde_json::to_string_pretty(&self.meta)?;
+        self.write("meta.json", &json)
+    }
+
+    /// The `meta.json` of every iteration recorded in `session_dir`, in
+    /// iteration order. Directories without a readable `meta.json` are skipped.
+    pub fn read_all(session_dir: &Path) -> Result<Vec<IterationMeta>> {
+        let dir = session_dir.join(ITERATIONS_DIR);
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut metas = Vec::new();
+        for entry in fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+            let path = entry?.path().join("meta.json");
+            match fs::read_to_string(&path)
+                .map_err(eyre::Report::from)
+                .and_then(|json| serde_json::from_str::<IterationMeta>(&json).map_err(eyre::Report::from))
+            {
+                Ok(meta) => metas.push(meta),
+                Err(e) => log::debug!("read_all: skipping path={} error={}", path.display(), e),
+            }
+        }
+        metas.sort_by_key(|meta| meta.iteration);
+        Ok(metas)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_dir_is_zero_padded_under_iterations() {
+        let dir = IterationArtifacts::dir_for(Path::new("/s"), 7);
+        assert_eq!(dir, Path::new("/s/iterations/007"));
+        let artifacts = IterationArtifacts::new(Path::new("/s"), 12, "opus");
+        assert_eq!(artifacts.display("diff.patch"), "iterations/012/diff.patch");
+    }
+
+    #[test]
+    fn test_gate_file_slugs_the_name() {
+        assert_eq!(IterationArtifacts::gate_file("clippy"), "gate-clippy.log");
+        assert_eq!(
+            IterationArtifacts::gate_file("Unit Tests (fast)"),
+            "gate-unit-tests-fast.log"
+        );
+        assert_eq!(IterationArtifacts::gate_file("../x"), "gate-x.log");
+        assert_eq!(IterationArtifacts::gate_file("!!"), "gate-unnamed.log");
+    }
+
+    #[test]
+    fn test_agent_output_files_per_attempt() {
+        let session = tempdir().unwrap();
+        let artifacts = IterationArtifacts::new(session.path(), 1, "opus");
+        let output = AgentOutput {
+            stdout: "out".to_string(),
+            stderr: "err".to_string(),
+            ..AgentOutput::default()
+        };
+        artifacts.write_agent_output(&output, Some(1)).unwrap();
+        let shown = artifacts.write_agent_output(&output, None).unwrap();
+        assert_eq!(shown, "iterations/001/agent.stdout");
+        for name in [
+            "agent.attempt-1.stdout",
+            "agent.attempt-1.stderr",
+            "agent.stdout",
+            "agent.stderr",
+        ] {
+            assert!(artifacts.path(name).exists(), "{} missing", name);
+        }
+        assert_eq!(fs::read_to_string(artifacts.path("agent.stderr")).unwrap(), "err");
+    }
+
+    #[test]
+    fn test_write_meta_records_progress() {
+        let session = tempdir().unwrap();
+        let mut artifacts = IterationArtifacts::new(session.path(), 3, "sonnet");
+        artifacts.meta().agent_attempts = 2;
+        artifacts.meta().agent_exit_code = Some(0);
+        artifacts.meta().validation_passed = Some(false);
+        artifacts.write_meta().unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(artifacts.path("meta.json")).unwrap()).unwrap();
+        assert_eq!(json["iteration"], 3);
+        assert_eq!(json["model"], "sonnet");
+        assert_eq!(json["agent_attempts"], 2);
+        assert_eq!(json["agent_exit_code"], 0);
+        assert_eq!(json["validation_passed"], false);
+        assert!(json.get("judge_passed").is_none());
+        assert!(!json["finished"].as_str().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_read_all_in_iteration_order() {
+        let session = tempdir().unwrap();
+        assert!(IterationArtifacts::read_all(session.path()).unwrap().is_empty());
+        for iteration in [2, 10, 1] {
+            IterationArtifacts::new(session.path(), iteration, "opus")
+                .write_meta()
+                .unwrap();
+        }
+        fs::create_dir_all(IterationArtifacts::dir_for(session.path(), 11)).unwrap();
+
+        let metas = IterationArtifacts::read_all(session.path()).unwrap();
+        let iterations: Vec<u32> = metas.iter().map(|m| m.iteration).collect();
+        assert_eq!(iterations, vec![1, 2, 10]);
+    }
+}
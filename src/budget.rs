@@ -1,3 +1,5 @@
+use crate::config::BudgetConfig;
+use crate::usage::Usage;
 use std::time::{Duration, Instant};
 
 const SECS_PER_MINUTE: u64 = 60;
@@ -8,75 +10,128 @@ const SECS_PER_MINUTE: u64 = 60;
 /// in production; documented in the design's implementation notes.
 const PREAGE_ENV: &str = "RWL_BUDGET_PREAGE_SECS";
 
-/// Why the wall-clock budget was exceeded.
+/// Why the budget was exceeded: which cap tripped, and by how much.
 ///
-/// Carries both the elapsed minutes and the configured cap so the surfaced
-/// message is machine- and human-readable.
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Reason {
-    /// Whole minutes elapsed when the cap tripped.
-    pub elapsed_minutes: u64,
-    /// The configured cap, in minutes (`max-total-minutes`).
-    pub cap_minutes: u64,
+/// Carries both the observed value and the configured cap so the surfaced
+/// message is machine- and human-readable, and names the config key.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reason {
+    /// `max-total-minutes` tripped.
+    WallClock {
+        /// Whole minutes elapsed when the cap tripped.
+        elapsed_minutes: u64,
+        /// The configured cap, in minutes.
+        cap_minutes: u64,
+    },
+    /// `max-cost-usd` tripped (on the agent's self-reported spend).
+    Cost { spent_usd: f64, cap_usd: f64 },
+    /// `max-total-tokens` tripped.
+    Tokens { used: u64, cap: u64 },
 }
 
 impl std::fmt::Display for Reason {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "wall-clock budget exceeded: {} min elapsed >= {} min cap (max-total-minutes)",
-            self.elapsed_minutes, self.cap_minutes
-        )
+        match self {
+            Reason::WallClock {
+                elapsed_minutes,
+                cap_minutes,
+            } => write!(
+                f,
+                "wall-clock budget exceeded: {} min elapsed >= {} min cap (max-total-minutes)",
+                elapsed_minutes, cap_minutes
+            ),
+            Reason::Cost { spent_usd, cap_usd } => write!(
+                f,
+                "cost budget exceeded: ${:.4} spent >= ${:.4} cap (max-cost-usd)",
+                spent_usd, cap_usd
+            ),
+            Reason::Tokens { used, cap } => write!(
+                f,
+                "token budget exceeded: {} tokens used >= {} token cap (max-total-tokens)",
+                used, cap
+            ),
+        }
     }
 }
 
-/// Wall-clock budget for a whole run.
+/// Budget for a whole run: wall-clock, plus optional cost and token caps.
 ///
-/// Tracks the run start instant (monotonic) and the configured cap. A cap of
-/// `0` means unlimited and never trips.
+/// Tracks the run start instant (monotonic) and the spend recorded so far. Each
+/// cap of `0` means unlimited and never trips. Cost and token spend only
+/// accrue in `stream-json` mode, the only mode that reports it.
 #[derive(Debug, Clone)]
 pub struct Budget {
     start: Instant,
     cap_minutes: u64,
+    max_cost_usd: f64,
+    max_total_tokens: u64,
+    spent: Usage,
 }
 
 impl Budget {
-    /// Start a budget now with the given cap (`0` = unlimited).
+    /// Start a budget now with the configured caps (`0` = unlimited).
     ///
     /// Honors the `RWL_BUDGET_PREAGE_SECS` test hook: if set to a number of
     /// seconds, the start instant is pushed that far into the past so a
     /// non-zero cap can be tripped deterministically in an integration test.
-    pub fn start(cap_minutes: u64) -> Self {
+    pub fn start(config: &BudgetConfig) -> Self {
         let now = Instant::now();
         let start = match std::env::var(PREAGE_ENV).ok().and_then(|v| v.parse::<u64>().ok()) {
             Some(secs) => now.checked_sub(Duration::from_secs(secs)).unwrap_or(now),
             None => now,
         };
-        Self { start, cap_minutes }
+        Self {
+            start,
+            cap_minutes: config.max_total_minutes,
+            max_cost_usd: config.max_cost_usd,
+            max_total_tokens: config.max_total_tokens,
+            spent: Usage::default(),
+        }
+    }
+
+    /// Charge one agent invocation's reported usage against the budget.
+    pub fn record(&mut self, usage: &Usage) {
+        self.spent.add(usage);
     }
 
     /// Whether the budget has been exceeded as of now.
     ///
-    /// Returns `Some(reason)` only when the cap is non-zero AND the elapsed
-    /// wall-clock time has reached or passed it; `None` otherwise.
+    /// Returns `Some(reason)` for the first non-zero cap that has been reached
+    /// or passed (wall-clock, then cost, then tokens); `None` otherwise.
     pub fn exceeded(&self) -> Option<Reason> {
         self.exceeded_at(self.start.elapsed())
     }
 
+    /// Wall-clock time left before `max-total-minutes` trips; `None` when unlimited.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.remaining_at(self.start.elapsed())
+    }
+
+    fn remaining_at(&self, elapsed: Duration) -> Option<Duration> {
+        (self.cap_minutes > 0).then(|| Duration::from_secs(self.cap_minutes * SECS_PER_MINUTE).saturating_sub(elapsed))
+    }
+
     /// Threshold check against an explicit elapsed duration (testable, no clock).
     fn exceeded_at(&self, elapsed: Duration) -> Option<Reason> {
-        if self.cap_minutes == 0 {
-            return None;
-        }
-        let cap = Duration::from_secs(self.cap_minutes * SECS_PER_MINUTE);
-        if elapsed >= cap {
-            Some(Reason {
+        if self.cap_minutes > 0 && elapsed >= Duration::from_secs(self.cap_minutes * SECS_PER_MINUTE) {
+            return Some(Reason::WallClock {
                 elapsed_minutes: elapsed.as_secs() / SECS_PER_MINUTE,
                 cap_minutes: self.cap_minutes,
-            })
-        } else {
-            None
+            });
+        }
+        if self.max_cost_usd > 0.0 && self.spent.cost_usd >= self.max_cost_usd {
+            return Some(Reason::Cost {
+                spent_usd: self.spent.cost_usd,
+                cap_usd: self.max_cost_usd,
+            });
+        }
+        if self.max_total_tokens > 0 && self.spent.total_tokens() >= self.max_total_tokens {
+            return Some(Reason::Tokens {
+                used: self.spent.total_tokens(),
+                cap: self.max_total_tokens,
+            });
         }
+        None
     }
 
     /// Construct a budget whose start is pre-aged by `elapsed`, for deterministic
@@ -86,6 +141,9 @@ impl Budget {
         Self {
             start: Instant::now() - elapsed,
             cap_minutes,
+            max_cost_usd: 0.0,
+            max_total_tokens: 0,
+            spent: Usage::default(),
         }
     }
 }
@@ -95,33 +153,55 @@ impl Budget {
 mod tests {
     use super::*;
 
+    fn minutes(cap_minutes: u64) -> BudgetConfig {
+        BudgetConfig {
+            max_total_minutes: cap_minutes,
+            ..BudgetConfig::default()
+        }
+    }
+
     #[test]
     fn test_unlimited_cap_never_exceeds() {
-        let budget = Budget::start(0);
+        let mut budget = Budget::start(&BudgetConfig::default());
+        budget.record(&Usage {
+            cost_usd: 1_000.0,
+            input_tokens: 1_000_000,
+            ..Usage::default()
+        });
         assert!(budget.exceeded_at(Duration::from_secs(10_000)).is_none());
     }
 
     #[test]
     fn test_under_cap_returns_none() {
-        let budget = Budget::start(5);
+        let budget = Budget::start(&minutes(5));
         // 4m59s < 5m
         assert!(budget.exceeded_at(Duration::from_secs(299)).is_none());
     }
 
     #[test]
     fn test_at_cap_returns_some() {
-        let budget = Budget::start(5);
+        let budget = Budget::start(&minutes(5));
         let reason = budget.exceeded_at(Duration::from_secs(300)).unwrap();
-        assert_eq!(reason.cap_minutes, 5);
-        assert_eq!(reason.elapsed_minutes, 5);
+        assert_eq!(
+            reason,
+            Reason::WallClock {
+                elapsed_minutes: 5,
+                cap_minutes: 5
+            }
+        );
     }
 
     #[test]
     fn test_over_cap_returns_some() {
-        let budget = Budget::start(1);
+        let budget = Budget::start(&minutes(1));
         let reason = budget.exceeded_at(Duration::from_secs(125)).unwrap();
-        assert_eq!(reason.cap_minutes, 1);
-        assert_eq!(reason.elapsed_minutes, 2);
+        assert_eq!(
+            reason,
+            Reason::WallClock {
+                elapsed_minutes: 2,
+                cap_minutes: 1
+            }
+        );
     }
 
     #[test]
@@ -133,9 +213,78 @@ mod tests {
         assert!(budget.exceeded().is_some());
     }
 
+    #[test]
+    fn test_remaining_counts_down_to_zero() {
+        assert_eq!(Budget::start(&BudgetConfig::default()).remaining(), None);
+        let budget = Budget::start(&minutes(2));
+        assert_eq!(
+            budget.remaining_at(Duration::from_secs(30)),
+            Some(Duration::from_secs(90))
+        );
+        assert_eq!(budget.remaining_at(Duration::from_secs(500)), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_cost_cap_trips_on_recorded_spend() {
+        let mut budget = Budget::start(&BudgetConfig {
+            max_cost_usd: 1.0,
+            ..BudgetConfig::default()
+        });
+        budget.record(&Usage {
+            cost_usd: 0.6,
+            ..Usage::default()
+        });
+        assert!(budget.exceeded_at(Duration::ZERO).is_none());
+        budget.record(&Usage {
+            cost_usd: 0.4,
+            ..Usage::default()
+        });
+        let reason = budget.exceeded_at(Duration::ZERO).unwrap();
+        assert!(matches!(reason, Reason::Cost { cap_usd, .. } if cap_usd == 1.0));
+    }
+
+    #[test]
+    fn test_token_cap_counts_all_token_kinds() {
+        let mut budget = Budget::start(&BudgetConfig {
+            max_total_tokens: 100,
+            ..BudgetConfig::default()
+        });
+        budget.record(&Usage {
+            input_tokens: 40,
+            output_tokens: 10,
+            cache_read_input_tokens: 50,
+            ..Usage::default()
+        });
+        assert_eq!(
+            budget.exceeded_at(Duration::ZERO),
+            Some(Reason::Tokens { used: 100, cap: 100 })
+        );
+    }
+
+    #[test]
+    fn test_wall_clock_reported_before_cost() {
+        let mut budget = Budget::start(&BudgetConfig {
+            max_total_minutes: 1,
+            max_cost_usd: 0.5,
+            max_total_tokens: 0,
+        });
+        budget.record(&Usage {
+            cost_usd: 1.0,
+            ..Usage::default()
+        });
+        assert!(matches!(
+            budget.exceeded_at(Duration::from_secs(60)),
+            Some(Reason::WallClock { .. })
+        ));
+        assert!(matches!(
+            budget.exceeded_at(Duration::from_secs(30)),
+            Some(Reason::Cost { .. })
+        ));
+    }
+
     #[test]
     fn test_reason_display_mentions_cap_and_elapsed() {
-        let reason = Reason {
+        let reason = Reason::WallClock {
             elapsed_minutes: 7,
             cap_minutes: 5,
         };
@@ -144,4 +293,17 @@ mod tests {
         assert!(s.contains('5'));
         assert!(s.contains("max-total-minutes"));
     }
+
+    #[test]
+    fn test_reason_display_names_cost_and_token_caps() {
+        let cost = Reason::Cost {
+            spent_usd: 2.5,
+            cap_usd: 2.0,
+        };
+        assert!(cost.to_string().contains("max-cost-usd"));
+        assert!(cost.to_string().contains("$2.5000"));
+        let tokens = Reason::Tokens { used: 120, cap: 100 };
+        assert!(tokens.to_string().contains("max-total-tokens"));
+        assert!(tokens.to_string().contains("120"));
+    }
 }
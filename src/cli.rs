@@ -1,5 +1,5 @@
 use crate::config::Isolation;
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -30,21 +30,91 @@ pub enum Commands {
     /// Run the loop
     Run(RunArgs),
 
+    /// Run the plans listed in a file, one after another
+    Batch(BatchArgs),
+
+    /// Render the prompt, agent argv and resolved config without running
+    Prompt(PromptArgs),
+
     /// Show current progress
     Status,
+
+    /// Work with plan files
+    #[command(subcommand)]
+    Plan(PlanCommands),
+}
+
+#[derive(Subcommand)]
+pub enum PlanCommands {
+    /// Check a plan for problems before spending an agent run on it
+    Lint(LintArgs),
 }
 
 #[derive(Parser)]
 pub struct RunArgs {
-    /// Path to the implementation plan file
+    /// Path to the implementation plan file; repeat to queue several plans
     #[arg(short, long, required = true)]
-    pub plan: PathBuf,
+    pub plan: Vec<PathBuf>,
+
+    #[command(flatten)]
+    pub overrides: ConfigOverrides,
+
+    /// Base path for session files (default: /tmp/rwl/<reposlug>)
+    #[arg(short = 's', long)]
+    pub session_path: Option<PathBuf>,
+
+    /// Bypass the containment preflight (run a permission-bypassed agent
+    /// against an uncontained working tree). Use with care.
+    #[arg(long = "unsafe")]
+    pub unsafe_opt: bool,
+
+    #[command(flatten)]
+    pub queue: QueueArgs,
+}
+
+#[derive(Parser)]
+pub struct BatchArgs {
+    /// File listing the plans to run, one path per line, relative to the
+    /// file (blank lines and `#` comments are skipped)
+    pub plans: PathBuf,
+
+    #[command(flatten)]
+    pub overrides: ConfigOverrides,
+
+    /// Base path for session files (default: /tmp/rwl/<reposlug>)
+    #[arg(short = 's', long)]
+    pub session_path: Option<PathBuf>,
+
+    /// Bypass the containment preflight (run a permission-bypassed agent
+    /// against an uncontained working tree). Use with care.
+    #[arg(long = "unsafe")]
+    pub unsafe_opt: bool,
+
+    #[command(flatten)]
+    pub queue: QueueArgs,
+}
 
+/// How a queue of plans runs (`run` with several `--plan`s, and `batch`).
+#[derive(Args, Debug)]
+pub struct QueueArgs {
+    /// Start each plan from the branch of the last plan that completed
+    /// instead of HEAD (worktree isolation)
+    #[arg(long)]
+    pub chain: bool,
+
+    /// Stop the queue at the first plan that does not complete
+    #[arg(long)]
+    pub stop_on_failure: bool,
+}
+
+/// Config overrides shared by `run` and `prompt`.
+#[derive(Args, Debug)]
+pub struct ConfigOverrides {
     /// Maximum number of iterations (overrides config)
     #[arg(short, long)]
     pub max_iterations: Option<u32>,
 
-    /// LLM model to use (overrides config)
+    /// LLM model to use (overrides config and disables llm.escalation)
     #[arg(short = 'M', long)]
     pub model: Option<String>,
 
@@ -52,18 +122,39 @@ pub struct RunArgs {
     #[arg(short, long)]
     pub timeout: Option<u32>,
 
-    /// Base path for session files (default: /tmp/rwl/<reposlug>)
-    #[arg(short = 's', long)]
-    pub session_path: Option<PathBuf>,
-
     /// Isolation strategy (overrides config): worktree (default) or none
     #[arg(long, value_enum, ignore_case = true)]
     pub isolation: Option<IsolationArg>,
+}
 
-    /// Bypass the containment preflight (run a permission-bypassed agent
-    /// against an uncontained working tree). Use with care.
-    #[arg(long = "unsafe")]
-    pub unsafe_opt: bool,
+#[derive(Parser)]
+pub struct PromptArgs {
+    /// Path to the implementation plan file
+    #[arg(short, long, required = true)]
+    pub plan: PathBuf,
+
+    #[command(flatten)]
+    pub overrides: ConfigOverrides,
+
+    /// Existing session directory to continue from (its progress, last diff,
+    /// failed gates and judge rejection feed the prompt)
+    #[arg(long)]
+    pub session: Option<PathBuf>,
+
+    /// Iteration to render (default: the session's next, or 1)
+    #[arg(long)]
+    pub iteration: Option<u32>,
+
+    /// Write the report to this file instead of stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Parser)]
+pub struct LintArgs {
+    /// Path to the implementation plan file
+    #[arg(short, long, required = true)]
+    pub plan: PathBuf,
 }
 
 /// CLI surface for the isolation strategy, mirroring [`Isolation`].
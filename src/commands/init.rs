@@ -1,6 +1,5 @@
 use crate::cli::Cli;
 use crate::config::Config;
-use crate::templates::PROMPT_TEMPLATE;
 use colored::*;
 use eyre::{Context, Result};
 use std::fs;
@@ -78,10 +77,8 @@ pub fn init(work_dir: &Path, config_path: Option<&std::path::PathBuf>) -> Result
 
     println!("{} Created {}", "✓".green(), ".rwl/rwl.yml".cyan());
 
-    // 3. Create PROMPT.md template
-    let prompt_path = rwl_dir.join("PROMPT.md");
-    fs::write(&prompt_path, PROMPT_TEMPLATE).context("Failed to write PROMPT.md")?;
-    println!("{} Created {}", "✓".green(), ".rwl/PROMPT.md".cyan());
+    // 3. No PROMPT.md: a copy would override the built-in template and go
+    // stale as it changes. Projects that customize the prompt create one.
 
     // 4. Create .gitignore for .rwl/
     let gitignore_path = rwl_dir.join(".gitignore");
@@ -93,7 +90,11 @@ pub fn init(work_dir: &Path, config_path: Option<&std::path::PathBuf>) -> Result
     println!();
     println!("Next steps:");
     println!("  1. Edit {} to customize settings", ".rwl/rwl.yml".cyan());
-    println!("  2. Edit {} to customize the prompt", ".rwl/PROMPT.md".cyan());
+    println!(
+        "  2. Create {} to replace the built-in prompt ({} shows it)",
+        ".rwl/PROMPT.md".cyan(),
+        "rwl prompt --plan <path>".cyan()
+    );
     println!("  3. Run {} to start the loop", "rwl run --plan <path>".cyan());
 
     Ok(())
@@ -104,6 +105,7 @@ pub fn init(work_dir: &Path, config_path: Option<&std::path::PathBuf>) -> Result
 mod tests {
     use super::*;
     use crate::config::{Config, Isolation};
+    use crate::templates::{PromptSource, load_prompt_template};
     use tempfile::tempdir;
 
     #[test]
@@ -113,10 +115,22 @@ mod tests {
 
         assert!(dir.path().join(".rwl").exists());
         assert!(dir.path().join(".rwl/rwl.yml").exists());
-        assert!(dir.path().join(".rwl/PROMPT.md").exists());
         assert!(dir.path().join(".rwl/.gitignore").exists());
     }
 
+    /// Without a PROMPT.md of its own the project renders the built-in
+    /// template, so it picks up changes to it.
+    #[test]
+    fn test_init_leaves_the_built_in_prompt_in_effect() {
+        let dir = tempdir().unwrap();
+        init(dir.path(), None).unwrap();
+
+        assert!(!dir.path().join(".rwl/PROMPT.md").exists());
+        let config = Config::load(Some(&dir.path().join(".rwl/rwl.yml"))).unwrap();
+        let template = load_prompt_template(dir.path(), &config.prompt).unwrap();
+        assert_eq!(template.source, PromptSource::BuiltIn);
+    }
+
     #[test]
     fn test_init_does_not_overwrite() {
         let dir = tempdir().unwrap();
@@ -1,3 +1,5 @@
 pub mod init;
+pub mod plan;
+pub mod prompt;
 pub mod run;
 pub mod status;
@@ -0,0 +1,403 @@
+use crate::cli::{Cli, LintArgs};
+use crate::config::Config;
+use crate::plan::{self, ChecklistItem, Plan};
+use crate::progress::PROGRESS_FILES;
+use crate::safety;
+use colored::*;
+use eyre::Result;
+use log::debug;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Extensions that make a backticked word without a `/` a file name
+/// (`Cargo.toml`) rather than code (`plan.phases`).
+const FILE_EXTENSIONS: &[&str] = &[
+    "c", "cfg", "cpp", "css", "go", "h", "hpp", "html", "ini", "java", "js", "json", "jsx", "kt", "lock", "md",
+    "proto", "py", "rb", "rs", "sh", "sql", "toml", "ts", "tsx", "txt", "xml", "yaml", "yml",
+];
+
+/// Leading words of a step that only reads what it names, so naming a
+/// protected path there is fine ("Read `docs/design/parser.md`").
+const READ_VERBS: &[&str] = &["check", "consult", "follow", "read", "review", "see", "study"];
+
+/// Words a phase command may start with that are shell syntax or builtins,
+/// not programs to find on `PATH`.
+const SHELL_WORDS: &[&str] = &[
+    "!", ".", "[", "[[", "{", "}", "case", "cd", "do", "done", "echo", "elif", "else", "esac", "eval", "exec", "exit",
+    "export", "false", "fi", "for", "if", "printf", "set", "source", "test", "then", "true", "unset", "until", "while",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Diagnostic {
+    severity: Severity,
+    line: Option<usize>,
+    message: String,
+}
+
+impl Diagnostic {
+    fn error(line: Option<usize>, message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            line,
+            message,
+        }
+    }
+
+    fn warning(line: Option<usize>, message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            line,
+            message,
+        }
+    }
+}
+
+/// Check a plan against what `run` will need, without running anything:
+/// it reads and parses, the files it names exist, no step edits a protected
+/// path and the phase commands resolve. Prints the problems found and
+/// returns exit code 1 if any is an error; warnings alone pass.
+pub fn lint(cli: &Cli, args: &LintArgs) -> Result<i32> {
+    let config = Config::load(cli.config.as_ref())?;
+    let repo = Path::new(".");
+    debug!("lint: plan={}", args.plan.display());
+
+    let diagnostics = match std::fs::read_to_string(&args.plan) {
+        Ok(content) => {
+            // Links resolve against the plan's directory, as markdown does.
+            let plan_dir = args.plan.parent().filter(|dir| dir.is_dir()).unwrap_or(repo);
+            check(&content, plan_dir, repo, &config.safety.protected_paths)
+        }
+        Err(e) => vec![Diagnostic::error(None, format!("cannot read the plan: {}", e))],
+    };
+
+    let name = args.plan.display();
+    for diagnostic in &diagnostics {
+        let location = match diagnostic.line {
+            Some(line) => format!("{}:{}", name, line),
+            None => name.to_string(),
+        };
+        let severity = match diagnostic.severity {
+            Severity::Error => "error".red().bold(),
+            Severity::Warning => "warning".yellow().bold(),
+        };
+        println!("{}: {}: {}", location, severity, diagnostic.message);
+    }
+
+    let errors = diagnostics.iter().filter(|d| d.severity == Severity::Error).count();
+    let warnings = diagnostics.len() - errors;
+    debug!("lint: errors={} warnings={}", errors, warnings);
+    if errors > 0 {
+        println!("{} {}: {} error(s), {} warning(s)", "✗".red(), name, errors, warnings);
+        Ok(1)
+    } else if warnings > 0 {
+        println!("{} {}: {} warning(s)", "⚠".yellow(), name, warnings);
+        Ok(0)
+    } else {
+        println!("{} {}: no problems found", "✓".green(), name);
+        Ok(0)
+    }
+}
+
+/// The diagnostics for plan `content`, in line order. Backticked paths
+/// resolve against `repo` (where the agent and the phase commands run), link
+/// targets against `plan_dir`.
+fn check(content: &str, plan_dir: &Path, repo: &Path, protected: &[String]) -> Vec<Diagnostic> {
+    if content.trim().is_empty() {
+        return vec![Diagnostic::error(None, "the plan is empty".to_string())];
+    }
+    let parsed = Plan::parse(content);
+    let mut diagnostics = Vec::new();
+
+    // Phases and checklists.
+    if parsed.phases.is_empty() && parsed.loose.is_empty() {
+        diagnostics.push(Diagnostic::warning(
+            None,
+            "no phases or checklist items: only the completion signal can tell the plan is done".to_string(),
+        ));
+    }
+    for (line, reason) in &parsed.ignored {
+        diagnostics.push(Diagnostic::error(
+            Some(*line),
+            format!("phase check ignored: {}", reason),
+        ));
+    }
+
+    // Phase commands.
+    for phase in &parsed.phases {
+        let commands = phase.validation.iter().map(|command| ("validation", command)).chain(
+            phase
+                .gates
+                .iter()
+                .filter_map(|gate| gate.command.as_ref().map(|command| ("gate", command))),
+        );
+        for (kind, command) in commands {
+            for program in programs(command) {
+                if !resolves(&program, repo) {
+                    diagnostics.push(Diagnostic::error(
+                        Some(phase.line),
+                        format!(
+                            "{}: {} command `{}`: `{}` not found on PATH",
+                            phase.title, kind, command, program
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    // Referenced paths, and steps that would edit protected ones.
+    let protected: Vec<String> = protected
+        .iter()
+        .cloned()
+        .chain(PROGRESS_FILES.iter().map(|path| path.to_string()))
+        .collect();
+    let steps: HashMap<usize, &ChecklistItem> = parsed.unchecked().into_iter().map(|item| (item.line, item)).collect();
+    let link_dir = plan_dir.strip_prefix(repo).unwrap_or(plan_dir);
+    for (number, line, in_code) in plan::lines(content) {
+        if in_code || plan::is_phase_check(line) {
+            continue;
+        }
+        // (as written, relative to the repo)
+        let refs = code_paths(line)
+            .into_iter()
+            .map(|path| (path, Path::new(path).to_path_buf()))
+            .chain(link_targets(line).into_iter().map(|path| (path, link_dir.join(path))));
+        let step = steps.get(&number).filter(|item| !reads_only(item));
+        for (path, relative) in refs {
+            let full = repo.join(&relative);
+            if !full.exists() {
+                let parent = Path::new(path).parent().filter(|dir| !dir.as_os_str().is_empty());
+                match parent {
+                    Some(dir) if !full.parent().is_some_and(Path::exists) => {
+                        diagnostics.push(Diagnostic::error(
+                            Some(number),
+                            format!("`{}` not found, nor its directory `{}`", path, dir.display()),
+                        ));
+                    }
+                    _ => diagnostics.push(Diagnostic::warning(
+                        Some(number),
+                        format!("`{}` does not exist yet", path),
+                    )),
+                }
+            }
+            if step.is_some() && safety::is_protected(&relative.to_string_lossy(), &protected) {
+                diagnostics.push(Diagnostic::error(
+                    Some(number),
+                    format!(
+                        "step targets protected path `{}` (safety.protected-paths): its edits would be reverted every iteration",
+                        path
+                    ),
+                ));
+            }
+        }
+    }
+
+    diagnostics.sort_by_key(|diagnostic| diagnostic.line);
+    diagnostics
+}
+
+/// The step only reads what it names: its first word is one of [`READ_VERBS`].
+fn reads_only(item: &ChecklistItem) -> bool {
+    item.text
+        .split_whitespace()
+        .next()
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+        .is_some_and(|word| READ_VERBS.contains(&word.as_str()))
+}
+
+/// Backticked words in `line` that look like file paths.
+fn code_paths(line: &str) -> Vec<&str> {
+    let parts: Vec<&str> = line.split('`').collect();
+    // Odd parts are code spans; a trailing unclosed one is not.
+    parts
+        .get(1..parts.len().saturating_sub(1))
+        .unwrap_or_default()
+        .iter()
+        .step_by(2)
+        .map(|span| span.trim())
+        .filter(|span| looks_like_path(span))
+        .collect()
+}
+
+/// Targets of `[text](target)` links, minus URLs and `#anchors`.
+fn link_targets(line: &str) -> Vec<&str> {
+    let mut targets = Vec::new();
+    let mut rest = line;
+    while let Some(start) = rest.find("](") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find(')') else {
+            break;
+        };
+        let target = rest[..end].split('#').next().unwrap_or_default();
+        let target = target.split_whitespace().next().unwrap_or_default();
+        if !target.is_empty() && !target.contains(':') {
+            targets.push(target);
+        }
+        rest = &rest[end..];
+    }
+    targets
+}
+
+/// A single word with a `/` or a known file extension, and nothing that
+/// makes it code, a glob or a placeholder instead.
+fn looks_like_path(word: &str) -> bool {
+    let plain = word.chars().any(|c| c.is_alphanumeric())
+        && !word.starts_with('-')
+        && !word
+            .chars()
+            .any(|c| c.is_whitespace() || "!\"#$%&'()*,:;<=>?@[\\]^{|}~".contains(c));
+    plain
+        && (word.contains('/')
+            || word
+                .rsplit_once('.')
+                .is_some_and(|(stem, ext)| !stem.is_empty() && FILE_EXTENSIONS.contains(&ext)))
+}
+
+/// The programs a shell command runs: the first word of each `;`, `&&`,
+/// `||` or `|` separated part, after any `VAR=value` assignments. Shell
+/// words and `$VARIABLE` programs are left out.
+fn programs(command: &str) -> Vec<String> {
+    command
+        .replace("&&", ";")
+        .replace("||", ";")
+        .split([';', '|', '\n'])
+        .filter_map(|part| part.split_whitespace().find(|word| !is_assignment(word)))
+        .map(|word| word.trim_start_matches('(').to_string())
+        .filter(|word| !word.is_empty() && !word.starts_with('$') && !SHELL_WORDS.contains(&word.as_str()))
+        .collect()
+}
+
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=')
+        .is_some_and(|(name, _)| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+}
+
+/// `program` is on `PATH`, or is a path (`./scripts/check.sh`) that exists
+/// in `repo`.
+fn resolves(program: &str, repo: &Path) -> bool {
+    if program.contains('/') {
+        repo.join(program).exists()
+    } else {
+        which::which(program).is_ok()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn protected() -> Vec<String> {
+        vec![".git/".to_string(), ".rwl/".to_string(), "docs/design/".to_string()]
+    }
+
+    fn messages(diagnostics: &[Diagnostic], severity: Severity) -> Vec<(Option<usize>, &str)> {
+        diagnostics
+            .iter()
+            .filter(|d| d.severity == severity)
+            .map(|d| (d.line, d.message.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_check_reports_problems_by_line() {
+        let repo = tempdir().unwrap();
+        std::fs::create_dir_all(repo.path().join("src")).unwrap();
+        std::fs::create_dir_all(repo.path().join("docs/design")).unwrap();
+        std::fs::create_dir_all(repo.path().join(".rwl")).unwrap();
+        std::fs::write(repo.path().join("Cargo.toml"), "").unwrap();
+        std::fs::write(repo.path().join("docs/design/parser.md"), "").unwrap();
+
+        let plan = "\
+# Plan
+
+Validation: `make`
+
+## Phase 1: Parser
+
+- [ ] Read `docs/design/parser.md` and [the manifest](Cargo.toml)
+- [ ] Add `src/parser.rs`
+- [ ] Wire it into `srcs/main.rs`
+- [ ] Update `docs/design/parser.md` with the grammar
+- [x] Bump `.rwl/rwl.yml`
+
+Validation: `cargo-nonexistent-xyz test && true`
+Gate: fmt `RUSTFLAGS=-Dwarnings ./scripts/check.sh`
+
+```
+- [ ] Edit `.rwl/rwl.yml`
+```
+";
+        let diagnostics = check(plan, repo.path(), repo.path(), &protected());
+        assert_eq!(
+            messages(&diagnostics, Severity::Error),
+            vec![
+                (Some(3), "phase check ignored: outside any phase"),
+                (
+                    Some(5),
+                    "Phase 1: Parser: validation command `cargo-nonexistent-xyz test && true`: \
+                     `cargo-nonexistent-xyz` not found on PATH"
+                ),
+                (
+                    Some(5),
+                    "Phase 1: Parser: gate command `RUSTFLAGS=-Dwarnings ./scripts/check.sh`: \
+                     `./scripts/check.sh` not found on PATH"
+                ),
+                (Some(9), "`srcs/main.rs` not found, nor its directory `srcs`"),
+                (
+                    Some(10),
+                    "step targets protected path `docs/design/parser.md` (safety.protected-paths): \
+                     its edits would be reverted every iteration"
+                ),
+            ]
+        );
+        assert_eq!(
+            messages(&diagnostics, Severity::Warning),
+            vec![
+                (Some(8), "`src/parser.rs` does not exist yet"),
+                (Some(11), "`.rwl/rwl.yml` does not exist yet")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_empty_and_untracked_plans() {
+        let repo = tempdir().unwrap();
+        let empty = check("  \n", repo.path(), repo.path(), &protected());
+        assert_eq!(messages(&empty, Severity::Error), vec![(None, "the plan is empty")]);
+
+        let prose = check("# Plan\nDo the thing.\n", repo.path(), repo.path(), &protected());
+        assert!(messages(&prose, Severity::Error).is_empty());
+        assert_eq!(messages(&prose, Severity::Warning).len(), 1);
+    }
+
+    #[test]
+    fn test_path_detection() {
+        assert_eq!(
+            code_paths("Edit `src/main.rs`, `Cargo.toml` and `plan.phases`; run `cargo test` on `*.rs` `dangling"),
+            vec!["src/main.rs", "Cargo.toml"]
+        );
+        assert_eq!(
+            link_targets("See [docs](docs/a.md#intro), [site](https://x.dev) and [top](#top)"),
+            vec!["docs/a.md"]
+        );
+        assert!(!looks_like_path("--all-targets"));
+        assert!(!looks_like_path("<promise>DONE</promise>"));
+    }
+
+    #[test]
+    fn test_programs() {
+        assert_eq!(
+            programs("FOO=1 cargo test 2>&1 | tee out.log && (./check.sh || echo failed); $CARGO fmt"),
+            vec!["cargo", "tee", "./check.sh"]
+        );
+        assert_eq!(programs("test -f done && true"), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,210 @@
+use crate::agent::{self, AgentRequest};
+use crate::artifacts::IterationArtifacts;
+use crate::budget::Budget;
+use crate::cli::{Cli, PromptArgs};
+use crate::commands::run::{plan_in_work_dir, resolve_config, resolve_plan};
+use crate::config::{Config, ProgressStrategy, PromptTransport};
+use crate::escalation::ModelLadder;
+use crate::plan::Plan;
+use crate::progress::{self, ProgressTracker};
+use crate::runner::{Carryover, PromptInputs, render_prompt};
+use crate::summarize;
+use crate::templates;
+use colored::*;
+use eyre::{Context, Result};
+use log::debug;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Run the `run` pipeline up to the prompt and report what the agent would
+/// receive: the rendered prompt, the agent argv, the protected paths and the
+/// resolved config. No session dir or worktree is created and no agent runs;
+/// the prompt's helpers resolve against the current directory.
+///
+/// With `--session`, the prompt continues that session: its progress log,
+/// next iteration number, model, last diffstat, failed gates and judge
+/// rejection are replayed from its artifacts. When the session has a
+/// worktree, the plan copy there (as the agent left it), the helpers and the
+/// agent's working directory are the worktree's, as in the run.
+pub fn run(_cli: &Cli, args: &PromptArgs) -> Result<()> {
+    let cwd = Path::new(".");
+    debug!(
+        "prompt: plan={} session={:?} iteration={:?} output={:?}",
+        args.plan.display(),
+        args.session,
+        args.iteration,
+        args.output
+    );
+
+    let mut config = resolve_config(cwd, &args.overrides)?;
+    let template = templates::load_prompt_template(cwd, &config.prompt)?;
+    let plan_path = resolve_plan(&args.plan)?;
+
+    // The session's worktree, or the project without `--session` or under
+    // `isolation: none`.
+    let work_dir = match &args.session {
+        Some(session_dir) if !session_dir.is_dir() => {
+            return Err(eyre::eyre!("Session directory not found: {}", session_dir.display()));
+        }
+        Some(session_dir) if session_dir.join("worktree").is_dir() => session_dir.join("worktree"),
+        _ => cwd.to_path_buf(),
+    };
+    let plan_path = plan_in_work_dir(cwd, &work_dir, plan_path);
+    debug!("prompt: work_dir={} plan_path={}", work_dir.display(), plan_path.display());
+
+    let (progress, carryover, last) = match &args.session {
+        Some(session_dir) => {
+            let records = ProgressTracker::in_work_dir(&work_dir).records()?;
+            // Compacted as the run would, with the session's existing digest;
+            // no summary model is called here.
+            let digest = match config.progress.strategy {
+                ProgressStrategy::Summarize => summarize::load_digest(session_dir),
+                ProgressStrategy::Tail => None,
+            };
+            let progress = progress::compact(&records, &config.progress, digest.as_ref());
+            let metas = IterationArtifacts::read_all(session_dir)?;
+            let carryover = Carryover::replay(session_dir, &metas, &config);
+            (progress, carryover, metas.last().cloned())
+        }
+        None => (ProgressTracker::header(&plan_path), Carryover::default(), None),
+    };
+    let iteration = args
+        .iteration
+        .unwrap_or_else(|| last.as_ref().map_or(1, |meta| meta.iteration + 1));
+    config.llm.model = match &last {
+        Some(meta) if args.overrides.model.is_none() && !meta.model.is_empty() => meta.model.clone(),
+        _ => ModelLadder::new(&config.llm).current().to_string(),
+    };
+    debug!("prompt: iteration={} model={}", iteration, config.llm.model);
+
+    let inputs = PromptInputs {
+        template: &template,
+        work_dir: &work_dir,
+        plan_path: &plan_path,
+        phase: Plan::load(&plan_path)
+            .ok()
+            .filter(Plan::gated)
+            .map(|plan| plan.first_unfinished()),
+        iteration,
+        progress: &progress,
+        remaining: Budget::start(&config.budget).remaining(),
+        carryover: &carryover,
+    };
+    let prompt = render_prompt(&inputs, &config)?;
+
+    // The argv the loop would spawn, with the prompt file where the run
+    // would write it.
+    let session_dir = args.session.clone().unwrap_or_else(|| PathBuf::from("<session>"));
+    let prompt_file = IterationArtifacts::dir_for(&session_dir, iteration).join("prompt.md");
+    let backend = agent::backend_for(&config.llm).context("Invalid agent backend configuration")?;
+    let request = AgentRequest {
+        model: &config.llm.model,
+        prompt: &prompt,
+        work_dir: &work_dir,
+        prompt_file: &prompt_file,
+        skip_permissions: config.llm.dangerously_skip_permissions,
+        output_format: config.llm.output_format,
+        transport: config.llm.prompt_transport,
+    };
+    let argv = format_argv(&backend.command(&request)?, &prompt, config.llm.prompt_transport);
+
+    let report = format_report(&template.source, iteration, &prompt, backend.name(), &argv, &config)?;
+    match &args.output {
+        Some(path) => {
+            std::fs::write(path, &report).with_context(|| format!("Failed to write {}", path.display()))?;
+            println!(
+                "{} Wrote prompt for iteration {} to {}",
+                "✓".green(),
+                iteration,
+                path.display()
+            );
+        }
+        None => print!("{}", report),
+    }
+
+    Ok(())
+}
+
+/// The command line for `cmd`, with the rendered prompt shown as `<prompt>`
+/// (it is printed in full above) and arguments needing quotes quoted.
+fn format_argv(cmd: &Command, prompt: &str, transport: PromptTransport) -> String {
+    let mut parts = vec![quote(&cmd.get_program().to_string_lossy())];
+    for arg in cmd.get_args() {
+        let arg = arg.to_string_lossy();
+        if arg == prompt {
+            parts.push("<prompt>".to_string());
+        } else {
+            parts.push(quote(&arg));
+        }
+    }
+    if transport == PromptTransport::Stdin {
+        parts.push("< <prompt>".to_string());
+    }
+    parts.join(" ")
+}
+
+fn quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:@,+".contains(c));
+    if plain { arg.to_string() } else { format!("{:?}", arg) }
+}
+
+fn format_report(
+    source: &templates::PromptSource,
+    iteration: u32,
+    prompt: &str,
+    backend: &str,
+    argv: &str,
+    config: &Config,
+) -> Result<String> {
+    let protected = if config.safety.protected_paths.is_empty() {
+        "(none)\n".to_string()
+    } else {
+        config
+            .safety
+            .protected_paths
+            .iter()
+            .map(|p| format!("- {}\n", p))
+            .collect()
+    };
+    let yaml = serde_yaml::to_string(config).context("Failed to serialize config")?;
+
+    Ok(format!(
+        "=== Prompt (iteration {}, template: {}) ===\n{}\n\n\
+         === Agent argv (backend: {}) ===\n{}\n\n\
+         === Protected paths ===\n{}\n\
+         === Resolved config ===\n{}",
+        iteration,
+        source,
+        prompt.trim_end(),
+        backend,
+        argv,
+        protected,
+        yaml
+    ))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_argv_elides_prompt_and_quotes() {
+        let mut cmd = Command::new("claude");
+        cmd.args(["--print", "--model", "opus", "a b", "line one\nline two"]);
+        assert_eq!(
+            format_argv(&cmd, "line one\nline two", PromptTransport::Argv),
+            r#"claude --print --model opus "a b" <prompt>"#
+        );
+
+        let mut cmd = Command::new("claude");
+        cmd.args(["--print", ""]);
+        assert_eq!(
+            format_argv(&cmd, "unused", PromptTransport::Stdin),
+            r#"claude --print "" < <prompt>"#
+        );
+    }
+}
@@ -1,54 +1,165 @@
-use crate::cli::{Cli, RunArgs};
-use crate::config::Config;
+use crate::agent;
+use crate::cli::{BatchArgs, Cli, ConfigOverrides, QueueArgs, RunArgs};
+use crate::config::{Config, OutputFormat};
 use crate::git::{GitManager, reposlug};
-use crate::progress::ProgressTracker;
-use crate::result::RunResult;
+use crate::interrupt;
+use crate::progress::{PROGRESS_FILES, ProgressTracker};
+use crate::result::{BatchResult, QueuedPlan, RunResult};
 use crate::runner::LoopRunner;
 use crate::safety::{Workdir, resolve_workdir};
+use crate::session::LAST_SESSION_PATH;
+use crate::templates::{self, PromptSource};
+use chrono::Utc;
 use colored::*;
 use eyre::{Context, Result};
 use log::{debug, warn};
 use std::path::{Path, PathBuf};
 
-pub fn run(_cli: &Cli, args: &RunArgs) -> Result<RunResult> {
+/// `rwl run`: one plan, or a queue of them when `--plan` is repeated.
+/// Returns the process exit code.
+pub fn run(_cli: &Cli, args: &RunArgs) -> Result<i32> {
+    match args.plan.as_slice() {
+        [plan] => {
+            let result = run_plan(plan, &args.overrides, args.session_path.as_ref(), args.unsafe_opt, None)?;
+            Ok(result.exit_code)
+        }
+        plans => run_queue(
+            plans,
+            &args.overrides,
+            args.session_path.as_ref(),
+            args.unsafe_opt,
+            &args.queue,
+        ),
+    }
+}
+
+/// `rwl batch`: the queue of plans listed in a file.
+pub fn batch(_cli: &Cli, args: &BatchArgs) -> Result<i32> {
+    let plans = read_plan_list(&args.plans)?;
+    run_queue(
+        &plans,
+        &args.overrides,
+        args.session_path.as_ref(),
+        args.unsafe_opt,
+        &args.queue,
+    )
+}
+
+/// The plans of a batch file: one path per line, relative to the file;
+/// blank lines and `#` comments are skipped.
+fn read_plan_list(path: &Path) -> Result<Vec<PathBuf>> {
+    let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let plans: Vec<PathBuf> = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| dir.join(line))
+        .collect();
+    if plans.is_empty() {
+        return Err(eyre::eyre!("No plans listed in {}", path.display()));
+    }
+    Ok(plans)
+}
+
+/// Run `plans` one after another, each in its own session, and write the
+/// queue's summary to `batch-<timestamp>.json` in the session base. Returns
+/// the exit code of the first plan that did not complete, or 0.
+fn run_queue(
+    plans: &[PathBuf],
+    overrides: &ConfigOverrides,
+    session_path: Option<&PathBuf>,
+    unsafe_opt: bool,
+    queue: &QueueArgs,
+) -> Result<i32> {
     let cwd = Path::new(".");
     debug!(
-        "run: plan={} isolation_override={:?} unsafe_opt={}",
-        args.plan.display(),
-        args.isolation,
-        args.unsafe_opt
+        "run_queue: plans={} chain={} stop_on_failure={}",
+        plans.len(),
+        queue.chain,
+        queue.stop_on_failure
     );
+    let base = match session_path {
+        Some(path) => path.clone(),
+        None => default_session_base(cwd)?,
+    };
+    let started = Utc::now();
 
-    // 1. Ensure .rwl/ exists in the user's working tree
-    ensure_initialized(cwd)?;
-
-    // 2. Load config, apply CLI overrides
-    let mut config = Config::load_local(cwd).context("Failed to load local config")?;
+    let mut queued: Vec<QueuedPlan> = Vec::new();
+    // The branch the next chained plan starts from: the last plan that
+    // completed, so no plan builds on unfinished work.
+    let mut previous_branch: Option<String> = None;
+    for (i, plan) in plans.iter().enumerate() {
+        let failed = queued.iter().any(|plan| !plan.complete());
+        if interrupt::stop_requested() || (queue.stop_on_failure && failed) {
+            debug!("run_queue: skipping plan={}", plan.display());
+            queued.push(QueuedPlan::skipped(plan));
+            continue;
+        }
 
-    if let Some(max) = args.max_iterations {
-        config.loop_config.max_iterations = max;
-    }
-    if let Some(ref model) = args.model {
-        config.llm.model = model.clone();
-    }
-    if let Some(timeout) = args.timeout {
-        config.loop_config.iteration_timeout_minutes = timeout;
-    }
-    if let Some(isolation) = args.isolation {
-        config.safety.isolation = isolation.into();
+        println!();
+        println!(
+            "{} Plan {}/{}: {}",
+            "→".cyan(),
+            i + 1,
+            plans.len(),
+            plan.display().to_string().bold()
+        );
+        let start = previous_branch.as_deref().filter(|_| queue.chain);
+        match run_plan(plan, overrides, Some(&base), unsafe_opt, start) {
+            Ok(result) => {
+                if result.outcome == "complete" && result.branch.is_some() {
+                    previous_branch = result.branch.clone();
+                }
+                queued.push(QueuedPlan::finished(plan, &result));
+            }
+            Err(e) => {
+                eprintln!("{} {}: {:#}", "✗".red(), plan.display(), e);
+                queued.push(QueuedPlan::failed(plan, &e));
+            }
+        }
     }
 
-    // 3. Validate plan file exists, canonicalize to an absolute path so it
-    //    resolves regardless of work_dir (the worktree may not contain it).
-    ensure_plan_exists(&args.plan)?;
-    let plan_path = args
-        .plan
-        .canonicalize()
-        .with_context(|| format!("Failed to canonicalize plan path: {}", args.plan.display()))?;
-    debug!("run: canonicalized plan path -> {}", plan_path.display());
+    let batch = BatchResult::new(queued, started, queue.chain, queue.stop_on_failure);
+    std::fs::create_dir_all(&base).context("Failed to create session directory")?;
+    let path = base.join(format!("batch-{}.json", started.format("%Y%m%d-%H%M%S")));
+    batch.write_json(&path)?;
+    print_queue(&batch);
+    println!("batch: {}", path.display());
+    Ok(batch.exit_code)
+}
+
+/// Run one plan in a fresh session. A chained plan passes `start`, the
+/// branch its worktree starts from instead of HEAD.
+fn run_plan(
+    plan: &Path,
+    overrides: &ConfigOverrides,
+    session_path: Option<&PathBuf>,
+    unsafe_opt: bool,
+    start: Option<&str>,
+) -> Result<RunResult> {
+    let cwd = Path::new(".");
+    debug!(
+        "run_plan: plan={} isolation_override={:?} unsafe_opt={} start={:?}",
+        plan.display(),
+        overrides.isolation,
+        unsafe_opt,
+        start
+    );
+
+    // 1-2c. Load config from .rwl/, apply CLI overrides, check the backend
+    let config = resolve_config(cwd, overrides)?;
+
+    // 2d. Resolve and parse the prompt template (`prompt.template`, the
+    //     project's .rwl/PROMPT.md, or the built-in) so a syntax error is
+    //     reported with its line and column before any worktree exists.
+    let prompt = templates::load_prompt_template(cwd, &config.prompt)?;
+
+    // 3. Validate plan file exists, canonicalize to an absolute path
+    let plan_path = resolve_plan(plan)?;
 
     // 4. Create session directory
-    let session_dir = create_session_dir(cwd, args.session_path.as_ref())?;
+    let session_dir = create_session_dir(cwd, session_path)?;
     let timestamp = session_dir
         .file_name()
         .map(|n| n.to_string_lossy().to_string())
@@ -60,13 +171,24 @@ pub fn run(_cli: &Cli, args: &RunArgs) -> Result<RunResult> {
         cwd,
         &config.safety,
         config.llm.dangerously_skip_permissions,
-        args.unsafe_opt,
+        unsafe_opt,
         &session_dir,
         &plan_path,
         &timestamp,
     )?;
 
-    // 5b. Isolation on + auto-commit off is a stranding hazard: work happens in
+    // 5a. The run is going ahead: note its session in the project for `rwl status`.
+    record_last_session(cwd, &session_dir)?;
+
+    // 5b. A chained plan picks up where the previous plan's branch left off.
+    if let Some(start) = start {
+        match branch {
+            Some(_) => GitManager::new(&work_dir).reset_hard(start)?,
+            None => warn!("run_plan: --chain needs worktree isolation; starting from the working tree"),
+        }
+    }
+
+    // 5c. Isolation on + auto-commit off is a stranding hazard: work happens in
     //     the throwaway worktree under /tmp, but with auto-commit disabled nothing
     //     lands on the review branch, so the worktree's commits never reach the
     //     user. Warn (per the design's risk table) recommending auto-commit stay on.
@@ -84,16 +206,24 @@ pub fn run(_cli: &Cli, args: &RunArgs) -> Result<RunResult> {
     config.save_local(&work_dir)?;
     baseline_commit(&work_dir, branch.is_some())?;
 
-    // 7. Initialize progress tracker in session directory (always fresh)
-    let progress_path = session_dir.join("progress.txt");
-    let progress = ProgressTracker::new(&progress_path);
+    // 7. Initialize the progress log in the work_dir (always fresh), where the
+    //    agent and `rwl status` read it. Excluded locally so it is never
+    //    committed; the runner undoes agent edits to it.
+    let progress = ProgressTracker::in_work_dir(&work_dir);
     progress.init(&plan_path)?;
+    let git = GitManager::new(&work_dir);
+    if git.is_repo() {
+        for path in PROGRESS_FILES {
+            git.exclude(&format!("/{}", path))?;
+        }
+    }
 
     // 8. Print startup banner
-    print_banner(&config, &plan_path, &session_dir, branch.as_deref())?;
+    print_banner(&config, &plan_path, &session_dir, branch.as_deref(), &prompt.source)?;
 
     // 9. Run the loop
-    let mut runner = LoopRunner::new(&work_dir, plan_path, session_dir.clone(), branch.clone())?;
+    let plan_path = plan_in_work_dir(cwd, &work_dir, plan_path);
+    let mut runner = LoopRunner::new(&work_dir, plan_path, session_dir.clone(), branch.clone(), prompt)?;
     let result = runner.run()?;
 
     // 10. Write result.json to session directory
@@ -111,6 +241,59 @@ pub fn run(_cli: &Cli, args: &RunArgs) -> Result<RunResult> {
     Ok(result)
 }
 
+/// Steps 1-2c of a run, shared with `rwl prompt`: require `.rwl/`, load the
+/// local config, apply the CLI overrides, and reject a config the run would
+/// fail on (bad agent backend, a usage cap without stream-json).
+pub(crate) fn resolve_config(cwd: &Path, overrides: &ConfigOverrides) -> Result<Config> {
+    // 1. Ensure .rwl/ exists in the user's working tree
+    ensure_initialized(cwd)?;
+
+    // 2. Load config, apply CLI overrides
+    let mut config = Config::load_local(cwd).context("Failed to load local config")?;
+
+    if let Some(max) = overrides.max_iterations {
+        config.loop_config.max_iterations = max;
+    }
+    if let Some(ref model) = overrides.model {
+        // An explicit model pins the run: no escalation ladder.
+        config.llm.model = model.clone();
+        config.llm.escalation.clear();
+    }
+    if let Some(timeout) = overrides.timeout {
+        config.loop_config.iteration_timeout_minutes = timeout;
+    }
+    if let Some(isolation) = overrides.isolation {
+        config.safety.isolation = isolation.into();
+    }
+
+    // 2b. Resolve the agent backend now so a bad `llm.backend` / `llm.command`
+    //     (unknown variable syntax, empty argv) fails before any worktree exists.
+    agent::backend_for(&config.llm).context("Invalid agent backend configuration")?;
+
+    // 2c. Cost/token caps are enforced on reported usage, which only the
+    //     stream-json mode captures. Refuse rather than run with a cap that
+    //     can never trip.
+    if config.budget.needs_usage() && config.llm.output_format != OutputFormat::StreamJson {
+        return Err(eyre::eyre!(
+            "budget.max-cost-usd / budget.max-total-tokens require llm.output-format: stream-json \
+             (usage is not reported in text mode)"
+        ));
+    }
+
+    Ok(config)
+}
+
+/// Validate the plan file exists and canonicalize it to an absolute path so
+/// it resolves regardless of work_dir (the worktree may not contain it).
+pub(crate) fn resolve_plan(plan: &Path) -> Result<PathBuf> {
+    ensure_plan_exists(plan)?;
+    let plan_path = plan
+        .canonicalize()
+        .with_context(|| format!("Failed to canonicalize plan path: {}", plan.display()))?;
+    debug!("resolve_plan: canonicalized plan path -> {}", plan_path.display());
+    Ok(plan_path)
+}
+
 /// Make the baseline `rwl: session setup` commit in the work_dir so the
 /// protected-path guard (Phase 2) reverts only agent deltas against HEAD.
 ///
@@ -137,17 +320,44 @@ fn baseline_commit(work_dir: &Path, isolated: bool) -> Result<()> {
     Ok(())
 }
 
+/// Where sessions go without `--session-path`: `/tmp/rwl/<reposlug>`.
+pub(crate) fn default_session_base(work_dir: &Path) -> Result<PathBuf> {
+    Ok(PathBuf::from("/tmp/rwl").join(reposlug(work_dir)?))
+}
+
+/// Point [`LAST_SESSION_PATH`] at `session_dir`. Excluded locally first, so
+/// it is never committed (the session setup commit included).
+fn record_last_session(work_dir: &Path, session_dir: &Path) -> Result<()> {
+    let git = GitManager::new(work_dir);
+    if git.is_repo() {
+        git.exclude(&format!("/{}", LAST_SESSION_PATH))?;
+    }
+    let session_dir = session_dir.canonicalize().unwrap_or_else(|_| session_dir.to_path_buf());
+    std::fs::write(work_dir.join(LAST_SESSION_PATH), format!("{}\n", session_dir.display()))
+        .context("Failed to record the session directory")
+}
+
+/// The session directory [`LAST_SESSION_PATH`] names, if any.
+pub(crate) fn last_session(work_dir: &Path) -> Option<PathBuf> {
+    let recorded = std::fs::read_to_string(work_dir.join(LAST_SESSION_PATH)).ok()?;
+    Some(PathBuf::from(recorded.trim())).filter(|path| !path.as_os_str().is_empty())
+}
+
 fn create_session_dir(work_dir: &Path, session_path: Option<&PathBuf>) -> Result<PathBuf> {
     let base = match session_path {
         Some(p) => p.clone(),
-        None => {
-            let slug = reposlug(work_dir)?;
-            PathBuf::from("/tmp/rwl").join(slug)
-        }
+        None => default_session_base(work_dir)?,
     };
     let timestamp = chrono::Utc::now().format("%Y%m%d-%H%M%S").to_string();
-    let session_dir = base.join(&timestamp);
-    std::fs::create_dir_all(&session_dir).context("Failed to create session directory")?;
+    std::fs::create_dir_all(&base).context("Failed to create session directory")?;
+    // Queued plans can start within the same second: never share a session.
+    let mut session_dir = base.join(&timestamp);
+    let mut n = 1;
+    while session_dir.exists() {
+        n += 1;
+        session_dir = base.join(format!("{}-{}", timestamp, n));
+    }
+    std::fs::create_dir(&session_dir).context("Failed to create session directory")?;
     Ok(session_dir)
 }
 
@@ -159,6 +369,26 @@ fn ensure_initialized(work_dir: &Path) -> Result<()> {
     Ok(())
 }
 
+/// The copy of the plan the agent works on: the worktree's, when the plan is
+/// part of the project and checked out there, so ticked checklist items are
+/// read back from (and committed to) the run's branch. Otherwise the plan
+/// itself.
+pub(crate) fn plan_in_work_dir(cwd: &Path, work_dir: &Path, plan_path: PathBuf) -> PathBuf {
+    let Ok(project) = cwd.canonicalize() else {
+        return plan_path;
+    };
+    let Ok(relative) = plan_path.strip_prefix(&project) else {
+        return plan_path;
+    };
+    match work_dir.join(relative).canonicalize() {
+        Ok(copy) if copy.is_file() => {
+            debug!("plan_in_work_dir: using {}", copy.display());
+            copy
+        }
+        _ => plan_path,
+    }
+}
+
 fn ensure_plan_exists(plan_path: &Path) -> Result<()> {
     if !plan_path.exists() {
         return Err(eyre::eyre!("Plan file not found: {}", plan_path.display()));
@@ -166,20 +396,52 @@ fn ensure_plan_exists(plan_path: &Path) -> Result<()> {
     Ok(())
 }
 
-fn print_banner(config: &Config, plan_path: &Path, session_dir: &Path, branch: Option<&str>) -> Result<()> {
+fn print_banner(
+    config: &Config,
+    plan_path: &Path,
+    session_dir: &Path,
+    branch: Option<&str>,
+    prompt: &PromptSource,
+) -> Result<()> {
     println!();
     println!("{}", "╔════════════════════════════════════════╗".cyan());
     println!("{}", "║     Ralph Wiggum Loop - Starting       ║".cyan());
     println!("{}", "╚════════════════════════════════════════╝".cyan());
     println!();
     println!("  {} {}", "Plan:".bold(), plan_path.display());
-    println!("  {} {}", "Model:".bold(), config.llm.model);
+    match prompt {
+        PromptSource::Project(_) => println!(
+            "  {} {} {}",
+            "Prompt:".bold(),
+            prompt,
+            "⚠ overrides the built-in template".yellow()
+        ),
+        _ => println!("  {} {}", "Prompt:".bold(), prompt),
+    }
+    println!("  {} {:?}", "Backend:".bold(), config.llm.backend);
+    if config.llm.escalation.is_empty() {
+        println!("  {} {}", "Model:".bold(), config.llm.model);
+    } else {
+        println!(
+            "  {} {} (after {} consecutive failures)",
+            "Escalation:".bold(),
+            config.llm.escalation.join(" -> "),
+            config.llm.escalate_after
+        );
+    }
     println!("  {} {}", "Max iterations:".bold(), config.loop_config.max_iterations);
     println!(
         "  {} {} minutes",
         "Timeout:".bold(),
         config.loop_config.iteration_timeout_minutes
     );
+    if config.loop_config.stall_timeout_minutes > 0 {
+        println!(
+            "  {} {} minutes without output",
+            "Stall timeout:".bold(),
+            config.loop_config.stall_timeout_minutes
+        );
+    }
     println!("  {} {}", "Validation:".bold(), config.validation.command);
     println!("  {} {}", "Quality gates:".bold(), config.quality_gates.len());
     println!("  {} {:?}", "Isolation:".bold(), config.safety.isolation);
@@ -235,8 +497,10 @@ fn print_outcome(result: &RunResult) -> Result<()> {
             }
             println!();
             println!(
-                "  The wall-clock cap ({}) was reached. Adjust it to run longer.",
-                "max-total-minutes".cyan()
+                "  A budget cap ({}, {} or {}) was reached. Adjust it to run longer.",
+                "max-total-minutes".cyan(),
+                "max-cost-usd".cyan(),
+                "max-total-tokens".cyan()
             );
         }
         _ => {
@@ -250,7 +514,36 @@ fn print_outcome(result: &RunResult) -> Result<()> {
             }
         }
     }
+
+    // Passive cost display (stream-json diagnostics mode only).
+    if let Some(ref usage) = result.usage {
+        println!();
+        println!("  {} {}", "Reported usage:".bold(), usage.total);
+    }
     println!();
 
     Ok(())
 }
+
+/// One line per queued plan, after the last plan's own outcome.
+fn print_queue(batch: &BatchResult) {
+    let complete = batch.plans.iter().filter(|plan| plan.complete()).count();
+    println!();
+    println!("{} {}/{} plans complete", "Queue:".bold(), complete, batch.plans.len());
+    for plan in &batch.plans {
+        let mark = match plan.outcome.as_str() {
+            "complete" => "✓".green(),
+            "skipped" => "·".dimmed(),
+            _ => "✗".red(),
+        };
+        let mut line = format!("  {} {} {}", mark, plan.plan, plan.outcome);
+        if plan.exit_code.is_some() {
+            line.push_str(&format!(" ({} iterations)", plan.iterations));
+        }
+        if let Some(ref branch) = plan.branch {
+            line.push_str(&format!(" {}", branch.dimmed()));
+        }
+        println!("{}", line);
+    }
+    println!();
+}
@@ -1,7 +1,9 @@
 use crate::cli::Cli;
+use crate::commands::run::{default_session_base, last_session};
 use crate::config::Config;
 use crate::git::GitManager;
-use crate::progress::ProgressTracker;
+use crate::plan::PhaseStatus;
+use crate::progress::{IterationRecord, ProgressTracker};
 use colored::*;
 use eyre::Result;
 use std::path::Path;
@@ -26,6 +28,7 @@ pub fn run(_cli: &Cli) -> Result<()> {
     let config = Config::load_local(work_dir)?;
 
     println!("{}", "Configuration:".bold());
+    println!("  Backend: {:?}", config.llm.backend);
     println!("  Model: {}", config.llm.model.cyan());
     println!("  Max iterations: {}", config.loop_config.max_iterations);
     println!("  Validation: {}", config.validation.command.dimmed());
@@ -33,8 +36,7 @@ pub fn run(_cli: &Cli) -> Result<()> {
     println!();
 
     // 3. Read progress
-    let progress_path = rwl_dir.join("progress.txt");
-    let tracker = ProgressTracker::new(&progress_path);
+    let tracker = latest_progress(work_dir)?;
 
     if !tracker.exists() {
         println!("{}", "Progress:".bold());
@@ -48,41 +50,27 @@ pub fn run(_cli: &Cli) -> Result<()> {
     }
 
     let progress = tracker.read()?;
-    let iteration_count = tracker.iteration_count()?;
 
     println!("{}", "Progress:".bold());
+    println!("  Log: {}", tracker.path().display().to_string().dimmed());
     if let Some(started) = progress.started {
         println!("  Started: {}", started.format("%Y-%m-%d %H:%M:%S UTC"));
     }
     if let Some(plan_path) = &progress.plan_path {
         println!("  Plan: {}", plan_path.cyan());
     }
-    println!("  Iterations: {}", iteration_count.to_string().bold());
-
-    if let Some(status) = &progress.last_status {
-        println!("  Status: {}", status);
+    println!("  Iterations: {}", progress.iterations.len().to_string().bold());
+    if let Some(last) = progress.iterations.last() {
+        println!("  Status: {}", last.summary);
+        print_phases(&last.phases);
     }
     println!();
 
-    // 4. Show recent progress entries (last 5 lines of meaningful content)
-    let content = tracker.raw_content()?;
-    let recent_lines: Vec<&str> = content
-        .lines()
-        .filter(|l| l.starts_with("##") || l.starts_with("Validation:") || l.starts_with("Promise:"))
-        .collect();
-
-    if !recent_lines.is_empty() {
+    // 4. Show the most recent iterations
+    if !progress.iterations.is_empty() {
         println!("{}", "Recent Activity:".bold());
-        for line in recent_lines.iter().rev().take(10).rev() {
-            if line.starts_with("##") {
-                println!("  {}", line.cyan());
-            } else if line.contains("PASSED") {
-                println!("    {}", line.green());
-            } else if line.contains("FAILED") {
-                println!("    {}", line.red());
-            } else {
-                println!("    {}", line.dimmed());
-            }
+        for record in progress.iterations.iter().rev().take(5).rev() {
+            print_record(record);
         }
         println!();
     }
@@ -114,3 +102,93 @@ pub fn run(_cli: &Cli) -> Result<()> {
 
     Ok(())
 }
+
+/// The progress log of the most recent run: the project's own
+/// `.rwl/progress.jsonl` (`isolation: none`), the one in the worktree of the
+/// session the last run recorded (wherever `--session-path` put it), or the
+/// one in the newest session's worktree under the default session base,
+/// whichever was written last.
+fn latest_progress(work_dir: &Path) -> Result<ProgressTracker> {
+    let mut candidates = vec![ProgressTracker::in_work_dir(work_dir)];
+    if let Some(session) = last_session(work_dir)
+        && session.join("worktree").is_dir()
+    {
+        candidates.push(ProgressTracker::in_work_dir(&session.join("worktree")));
+    }
+    let base = default_session_base(work_dir)?;
+    if let Ok(entries) = std::fs::read_dir(&base) {
+        let newest = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.join("worktree").is_dir())
+            .max();
+        if let Some(session) = newest {
+            candidates.push(ProgressTracker::in_work_dir(&session.join("worktree")));
+        }
+    }
+    let written = |tracker: &ProgressTracker| std::fs::metadata(tracker.path()).and_then(|m| m.modified()).ok();
+    let latest = candidates
+        .iter()
+        .enumerate()
+        .filter_map(|(i, tracker)| written(tracker).map(|time| (time, i)))
+        .max()
+        .map_or(0, |(_, i)| i);
+    Ok(candidates.swap_remove(latest))
+}
+
+/// Phase completion of a phased plan, one line per phase.
+fn print_phases(phases: &[PhaseStatus]) {
+    if phases.is_empty() {
+        return;
+    }
+    let complete = phases.iter().filter(|p| p.complete).count();
+    println!("  Phases: {}/{} complete", complete, phases.len());
+    for phase in phases {
+        let mark = if phase.complete { "✓".green() } else { "·".dimmed() };
+        println!("    {} {} ({}/{})", mark, phase.title, phase.checked, phase.total);
+    }
+}
+
+/// One iteration of the Recent Activity list.
+fn print_record(record: &IterationRecord) {
+    let model = record.model.as_deref().unwrap_or_default();
+    println!(
+        "  {} {}",
+        format!("Iteration {}", record.iteration).cyan(),
+        model.dimmed()
+    );
+    let validation = match record.validation_passed {
+        Some(true) => "PASSED".green(),
+        Some(false) => "FAILED".red(),
+        None if record.stalled => "SKIPPED (agent stalled)".yellow(),
+        None => "NOT RUN".dimmed(),
+    };
+    let promise = if record.promise_found { "FOUND".green() } else { "NOT FOUND".dimmed() };
+    println!("    Validation: {}  Promise: {}", validation, promise);
+    let failed: Vec<&str> = record
+        .gates
+        .iter()
+        .filter(|g| !g.passed)
+        .map(|g| g.name.as_str())
+        .collect();
+    if !record.gates.is_empty() {
+        if failed.is_empty() {
+            println!("    Quality gates: {}", "PASSED".green());
+        } else {
+            println!("    Quality gates: {} {}", "FAILED".red(), failed.join(", "));
+        }
+    }
+    if let Some(judge) = &record.judge {
+        println!(
+            "    Judge: {}",
+            if judge.passed { "PASSED".green() } else { "REJECTED".red() }
+        );
+    }
+    if !record.reverted_paths.is_empty() {
+        println!(
+            "    {} Reverted: {}",
+            "⚠".yellow(),
+            record.reverted_paths.join(", ").dimmed()
+        );
+    }
+}
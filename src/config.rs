@@ -9,8 +9,22 @@ use std::path::{Path, PathBuf};
 pub struct LoopConfig {
     pub max_iterations: u32,
     pub iteration_timeout_minutes: u32,
+    /// Kill the agent after this many minutes without a line of output, well
+    /// before the iteration timeout; the iteration is recorded as stalled and
+    /// the loop moves on. `0` disables the watchdog.
+    #[serde(rename = "stall-timeout-minutes")]
+    pub stall_timeout_minutes: u32,
+    /// Seconds the agent's process group gets between SIGTERM and SIGKILL
+    /// whenever it is stopped (timeout, stall, Ctrl-C, leftovers after exit).
+    #[serde(rename = "kill-grace-secs")]
+    pub kill_grace_secs: u64,
     pub sleep_between_secs: u64,
     pub completion_signal: String,
+    /// Accept the completion signal only once every `- [ ]` item of the plan
+    /// (as the agent left it) is checked off; until then it is rejected and
+    /// the unchecked items are fed back.
+    #[serde(rename = "require-checklist")]
+    pub require_checklist: bool,
 }
 
 impl Default for LoopConfig {
@@ -18,8 +32,11 @@ impl Default for LoopConfig {
         Self {
             max_iterations: 100,
             iteration_timeout_minutes: 10,
+            stall_timeout_minutes: 0,
+            kill_grace_secs: 10,
             sleep_between_secs: 2,
             completion_signal: "<promise>COMPLETE</promise>".to_string(),
+            require_checklist: false,
         }
     }
 }
@@ -61,23 +78,185 @@ impl QualityGate {
     }
 }
 
+/// Which coding-agent CLI drives each iteration (see `agent.rs`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Backend {
+    /// Claude Code (`claude --print`). The default.
+    #[default]
+    Claude,
+    /// OpenAI Codex CLI (`codex exec`).
+    Codex,
+    /// Aider (`aider --message`).
+    Aider,
+    /// A user-supplied argv template in `llm.command`.
+    Command,
+}
+
+/// How the agent's stdout is produced and read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// Plain prose (`claude --print`). The default.
+    #[default]
+    Text,
+    /// Opt-in diagnostics mode: `--output-format stream-json --verbose`, parsed
+    /// fail-soft to capture Claude's reported cost and token usage. Claude only.
+    StreamJson,
+}
+
 /// LLM configuration
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(default)]
 pub struct LlmConfig {
+    pub backend: Backend,
     pub model: String,
     pub dangerously_skip_permissions: bool,
+    /// `text` (default) or `stream-json` for per-iteration cost/usage capture.
+    #[serde(rename = "output-format")]
+    pub output_format: OutputFormat,
+    /// `argv` (default), `stdin` or `file`; the judge uses the same transport.
+    #[serde(rename = "prompt-transport")]
+    pub prompt_transport: PromptTransport,
+    /// Argv template for a generic CLI agent. Non-empty selects the command
+    /// backend; each element is rendered with Handlebars (`{{model}}`,
+    /// `{{prompt}}`, `{{prompt_file}}`, `{{work_dir}}`, `{{skip_permissions}}`).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub command: Vec<String>,
+    /// Model ladder, weakest first. Non-empty replaces `model`: the run starts
+    /// on the first rung and climbs after `escalate-after` consecutive
+    /// failing iterations.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub escalation: Vec<String>,
+    /// Consecutive validation failures before moving up the ladder. `0` = never.
+    #[serde(rename = "escalate-after")]
+    pub escalate_after: u32,
+    /// Retry policy for transient agent failures within one iteration.
+    pub retry: RetryConfig,
+    /// Pausing (rather than burning iterations) when the agent is rate-limited.
+    #[serde(rename = "rate-limit")]
+    pub rate_limit: RateLimitConfig,
 }
 
 impl Default for LlmConfig {
     fn default() -> Self {
         Self {
+            backend: Backend::default(),
             model: "opus".to_string(),
             dangerously_skip_permissions: true,
+            output_format: OutputFormat::default(),
+            prompt_transport: PromptTransport::default(),
+            command: Vec::new(),
+            escalation: Vec::new(),
+            escalate_after: 3,
+            retry: RetryConfig::default(),
+            rate_limit: RateLimitConfig::default(),
         }
     }
 }
 
+/// Rate-limit pausing (`llm.rate-limit`).
+///
+/// When an attempt fails with output matching `llm.retry.rate-limit-patterns`,
+/// the loop sleeps until the reset time in the message (or
+/// `default-wait-minutes` when there is none) and re-runs the same iteration,
+/// so the wait never consumes `max_iterations`. The wall-clock budget still
+/// bounds every pause.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// Cap on total pause time within one iteration, in minutes. `0` disables pausing.
+    pub max_wait_minutes: u64,
+    /// Pause used when the message carries no parseable reset time, in minutes.
+    pub default_wait_minutes: u64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            max_wait_minutes: 60,
+            default_wait_minutes: 5,
+        }
+    }
+}
+
+/// A class of agent failure that `llm.retry` may retry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RetryOn {
+    /// The agent ran past `iteration_timeout_minutes` and was killed.
+    Timeout,
+    /// The agent exited non-zero (or was killed by a signal).
+    NonZeroExit,
+    /// The agent exited non-zero and its output matched a rate-limit pattern.
+    RateLimit,
+}
+
+impl std::fmt::Display for RetryOn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RetryOn::Timeout => "timeout",
+            RetryOn::NonZeroExit => "non-zero-exit",
+            RetryOn::RateLimit => "rate-limit",
+        })
+    }
+}
+
+/// Retry policy for transient agent failures (`llm.retry`).
+///
+/// A retried attempt re-runs the same prompt within the same iteration, after
+/// an exponential backoff (`backoff-secs`, doubling, capped at
+/// `max-backoff-secs`). Failures outside `retry-on`, or on the last attempt,
+/// behave exactly as without a policy.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RetryConfig {
+    /// Attempts per iteration, the first included. `1` = never retry.
+    pub max_attempts: u32,
+    /// Delay before the first retry, in seconds.
+    pub backoff_secs: u64,
+    /// Upper bound on any single backoff delay, in seconds.
+    pub max_backoff_secs: u64,
+    /// Which failure classes are retryable.
+    pub retry_on: Vec<RetryOn>,
+    /// Words or phrases that mark a failed run as rate-limited when they
+    /// appear, case-insensitively and as whole words, in the last lines of
+    /// its stdout or stderr.
+    pub rate_limit_patterns: Vec<String>,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff_secs: 30,
+            max_backoff_secs: 600,
+            retry_on: vec![RetryOn::Timeout, RetryOn::NonZeroExit, RetryOn::RateLimit],
+            rate_limit_patterns: vec![
+                "rate limit".to_string(),
+                "rate_limit".to_string(),
+                "usage limit".to_string(),
+                "too many requests".to_string(),
+                "overloaded_error".to_string(),
+            ],
+        }
+    }
+}
+
+/// How the rendered prompt reaches the agent (`llm.prompt-transport`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PromptTransport {
+    /// As a single argv element (visible in `ps`, bounded by `ARG_MAX`).
+    #[default]
+    Argv,
+    /// Piped to the agent's stdin.
+    Stdin,
+    /// Via the per-iteration `prompt.md` in the session directory; argv only
+    /// carries a short pointer to it (or the backend's prompt-file flag).
+    File,
+}
+
 /// Isolation strategy for containing the agent's blast radius.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
@@ -115,29 +294,108 @@ impl Default for SafetyConfig {
 
 /// Budget / backpressure configuration.
 ///
-/// Wall-clock only (Option A per the design doc's Addendum): no cost or token
-/// caps. A `max_total_minutes` of `0` means unlimited.
+/// The wall-clock cap is the core stop mechanism (Option A per the design
+/// doc's Addendum). The cost and token caps are enforced on the agent's
+/// self-reported usage and therefore require `llm.output-format: stream-json`.
+/// They count every agent attempt, retries included, but not the judge or
+/// progress-summary calls, which run in `text` mode and report no usage.
+/// Every cap of `0` means unlimited.
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 #[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
 pub struct BudgetConfig {
     /// Wall-clock cap across the whole run, in minutes. `0` = unlimited.
     pub max_total_minutes: u64,
+    /// Reported-spend cap across the whole run, in USD. `0` = unlimited.
+    pub max_cost_usd: f64,
+    /// Reported-token cap (input + output + cache) across the run. `0` = unlimited.
+    pub max_total_tokens: u64,
+}
+
+impl BudgetConfig {
+    /// Whether a cap that depends on `stream-json` usage capture is set.
+    pub fn needs_usage(&self) -> bool {
+        self.max_cost_usd > 0.0 || self.max_total_tokens > 0
+    }
+}
+
+/// Prompt template configuration.
+///
+/// The template is resolved once at startup: `template` when set, else the
+/// project's `.rwl/PROMPT.md`, else the built-in template (see
+/// `templates::load_prompt_template`).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PromptConfig {
+    /// Handlebars template file, relative to the project root.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<PathBuf>,
+    /// How many recent commits the `recent_commits` variable lists.
+    pub recent_commits: usize,
+}
+
+impl Default for PromptConfig {
+    fn default() -> Self {
+        Self {
+            template: None,
+            recent_commits: 10,
+        }
+    }
+}
+
+/// How an oversized progress log is compacted for the prompt (`progress.strategy`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProgressStrategy {
+    /// Keep the last `keep-iterations` iterations verbatim and one line about
+    /// the earlier ones.
+    #[default]
+    Tail,
+    /// Fold earlier iterations into a digest written by `summary-model`.
+    Summarize,
+}
+
+/// Progress log compaction.
+///
+/// The log on disk (`.rwl/progress.txt`) always keeps the full history; these
+/// settings only bound how much of it is inlined into each prompt.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ProgressConfig {
+    /// Size above which the inlined progress is compacted. `0` = never.
+    pub max_bytes: usize,
+    pub strategy: ProgressStrategy,
+    /// Most recent iterations always inlined verbatim.
+    pub keep_iterations: u32,
+    /// Model that writes the `summarize` digest (default: `llm.model`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary_model: Option<String>,
+}
+
+impl Default for ProgressConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: 0,
+            strategy: ProgressStrategy::default(),
+            keep_iterations: 5,
+            summary_model: None,
+        }
+    }
 }
 
 /// LLM-as-judge configuration (optional).
 ///
-/// When present in `Config.judge`, a fresh Claude invocation is run as a final
+/// When present in `Config.judge`, a fresh invocation of the `llm.backend` agent is run as a final
 /// gate after validation and quality gates pass. Absent -> no judge runs,
 /// mirroring how `quality_gates` being configured = active.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct JudgeConfig {
-    /// Claude model to use for the judge call (e.g. "opus", "sonnet").
+    /// Model to use for the judge call (e.g. "opus", "sonnet").
     pub model: String,
     /// Binary verdict token that must appear on its own line (line-exact).
     /// E.g. `"<judge>PASS</judge>"`.
     pub signal: String,
-    /// The full prompt sent to the judge Claude invocation.
+    /// The full prompt sent to the judge invocation.
     pub prompt: String,
 }
 
@@ -173,6 +431,10 @@ pub struct Config {
     pub safety: SafetyConfig,
     #[serde(default)]
     pub budget: BudgetConfig,
+    #[serde(default)]
+    pub prompt: PromptConfig,
+    #[serde(default)]
+    pub progress: ProgressConfig,
     /// Optional LLM-as-judge gate. Absent -> no judge runs.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub judge: Option<JudgeConfig>,
@@ -199,6 +461,8 @@ impl Default for Config {
             git: GitConfig::default(),
             safety: SafetyConfig::default(),
             budget: BudgetConfig::default(),
+            prompt: PromptConfig::default(),
+            progress: ProgressConfig::default(),
             judge: None,
         }
     }
@@ -320,8 +584,11 @@ mod tests {
         assert_eq!(config.loop_config.sleep_between_secs, 2);
         assert_eq!(config.loop_config.completion_signal, "<promise>COMPLETE</promise>");
         assert_eq!(config.validation.command, "otto ci");
+        assert_eq!(config.llm.backend, Backend::Claude);
         assert_eq!(config.llm.model, "opus");
         assert!(config.llm.dangerously_skip_permissions);
+        assert!(config.llm.command.is_empty());
+        assert_eq!(config.llm.output_format, OutputFormat::Text);
         assert!(config.git.auto_commit);
         // Safety defaults: worktree isolation + baseline protected paths.
         assert_eq!(config.safety.isolation, Isolation::Worktree);
@@ -384,6 +651,9 @@ safety:
     fn test_budget_default_is_unlimited() {
         let config = Config::default();
         assert_eq!(config.budget.max_total_minutes, 0);
+        assert_eq!(config.budget.max_cost_usd, 0.0);
+        assert_eq!(config.budget.max_total_tokens, 0);
+        assert!(!config.budget.needs_usage());
     }
 
     #[test]
@@ -416,6 +686,25 @@ budget:
         assert_eq!(config.budget.max_total_minutes, 45);
     }
 
+    #[test]
+    fn test_budget_parses_cost_and_token_caps() {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join("test-config.yml");
+        let yaml = r#"
+budget:
+  max-cost-usd: 5.0
+  max-total-tokens: 2000000
+"#;
+        let mut file = fs::File::create(&config_path).unwrap();
+        file.write_all(yaml.as_bytes()).unwrap();
+
+        let config = Config::load_from_file(&config_path).unwrap();
+        assert_eq!(config.budget.max_total_minutes, 0);
+        assert_eq!(config.budget.max_cost_usd, 5.0);
+        assert_eq!(config.budget.max_total_tokens, 2_000_000);
+        assert!(config.budget.needs_usage());
+    }
+
     #[test]
     fn test_budget_rejects_unknown_field() {
         let dir = tempdir().unwrap();
@@ -423,7 +712,7 @@ budget:
         let yaml = r#"
 budget:
   max-total-minutes: 10
-  max-cost-usd: 5.0
+  max-dollars: 5.0
 "#;
         let mut file = fs::File::create(&config_path).unwrap();
         file.write_all(yaml.as_bytes()).unwrap();
@@ -489,6 +778,211 @@ judge:
         assert!(config.judge.is_none());
     }
 
+    #[test]
+    fn test_prompt_template_parses_and_defaults_to_none() {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join("test-config.yml");
+        let yaml = r#"
+prompt:
+  template: "prompts/ralph.md"
+  recent-commits: 3
+"#;
+        let mut file = fs::File::create(&config_path).unwrap();
+        file.write_all(yaml.as_bytes()).unwrap();
+
+        let config = Config::load_from_file(&config_path).unwrap();
+        assert_eq!(config.prompt.template, Some(PathBuf::from("prompts/ralph.md")));
+        assert_eq!(config.prompt.recent_commits, 3);
+        assert!(Config::default().prompt.template.is_none());
+        assert_eq!(Config::default().prompt.recent_commits, 10);
+    }
+
+    #[test]
+    fn test_progress_compaction_parses_kebab_case() {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join("test-config.yml");
+        let yaml = r#"
+progress:
+  max-bytes: 20000
+  strategy: summarize
+  keep-iterations: 3
+  summary-model: haiku
+"#;
+        let mut file = fs::File::create(&config_path).unwrap();
+        file.write_all(yaml.as_bytes()).unwrap();
+
+        let config = Config::load_from_file(&config_path).unwrap();
+        assert_eq!(config.progress.max_bytes, 20000);
+        assert_eq!(config.progress.strategy, ProgressStrategy::Summarize);
+        assert_eq!(config.progress.keep_iterations, 3);
+        assert_eq!(config.progress.summary_model.as_deref(), Some("haiku"));
+
+        let default = Config::default().progress;
+        assert_eq!(default.max_bytes, 0);
+        assert_eq!(default.strategy, ProgressStrategy::Tail);
+    }
+
+    #[test]
+    fn test_llm_backend_parses() {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join("test-config.yml");
+        let yaml = r#"
+llm:
+  backend: command
+  model: "local-7b"
+  command: ["my-agent", "--model", "{{model}}", "{{prompt}}"]
+"#;
+        let mut file = fs::File::create(&config_path).unwrap();
+        file.write_all(yaml.as_bytes()).unwrap();
+
+        let config = Config::load_from_file(&config_path).unwrap();
+        assert_eq!(config.llm.backend, Backend::Command);
+        assert_eq!(config.llm.command.len(), 4);
+        assert_eq!(config.llm.command[2], "{{model}}");
+    }
+
+    #[test]
+    fn test_llm_output_format_parses_kebab_case() {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join("test-config.yml");
+        let yaml = r#"
+llm:
+  model: "opus"
+  output-format: stream-json
+"#;
+        let mut file = fs::File::create(&config_path).unwrap();
+        file.write_all(yaml.as_bytes()).unwrap();
+
+        let config = Config::load_from_file(&config_path).unwrap();
+        assert_eq!(config.llm.output_format, OutputFormat::StreamJson);
+    }
+
+    #[test]
+    fn test_llm_retry_parses_and_defaults() {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join("test-config.yml");
+        let yaml = r#"
+llm:
+  model: "opus"
+  retry:
+    max-attempts: 4
+    backoff-secs: 5
+    retry-on: [timeout, rate-limit]
+"#;
+        let mut file = fs::File::create(&config_path).unwrap();
+        file.write_all(yaml.as_bytes()).unwrap();
+
+        let config = Config::load_from_file(&config_path).unwrap();
+        let retry = &config.llm.retry;
+        assert_eq!(retry.max_attempts, 4);
+        assert_eq!(retry.backoff_secs, 5);
+        assert_eq!(retry.max_backoff_secs, 600);
+        assert_eq!(retry.retry_on, vec![RetryOn::Timeout, RetryOn::RateLimit]);
+        assert!(retry.rate_limit_patterns.contains(&"rate limit".to_string()));
+        assert_eq!(LlmConfig::default().retry.max_attempts, 1);
+    }
+
+    #[test]
+    fn test_llm_prompt_transport_parses() {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join("test-config.yml");
+        let yaml = r#"
+llm:
+  prompt-transport: stdin
+"#;
+        let mut file = fs::File::create(&config_path).unwrap();
+        file.write_all(yaml.as_bytes()).unwrap();
+
+        let config = Config::load_from_file(&config_path).unwrap();
+        assert_eq!(config.llm.prompt_transport, PromptTransport::Stdin);
+        assert_eq!(LlmConfig::default().prompt_transport, PromptTransport::Argv);
+    }
+
+    #[test]
+    fn test_llm_escalation_parses() {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join("test-config.yml");
+        let yaml = r#"
+llm:
+  escalation: [sonnet, opus]
+  escalate-after: 2
+"#;
+        let mut file = fs::File::create(&config_path).unwrap();
+        file.write_all(yaml.as_bytes()).unwrap();
+
+        let config = Config::load_from_file(&config_path).unwrap();
+        assert_eq!(config.llm.escalation, vec!["sonnet", "opus"]);
+        assert_eq!(config.llm.escalate_after, 2);
+        assert_eq!(LlmConfig::default().escalate_after, 3);
+    }
+
+    #[test]
+    fn test_llm_rate_limit_parses_kebab_case() {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join("test-config.yml");
+        let yaml = r#"
+llm:
+  rate-limit:
+    max-wait-minutes: 300
+"#;
+        let mut file = fs::File::create(&config_path).unwrap();
+        file.write_all(yaml.as_bytes()).unwrap();
+
+        let config = Config::load_from_file(&config_path).unwrap();
+        assert_eq!(config.llm.rate_limit.max_wait_minutes, 300);
+        assert_eq!(config.llm.rate_limit.default_wait_minutes, 5);
+    }
+
+    #[test]
+    fn test_llm_retry_rejects_unknown_class() {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join("test-config.yml");
+        let yaml = r#"
+llm:
+  retry:
+    retry-on: [crash]
+"#;
+        let mut file = fs::File::create(&config_path).unwrap();
+        file.write_all(yaml.as_bytes()).unwrap();
+
+        assert!(Config::load_from_file(&config_path).is_err());
+    }
+
+    #[test]
+    fn test_stall_timeout_parses_kebab_case() {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join("test-config.yml");
+        let yaml = r#"
+loop:
+  iteration_timeout_minutes: 30
+  stall-timeout-minutes: 4
+  require-checklist: true
+"#;
+        let mut file = fs::File::create(&config_path).unwrap();
+        file.write_all(yaml.as_bytes()).unwrap();
+
+        let config = Config::load_from_file(&config_path).unwrap();
+        assert_eq!(config.loop_config.stall_timeout_minutes, 4);
+        assert_eq!(config.loop_config.kill_grace_secs, 10);
+        assert!(config.loop_config.require_checklist);
+        assert_eq!(Config::default().loop_config.stall_timeout_minutes, 0);
+        assert!(!Config::default().loop_config.require_checklist);
+    }
+
+    #[test]
+    fn test_llm_backend_rejects_unknown() {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join("test-config.yml");
+        let yaml = r#"
+llm:
+  backend: gemini
+"#;
+        let mut file = fs::File::create(&config_path).unwrap();
+        file.write_all(yaml.as_bytes()).unwrap();
+
+        assert!(Config::load_from_file(&config_path).is_err());
+    }
+
     #[test]
     fn test_quality_gate_command() {
         let gate = QualityGate {
@@ -0,0 +1,118 @@
+use crate::config::LlmConfig;
+use serde::{Deserialize, Serialize};
+
+/// The model one iteration ran with, as recorded in `result.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IterationModel {
+    pub iteration: u32,
+    pub model: String,
+}
+
+/// The model ladder a run climbs when it is visibly stuck (`llm.escalation`).
+///
+/// Starts on the first rung and moves up one rung after `escalate-after`
+/// consecutive failing iterations, resetting the streak on every move. It never
+/// steps back down, and stays on the top rung once there. With no
+/// `llm.escalation` configured the ladder is the single rung `llm.model`.
+#[derive(Debug, Clone)]
+pub struct ModelLadder {
+    rungs: Vec<String>,
+    after: u32,
+    rung: usize,
+    failures: u32,
+}
+
+impl ModelLadder {
+    pub fn new(llm: &LlmConfig) -> Self {
+        let rungs = if llm.escalation.is_empty() {
+            vec![llm.model.clone()]
+        } else {
+            llm.escalation.clone()
+        };
+        Self {
+            rungs,
+            after: llm.escalate_after,
+            rung: 0,
+            failures: 0,
+        }
+    }
+
+    /// The model the next iteration runs with.
+    pub fn current(&self) -> &str {
+        &self.rungs[self.rung]
+    }
+
+    /// Record whether an iteration passed validation.
+    ///
+    /// Returns the new model when this failure completed a streak and the
+    /// ladder climbed a rung.
+    pub fn record(&mut self, passed: bool) -> Option<&str> {
+        if passed {
+            self.failures = 0;
+            return None;
+        }
+        self.failures += 1;
+        if self.after == 0 || self.failures < self.after || self.rung + 1 >= self.rungs.len() {
+            return None;
+        }
+        self.rung += 1;
+        self.failures = 0;
+        Some(self.current())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn ladder(rungs: &[&str], after: u32) -> ModelLadder {
+        ModelLadder::new(&LlmConfig {
+            escalation: rungs.iter().map(|r| r.to_string()).collect(),
+            escalate_after: after,
+            ..LlmConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_no_escalation_is_single_rung_of_llm_model() {
+        let mut ladder = ladder(&[], 1);
+        assert_eq!(ladder.current(), "opus");
+        assert_eq!(ladder.record(false), None);
+        assert_eq!(ladder.current(), "opus");
+    }
+
+    #[test]
+    fn test_climbs_after_consecutive_failures() {
+        let mut ladder = ladder(&["haiku", "sonnet", "opus"], 2);
+        assert_eq!(ladder.current(), "haiku");
+        assert_eq!(ladder.record(false), None);
+        assert_eq!(ladder.record(false), Some("sonnet"));
+        assert_eq!(ladder.record(false), None);
+        assert_eq!(ladder.record(false), Some("opus"));
+        // Top rung: stays put.
+        assert_eq!(ladder.record(false), None);
+        assert_eq!(ladder.record(false), None);
+        assert_eq!(ladder.current(), "opus");
+    }
+
+    #[test]
+    fn test_pass_resets_the_streak_without_stepping_down() {
+        let mut ladder = ladder(&["sonnet", "opus"], 2);
+        ladder.record(false);
+        ladder.record(true);
+        assert_eq!(ladder.record(false), None);
+        assert_eq!(ladder.record(false), Some("opus"));
+        ladder.record(true);
+        assert_eq!(ladder.current(), "opus");
+    }
+
+    #[test]
+    fn test_escalate_after_zero_never_climbs() {
+        let mut ladder = ladder(&["sonnet", "opus"], 0);
+        for _ in 0..5 {
+            assert_eq!(ladder.record(false), None);
+        }
+        assert_eq!(ladder.current(), "sonnet");
+    }
+}
@@ -121,6 +121,110 @@ impl GitManager {
         Ok(stdout.lines().map(|s| s.to_string()).collect())
     }
 
+    /// Get the commit HEAD points at
+    pub fn head(&self) -> Result<String> {
+        let output = Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .current_dir(&self.repo_root)
+            .output()
+            .context("Failed to run git rev-parse")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(eyre::eyre!("git rev-parse failed: {}", stderr));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Diff of the working tree against `rev` (commits since plus uncommitted
+    /// changes to tracked files)
+    pub fn diff_since(&self, rev: &str) -> Result<String> {
+        self.diff(&["--binary", rev])
+    }
+
+    /// `git diff --stat` of the working tree against `rev`
+    pub fn diff_stat_since(&self, rev: &str) -> Result<String> {
+        self.diff(&["--stat", rev])
+    }
+
+    /// `git apply --stat` of a patch file: the diffstat of a recorded diff
+    pub fn patch_stat(&self, patch: &Path) -> Result<String> {
+        let output = Command::new("git")
+            .args(["apply", "--stat"])
+            .arg(patch)
+            .current_dir(&self.repo_root)
+            .output()
+            .context("Failed to run git apply")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(eyre::eyre!("git apply --stat failed: {}", stderr));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn diff(&self, args: &[&str]) -> Result<String> {
+        let output = Command::new("git")
+            .arg("diff")
+            .args(args)
+            .current_dir(&self.repo_root)
+            .output()
+            .context("Failed to run git diff")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(eyre::eyre!("git diff failed: {}", stderr));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Move the current branch, index and working tree to `rev`
+    pub fn reset_hard(&self, rev: &str) -> Result<()> {
+        let output = Command::new("git")
+            .args(["reset", "--hard", "-q", rev])
+            .current_dir(&self.repo_root)
+            .output()
+            .context("Failed to run git reset")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(eyre::eyre!("git reset failed: {}", stderr));
+        }
+
+        Ok(())
+    }
+
+    /// Add `pattern` to the repository's local exclude file (`info/exclude`,
+    /// shared by all worktrees) unless it is already listed, so `git status`
+    /// and `git add .` ignore it without touching any tracked `.gitignore`
+    pub fn exclude(&self, pattern: &str) -> Result<()> {
+        let output = Command::new("git")
+            .args(["rev-parse", "--git-path", "info/exclude"])
+            .current_dir(&self.repo_root)
+            .output()
+            .context("Failed to run git rev-parse")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(eyre::eyre!("git rev-parse failed: {}", stderr));
+        }
+
+        let path = self.repo_root.join(String::from_utf8_lossy(&output.stdout).trim());
+        let existing = std::fs::read_to_string(&path).unwrap_or_default();
+        if existing.lines().any(|line| line.trim() == pattern) {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create git info directory")?;
+        }
+        let separator = if existing.is_empty() || existing.ends_with('\n') { "" } else { "\n" };
+        std::fs::write(&path, format!("{}{}{}\n", existing, separator, pattern))
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
     /// Check if the current directory is a git repository
     pub fn is_repo(&self) -> bool {
         Command::new("git")
@@ -216,4 +320,63 @@ mod tests {
         let git = GitManager::new(dir.path());
         assert!(git.has_changes().unwrap());
     }
+
+    #[test]
+    fn test_exclude_hides_path_once() {
+        let dir = tempdir().unwrap();
+        Command::new("git")
+            .args(["init"])
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        std::fs::create_dir_all(dir.path().join(".rwl")).unwrap();
+        std::fs::write(dir.path().join(".rwl/progress.txt"), "log").unwrap();
+
+        let git = GitManager::new(dir.path());
+        assert!(git.has_changes().unwrap());
+        git.exclude("/.rwl/progress.txt").unwrap();
+        git.exclude("/.rwl/progress.txt").unwrap();
+        assert!(!git.has_changes().unwrap());
+
+        let exclude = std::fs::read_to_string(dir.path().join(".git/info/exclude")).unwrap();
+        assert_eq!(exclude.matches("/.rwl/progress.txt").count(), 1);
+    }
+
+    #[test]
+    fn test_diff_since_covers_commits_and_uncommitted_changes() {
+        let dir = tempdir().unwrap();
+        for args in [
+            vec!["init"],
+            vec!["config", "user.email", "test@test.com"],
+            vec!["config", "user.name", "Test"],
+        ] {
+            Command::new("git")
+                .args(&args)
+                .current_dir(dir.path())
+                .output()
+                .unwrap();
+        }
+        let git = GitManager::new(dir.path());
+        std::fs::write(dir.path().join("a.txt"), "one\n").unwrap();
+        git.auto_commit("initial").unwrap();
+        let start = git.head().unwrap();
+
+        std::fs::write(dir.path().join("b.txt"), "new\n").unwrap();
+        git.auto_commit("add b").unwrap();
+        std::fs::write(dir.path().join("a.txt"), "two\n").unwrap();
+
+        let diff = git.diff_since(&start).unwrap();
+        assert!(diff.contains("+new"));
+        assert!(diff.contains("-one"));
+        assert!(diff.contains("+two"));
+        let stat = git.diff_stat_since(&start).unwrap();
+        assert!(stat.contains("a.txt") && stat.contains("b.txt"), "{}", stat);
+        assert!(stat.contains("2 files changed"), "{}", stat);
+        assert_ne!(git.head().unwrap(), start);
+
+        let patch = dir.path().join("diff.patch");
+        std::fs::write(&patch, &diff).unwrap();
+        let replayed = git.patch_stat(&patch).unwrap();
+        assert!(replayed.contains("2 files changed"), "{}", replayed);
+    }
 }
@@ -0,0 +1,113 @@
+use colored::*;
+use eyre::{Context, Result};
+use std::sync::Once;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+/// Set by the first Ctrl-C: finish the current iteration, then stop.
+static STOP: AtomicBool = AtomicBool::new(false);
+
+/// Set by a second Ctrl-C, SIGTERM or SIGHUP: stop the agent's process group
+/// and return `Stopped` right away.
+static ABORT: AtomicBool = AtomicBool::new(false);
+
+/// The signal that last requested a stop (0 = none).
+static SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+static INSTALL: Once = Once::new();
+
+const SIGINT: i32 = 2;
+
+/// Install the process-wide interrupt handlers (idempotent).
+///
+/// * Ctrl-C (SIGINT) is two-stage: the first requests a graceful stop after
+///   the current iteration, the second aborts.
+/// * SIGTERM and SIGHUP abort at once, so CI cancellation and a closed
+///   terminal still unwind through the loop and write `result.json`.
+///
+/// The flags are process-wide, so every runner in the process sees them.
+pub fn install() -> Result<()> {
+    let mut result = Ok(());
+    INSTALL.call_once(|| result = install_handlers());
+    result
+}
+
+fn install_handlers() -> Result<()> {
+    ctrlc::set_handler(|| {
+        SIGNAL.store(SIGINT, Ordering::SeqCst);
+        if STOP.swap(true, Ordering::SeqCst) {
+            ABORT.store(true, Ordering::SeqCst);
+            eprintln!("\n{} Second Ctrl-C, stopping the agent now...", "⚠".yellow());
+        } else {
+            eprintln!(
+                "\n{} Received Ctrl-C, finishing current iteration (Ctrl-C again to abort)...",
+                "⚠".yellow()
+            );
+        }
+    })
+    .context("Failed to set Ctrl-C handler")?;
+
+    #[cfg(unix)]
+    {
+        let handler = on_terminate as extern "C" fn(libc::c_int);
+        for signal in [libc::SIGTERM, libc::SIGHUP] {
+            // SAFETY: the handler only stores to atomics, which is
+            // async-signal-safe.
+            if unsafe { libc::signal(signal, handler as libc::sighandler_t) } == libc::SIG_ERR {
+                return Err(eyre::eyre!("Failed to set handler for signal {}", signal));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+extern "C" fn on_terminate(signal: libc::c_int) {
+    SIGNAL.store(signal, Ordering::SeqCst);
+    STOP.store(true, Ordering::SeqCst);
+    ABORT.store(true, Ordering::SeqCst);
+}
+
+/// A stop was requested (any stage).
+pub fn stop_requested() -> bool {
+    STOP.load(Ordering::SeqCst)
+}
+
+/// An immediate abort was requested.
+pub fn abort_requested() -> bool {
+    ABORT.load(Ordering::SeqCst)
+}
+
+/// The abort flag, for [`crate::agent::Limits::cancel`].
+pub fn abort_flag() -> &'static AtomicBool {
+    &ABORT
+}
+
+/// Human-readable reason for the stop, for `result.json` and the session log.
+pub fn reason() -> String {
+    describe(SIGNAL.load(Ordering::SeqCst), abort_requested())
+}
+
+fn describe(signal: i32, aborted: bool) -> String {
+    match (signal, aborted) {
+        (SIGINT, false) => "Received Ctrl-C".to_string(),
+        (SIGINT, true) => "Received second Ctrl-C, agent stopped".to_string(),
+        #[cfg(unix)]
+        (libc::SIGTERM, _) => "Received SIGTERM, agent stopped".to_string(),
+        #[cfg(unix)]
+        (libc::SIGHUP, _) => "Received SIGHUP, agent stopped".to_string(),
+        (other, _) => format!("Received signal {}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_names_the_stage_and_signal() {
+        assert_eq!(describe(SIGINT, false), "Received Ctrl-C");
+        assert!(describe(SIGINT, true).contains("second Ctrl-C"));
+        assert!(describe(libc::SIGTERM, true).contains("SIGTERM"));
+        assert!(describe(libc::SIGHUP, true).contains("SIGHUP"));
+    }
+}
@@ -1,11 +1,15 @@
-use crate::config::JudgeConfig;
+use crate::agent::{self, AgentBackend, AgentRequest};
+use crate::config::{JudgeConfig, OutputFormat, PromptTransport};
+use crate::interrupt;
 use crate::runner::signal_on_own_line;
 use eyre::{Context, Result};
-use std::io::{BufRead, BufReader};
 use std::path::Path;
-use std::process::{Command, Stdio};
 use std::time::Duration;
 
+/// File (in the iteration's artifact dir) the judge prompt is written to; the
+/// payload of the `file` transport and a record of what the judge was sent.
+const JUDGE_PROMPT_FILE: &str = "judge-prompt.md";
+
 /// Judge call timeout: 10 minutes. The judge prompt is typically short and the
 /// model fast, but a conservative cap prevents hangs.
 const JUDGE_TIMEOUT_SECS: u64 = 600;
@@ -37,112 +41,69 @@ pub(crate) fn detect_verdict(output: &str, signal: &str) -> bool {
 
 /// Run the LLM-as-judge gate.
 ///
-/// Spawns a fresh `claude --print` invocation with the judge's model and
-/// prompt, then detects the verdict with a line-exact signal match. Returns
-/// `(passed, output)` where `output` is the full judge stdout+stderr (used by
-/// the caller to extract an explanation on FAIL).
+/// Spawns a fresh invocation of the configured agent backend with the judge's
+/// model and prompt, then detects the verdict with a line-exact signal match.
+/// Returns `(passed, output)` where `output` is the full judge stdout+stderr
+/// (used by the caller to extract an explanation on FAIL).
 ///
-/// The invocation mirrors `run_claude` in `runner.rs`: `--print`, `--model`,
-/// optional `--dangerously-skip-permissions`, `stdin(Stdio::null())`.
+/// The invocation goes through the same [`agent::run_agent`] envelope as the
+/// loop's own agent call, minus the live echo, and uses the same prompt
+/// transport.
 ///
 /// # Logging
 /// - DEBUG on entry: model and prompt length (never the full prompt - it can be large).
 /// - DEBUG/WARN on verdict outcome.
-pub fn run_judge(config: &JudgeConfig, work_dir: &Path, dangerously_skip_permissions: bool) -> Result<(bool, String)> {
+pub fn run_judge(
+    config: &JudgeConfig,
+    backend: &dyn AgentBackend,
+    work_dir: &Path,
+    artifact_dir: &Path,
+    dangerously_skip_permissions: bool,
+    transport: PromptTransport,
+) -> Result<(bool, String)> {
     log::debug!(
-        "run_judge: model={} prompt_len={} signal={:?} work_dir={}",
+        "run_judge: backend={} model={} prompt_len={} signal={:?} work_dir={}",
+        backend.name(),
         config.model,
         config.prompt.len(),
         config.signal,
         work_dir.display()
     );
 
-    which::which("claude").context("claude CLI not found; required for the judge gate")?;
-
-    let mut cmd = Command::new("claude");
-    cmd.arg("--print").arg("--model").arg(&config.model);
-
-    if dangerously_skip_permissions {
-        cmd.arg("--dangerously-skip-permissions");
-    }
-
-    cmd.arg(&config.prompt)
-        .current_dir(work_dir)
-        .stdin(Stdio::null())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped());
-
-    let mut child = cmd.spawn().context("Failed to spawn claude for judge gate")?;
-
-    // Drain stdout and stderr concurrently to avoid deadlocking on a full pipe buffer.
-    let child_stdout = child.stdout.take();
-    let stdout_handle = std::thread::spawn(move || {
-        let mut captured = String::new();
-        if let Some(stdout) = child_stdout {
-            let reader = BufReader::new(stdout);
-            for line in reader.lines() {
-                match line {
-                    Ok(line) => {
-                        captured.push_str(&line);
-                        captured.push('\n');
-                    }
-                    Err(_) => break,
-                }
-            }
-        }
-        captured
-    });
-
-    let child_stderr = child.stderr.take();
-    let stderr_handle = std::thread::spawn(move || {
-        let mut captured = String::new();
-        if let Some(stderr) = child_stderr {
-            let reader = BufReader::new(stderr);
-            for line in reader.lines() {
-                match line {
-                    Ok(line) => {
-                        captured.push_str(&line);
-                        captured.push('\n');
-                    }
-                    Err(_) => break,
-                }
-            }
-        }
-        captured
-    });
-
-    // Poll with a wall-clock timeout.
-    let start = std::time::Instant::now();
-    let timeout = Duration::from_secs(JUDGE_TIMEOUT_SECS);
-    loop {
-        match child.try_wait().context("Failed to check judge process status")? {
-            Some(_status) => {
-                let stdout = stdout_handle.join().unwrap_or_default();
-                let stderr = stderr_handle.join().unwrap_or_default();
-                let combined = format!("{}\n{}", stdout, stderr);
-
-                let passed = detect_verdict(&combined, &config.signal);
-                if passed {
-                    log::debug!("run_judge: verdict=PASS model={}", config.model);
-                } else {
-                    log::warn!(
-                        "run_judge: verdict=FAIL model={} output_len={}",
-                        config.model,
-                        combined.len()
-                    );
-                }
-                return Ok((passed, combined));
-            }
-            None => {
-                if start.elapsed() >= timeout {
-                    let _ = child.kill();
-                    let _ = child.wait();
-                    return Err(eyre::eyre!("Judge timed out after {} seconds", JUDGE_TIMEOUT_SECS));
-                }
-                std::thread::sleep(Duration::from_millis(500));
-            }
-        }
+    let prompt_file = artifact_dir.join(JUDGE_PROMPT_FILE);
+    let request = AgentRequest {
+        model: &config.model,
+        prompt: &config.prompt,
+        work_dir,
+        prompt_file: &prompt_file,
+        skip_permissions: dangerously_skip_permissions,
+        // The verdict is a plain-text line scan; judge spend is not captured.
+        output_format: OutputFormat::Text,
+        transport,
+    };
+    let output = agent::run_agent(
+        backend,
+        &request,
+        &agent::Limits {
+            cancel: Some(interrupt::abort_flag()),
+            ..agent::Limits::new(Duration::from_secs(JUDGE_TIMEOUT_SECS))
+        },
+        false,
+    )
+    .context("Judge gate invocation failed")?;
+    let combined = output.combined();
+
+    let passed = detect_verdict(&combined, &config.signal);
+    if passed {
+        log::debug!("run_judge: verdict=PASS model={}", config.model);
+    } else {
+        log::warn!(
+            "run_judge: verdict=FAIL model={} output_len={}",
+            config.model,
+            combined.len()
+        );
     }
+    Ok((passed, combined))
 }
 
 #[cfg(test)]
@@ -4,21 +4,30 @@ use log::info;
 use std::fs;
 use std::path::PathBuf;
 
+mod agent;
+mod artifacts;
 mod budget;
 mod cli;
 mod commands;
 mod config;
+mod escalation;
 mod git;
+mod interrupt;
 mod judge;
+mod plan;
+mod process;
 mod progress;
 mod result;
+mod retry;
 mod runner;
 mod safety;
 mod session;
+mod summarize;
 mod templates;
+mod usage;
 mod validation;
 
-use cli::{Cli, Commands};
+use cli::{Cli, Commands, PlanCommands};
 
 fn setup_logging() -> Result<()> {
     let log_dir = dirs::data_local_dir()
@@ -56,14 +65,17 @@ fn run() -> Result<i32> {
             commands::init::run(&cli).context("Init command failed")?;
             Ok(0)
         }
-        Commands::Run(args) => {
-            let result = commands::run::run(&cli, args).context("Run command failed")?;
-            Ok(result.exit_code)
+        Commands::Run(args) => commands::run::run(&cli, args).context("Run command failed"),
+        Commands::Batch(args) => commands::run::batch(&cli, args).context("Batch command failed"),
+        Commands::Prompt(args) => {
+            commands::prompt::run(&cli, args).context("Prompt command failed")?;
+            Ok(0)
         }
         Commands::Status => {
             commands::status::run(&cli).context("Status command failed")?;
             Ok(0)
         }
+        Commands::Plan(PlanCommands::Lint(args)) => commands::plan::lint(&cli, args).context("Plan lint failed"),
     }
 }
 
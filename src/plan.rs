@@ -0,0 +1,504 @@
+use crate::config::QualityGate;
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A `- [ ]` / `- [x]` line of the plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChecklistItem {
+    pub text: String,
+    pub checked: bool,
+    /// 1-based line number in the plan file.
+    pub line: usize,
+}
+
+/// A heading whose title starts with "Phase" (`## Phase 2: Parser`) and
+/// everything under it, up to the next heading of the same or a higher
+/// level or the next phase heading.
+///
+/// A phase may carry its own checks, one per line with the value in
+/// backticks: ``Validation: `cargo test parser::` `` (run in place of
+/// `validation.command` every iteration the phase is current), ``Gate: clippy `cargo clippy` ``
+/// (name optional) and ``Signal: `<promise>PARSER</promise>` ``.
+#[derive(Debug, Clone)]
+pub struct Phase {
+    pub title: String,
+    /// 1-based line number of the heading.
+    pub line: usize,
+    /// The section's markdown, heading included.
+    pub text: String,
+    pub items: Vec<ChecklistItem>,
+    pub validation: Option<String>,
+    pub gates: Vec<QualityGate>,
+    pub signal: Option<String>,
+}
+
+/// How far a phase has got, as recorded in progress and `result.json`. In a
+/// plan with phase checks, `complete` means the runner has verified it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhaseStatus {
+    pub title: String,
+    pub checked: usize,
+    pub total: usize,
+    pub complete: bool,
+}
+
+impl Phase {
+    /// Every checklist item is checked. A phase without a checklist is never
+    /// complete: nothing in the plan says when it is done.
+    pub fn complete(&self) -> bool {
+        !self.items.is_empty() && self.items.iter().all(|item| item.checked)
+    }
+
+    /// The phase declares a validation command, gates or a signal.
+    pub fn has_checks(&self) -> bool {
+        self.validation.is_some() || !self.gates.is_empty() || self.signal.is_some()
+    }
+
+    pub fn status(&self) -> PhaseStatus {
+        PhaseStatus {
+            title: self.title.clone(),
+            checked: self.items.iter().filter(|item| item.checked).count(),
+            total: self.items.len(),
+            complete: self.complete(),
+        }
+    }
+}
+
+/// The phases and checklists of a markdown plan. Fenced code blocks are
+/// skipped, so example checklists in them do not count.
+#[derive(Debug, Clone, Default)]
+pub struct Plan {
+    pub phases: Vec<Phase>,
+    /// Checklist items outside any phase.
+    pub loose: Vec<ChecklistItem>,
+    /// Phase check lines that had no effect, with the reason: outside any
+    /// phase, without a backticked value, or with text after it.
+    pub ignored: Vec<(usize, &'static str)>,
+}
+
+impl Plan {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read plan {}", path.display()))?;
+        Ok(Self::parse(&content))
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let mut plan = Plan::default();
+        // The open phase and its heading level.
+        let mut open: Option<(usize, Phase)> = None;
+
+        for (number, line, in_code) in lines(content) {
+            if !in_code && let Some((level, title)) = heading(line) {
+                let phase = is_phase_title(title);
+                if open
+                    .as_ref()
+                    .is_some_and(|(open_level, _)| phase || level <= *open_level)
+                    && let Some((_, done)) = open.take()
+                {
+                    plan.phases.push(done);
+                }
+                if phase {
+                    open = Some((
+                        level,
+                        Phase {
+                            title: title.to_string(),
+                            line: number,
+                            text: String::new(),
+                            items: Vec::new(),
+                            validation: None,
+                            gates: Vec::new(),
+                            signal: None,
+                        },
+                    ));
+                }
+            }
+
+            let item = if in_code { None } else { checklist_item(line, number) };
+            let check = if in_code { None } else { directive_label(line) };
+            match &mut open {
+                Some((_, phase)) => {
+                    phase.text.push_str(line);
+                    phase.text.push('\n');
+                    phase.items.extend(item);
+                    match check.map(|(directive, rest)| (directive, directive_value(&rest))) {
+                        Some((Directive::Validation, Ok((_, command)))) => phase.validation = Some(command),
+                        Some((Directive::Gate, Ok((name, command)))) => phase.gates.push(QualityGate {
+                            name: if name.is_empty() { command.clone() } else { name },
+                            command: Some(command),
+                            script: None,
+                        }),
+                        Some((Directive::Signal, Ok((_, signal)))) => phase.signal = Some(signal),
+                        Some((_, Err(reason))) => plan.ignored.push((number, reason)),
+                        None => {}
+                    }
+                }
+                None => {
+                    plan.loose.extend(item);
+                    if check.is_some() {
+                        plan.ignored.push((number, "outside any phase"));
+                    }
+                }
+            }
+        }
+        if let Some((_, phase)) = open {
+            plan.phases.push(phase);
+        }
+        plan
+    }
+
+    /// The phase to work on, with its index: the first that is not complete
+    /// among those with a checklist. `None` when no phase has a checklist or
+    /// all are done.
+    pub fn current_phase(&self) -> Option<(usize, &Phase)> {
+        self.phases
+            .iter()
+            .enumerate()
+            .find(|(_, phase)| !phase.items.is_empty() && !phase.complete())
+    }
+
+    pub fn status(&self) -> Vec<PhaseStatus> {
+        self.phases.iter().map(Phase::status).collect()
+    }
+
+    /// Every unchecked item, phased or not, in plan order.
+    pub fn unchecked(&self) -> Vec<&ChecklistItem> {
+        let mut items: Vec<&ChecklistItem> = self
+            .phases
+            .iter()
+            .flat_map(|phase| &phase.items)
+            .chain(&self.loose)
+            .filter(|item| !item.checked)
+            .collect();
+        items.sort_by_key(|item| item.line);
+        items
+    }
+
+    /// Some phase carries its own checks, so the run must verify the phases
+    /// one by one.
+    pub fn gated(&self) -> bool {
+        self.phases.iter().any(Phase::has_checks)
+    }
+
+    /// Index of the first phase whose checklist is not complete (phases
+    /// without one count as unfinished); the phase count when all are.
+    pub fn first_unfinished(&self) -> usize {
+        self.phases
+            .iter()
+            .position(|phase| !phase.complete())
+            .unwrap_or(self.phases.len())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Directive {
+    Validation,
+    Gate,
+    Signal,
+}
+
+/// The directive of a phase check line and what follows its label: a
+/// `Label:`, optionally bulleted or bold.
+fn directive_label(line: &str) -> Option<(Directive, String)> {
+    let line = line.replace("**", "");
+    let line = line.trim_start();
+    let line = line.strip_prefix(['-', '*', '+']).map_or(line, str::trim_start);
+    let (label, rest) = line.split_once(':')?;
+    let directive = match label.trim().to_lowercase().as_str() {
+        "validation" => Directive::Validation,
+        "gate" | "quality gate" => Directive::Gate,
+        "signal" | "completion signal" => Directive::Signal,
+        _ => return None,
+    };
+    Some((directive, rest.to_string()))
+}
+
+/// `(name, value)` after a directive label: an optional name and a single
+/// backticked value ending the line. Anything else is refused with the reason,
+/// so prose such as ``Validation: `a` passes and `b` is clean`` never becomes
+/// a command.
+fn directive_value(rest: &str) -> std::result::Result<(String, String), &'static str> {
+    let open = rest.find('`').ok_or("no backticked value")?;
+    let close = rest[open + 1..].find('`').map(|i| open + 1 + i).ok_or("no backticked value")?;
+    let value = rest[open + 1..close].trim();
+    if value.is_empty() {
+        return Err("no backticked value");
+    }
+    if !rest[close + 1..].trim().is_empty() {
+        return Err("text after the backticked value");
+    }
+    Ok((rest[..open].trim().to_string(), value.to_string()))
+}
+
+/// `line` is a phase check (`Validation:`, `Gate:`, `Signal:`), well-formed
+/// or not.
+pub fn is_phase_check(line: &str) -> bool {
+    directive_label(line).is_some()
+}
+
+/// The lines of a markdown document as `(line number, line, in fenced code)`;
+/// the fence lines themselves count as code.
+pub fn lines(content: &str) -> Lines<'_> {
+    Lines {
+        lines: content.lines().enumerate(),
+        fence: None,
+    }
+}
+
+pub struct Lines<'a> {
+    lines: std::iter::Enumerate<std::str::Lines<'a>>,
+    fence: Option<&'a str>,
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = (usize, &'a str, bool);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (i, line) = self.lines.next()?;
+        let trimmed = line.trim_start();
+        let in_code = match self.fence {
+            Some(marker) => {
+                if trimmed.starts_with(marker) {
+                    self.fence = None;
+                }
+                true
+            }
+            None if trimmed.starts_with("```") || trimmed.starts_with("~~~") => {
+                self.fence = Some(&trimmed[..3]);
+                true
+            }
+            None => false,
+        };
+        Some((i + 1, line, in_code))
+    }
+}
+
+/// `(level, title)` of an ATX heading (`## Title`).
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let rest = &line[indent..];
+    let level = rest.len() - rest.trim_start_matches('#').len();
+    let title = &rest[level..];
+    if !(1..=6).contains(&level) || !(title.is_empty() || title.starts_with([' ', '\t'])) {
+        return None;
+    }
+    Some((level, title.trim().trim_end_matches('#').trim_end()))
+}
+
+/// "Phase", case-insensitive, as a word: "Phase 2: Parser", "PHASE: setup".
+fn is_phase_title(title: &str) -> bool {
+    title.get(..5).is_some_and(|word| word.eq_ignore_ascii_case("phase"))
+        && !title[5..].starts_with(|c: char| c.is_alphanumeric() && !c.is_ascii_digit())
+}
+
+/// A `- [ ] text` / `* [x] text` list item.
+fn checklist_item(line: &str, line_number: usize) -> Option<ChecklistItem> {
+    let rest = line.trim_start().strip_prefix(['-', '*', '+'])?.strip_prefix(' ')?;
+    let rest = rest.trim_start();
+    let (checked, text) = if let Some(text) = rest.strip_prefix("[ ]") {
+        (false, text)
+    } else if let Some(text) = rest.strip_prefix("[x]").or_else(|| rest.strip_prefix("[X]")) {
+        (true, text)
+    } else {
+        return None;
+    };
+    if !(text.is_empty() || text.starts_with([' ', '\t'])) {
+        return None;
+    }
+    Some(ChecklistItem {
+        text: text.trim().to_string(),
+        checked,
+        line: line_number,
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    const PLAN: &str = "\
+# Plan: Calculator
+
+- [x] Read the design doc
+
+## Phase 1: Lexer
+
+Files: `src/lexer.rs`
+
+- [x] Tokenize numbers
+- [x] Tokenize operators
+
+## Phase 2: Parser
+
+### Notes
+
+```markdown
+- [ ] not a real item
+## Phase 9: not a heading
+```
+
+- [x] Parse sums
+* [ ] Parse products
+  - [ ] Nested: precedence
+
+## Phase 3: Docs
+
+Write the README.
+
+## Appendix
+
+- [ ] Outside any phase
+";
+
+    #[test]
+    fn test_parse_phases_and_checklists() {
+        let plan = Plan::parse(PLAN);
+        let titles: Vec<&str> = plan.phases.iter().map(|p| p.title.as_str()).collect();
+        assert_eq!(titles, vec!["Phase 1: Lexer", "Phase 2: Parser", "Phase 3: Docs"]);
+
+        let parser = &plan.phases[1];
+        assert_eq!(parser.line, 12);
+        assert!(parser.text.starts_with("## Phase 2: Parser\n"));
+        assert!(parser.text.contains("### Notes"));
+        assert!(!parser.text.contains("Phase 3"));
+        let items: Vec<(&str, bool)> = parser.items.iter().map(|i| (i.text.as_str(), i.checked)).collect();
+        assert_eq!(
+            items,
+            vec![
+                ("Parse sums", true),
+                ("Parse products", false),
+                ("Nested: precedence", false)
+            ]
+        );
+
+        let loose: Vec<&str> = plan.loose.iter().map(|i| i.text.as_str()).collect();
+        assert_eq!(loose, vec!["Read the design doc", "Outside any phase"]);
+    }
+
+    #[test]
+    fn test_current_phase_skips_done_and_untracked_phases() {
+        let plan = Plan::parse(PLAN);
+        let (index, phase) = plan.current_phase().unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(phase.title, "Phase 2: Parser");
+
+        let status = plan.status();
+        assert_eq!(
+            status[0],
+            PhaseStatus {
+                title: "Phase 1: Lexer".to_string(),
+                checked: 2,
+                total: 2,
+                complete: true,
+            }
+        );
+        assert_eq!((status[1].checked, status[1].total, status[1].complete), (1, 3, false));
+        // No checklist: never complete, never current.
+        assert!(!status[2].complete);
+
+        let done = Plan::parse(&PLAN.replace("[ ]", "[x]"));
+        assert!(done.current_phase().is_none());
+    }
+
+    #[test]
+    fn test_phase_checks() {
+        let plan = Plan::parse(
+            "Validation: `make`\n\
+             ## Phase 1: Lexer\n\
+             - [ ] Tokenize\n\
+             Validation: `cargo test lexer::`\n\
+             - **Gate:** clippy `cargo clippy -- -D warnings`\n\
+             Gate: `cargo fmt --check`\n\
+             Completion signal: `<promise>LEXER</promise>`\n\
+             ```\n\
+             Validation: `not this`\n\
+             ```\n\
+             Validation: run the tests by hand\n\
+             ## Phase 2: Parser\n",
+        );
+        let lexer = &plan.phases[0];
+        assert_eq!(lexer.validation.as_deref(), Some("cargo test lexer::"));
+        let gates: Vec<(&str, Option<&str>)> = lexer
+            .gates
+            .iter()
+            .map(|g| (g.name.as_str(), g.command.as_deref()))
+            .collect();
+        assert_eq!(
+            gates,
+            vec![
+                ("clippy", Some("cargo clippy -- -D warnings")),
+                ("cargo fmt --check", Some("cargo fmt --check"))
+            ]
+        );
+        assert_eq!(lexer.signal.as_deref(), Some("<promise>LEXER</promise>"));
+        assert!(lexer.has_checks());
+        assert!(!plan.phases[1].has_checks());
+        assert!(plan.gated());
+        assert!(!Plan::parse(PLAN).gated());
+        assert_eq!(
+            plan.ignored,
+            vec![(1, "outside any phase"), (11, "no backticked value")]
+        );
+    }
+
+    #[test]
+    fn test_phase_check_with_two_spans_is_ignored() {
+        let plan = Plan::parse(
+            "## Phase 1: Parser\n\
+             - [ ] Parse\n\
+             Validation: `cargo test parser::` passes and `cargo clippy` is clean\n\
+             Signal: `<promise>PARSER</promise>` when done\n",
+        );
+        let parser = &plan.phases[0];
+        assert_eq!(parser.validation, None);
+        assert_eq!(parser.signal, None);
+        assert!(!plan.gated());
+        assert_eq!(
+            plan.ignored,
+            vec![(3, "text after the backticked value"), (4, "text after the backticked value")]
+        );
+    }
+
+    #[test]
+    fn test_unchecked_in_plan_order() {
+        let plan = Plan::parse(PLAN);
+        let unchecked: Vec<(&str, usize)> = plan.unchecked().iter().map(|i| (i.text.as_str(), i.line)).collect();
+        assert_eq!(
+            unchecked,
+            vec![
+                ("Parse products", 22),
+                ("Nested: precedence", 23),
+                ("Outside any phase", 31)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_first_unfinished() {
+        assert_eq!(Plan::parse(PLAN).first_unfinished(), 1);
+        let done = Plan::parse("## Phase 1\n- [x] a\n## Phase 2\n- [x] b\n");
+        assert_eq!(done.first_unfinished(), 2);
+    }
+
+    #[test]
+    fn test_heading_and_item_syntax() {
+        assert_eq!(heading("## Phase 1 ##"), Some((2, "Phase 1")));
+        assert_eq!(heading("#hashtag"), None);
+        assert_eq!(heading("    # indented code"), None);
+        assert!(is_phase_title("Phase 2: Parser"));
+        assert!(is_phase_title("PHASE: setup"));
+        assert!(is_phase_title("Phase2"));
+        assert!(!is_phase_title("Phases overview"));
+        assert!(!is_phase_title("Phasing"));
+        assert!(checklist_item("- [X] done", 1).unwrap().checked);
+        assert!(checklist_item("- [ ]", 1).is_some());
+        assert!(checklist_item("- [x]done", 1).is_none());
+        assert!(checklist_item("-[ ] no space", 1).is_none());
+        assert!(checklist_item("- plain bullet", 1).is_none());
+    }
+}
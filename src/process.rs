@@ -0,0 +1,241 @@
+use std::process::{Child, Command};
+use std::time::{Duration, Instant};
+
+/// How often a graceful shutdown polls the group for exit.
+const GRACE_POLL_MS: u64 = 100;
+
+/// Longest process description kept for the session log.
+const MAX_DESCRIPTION_LEN: usize = 120;
+
+/// Put the command in a new process group of its own, led by the child.
+///
+/// Everything the agent spawns (dev servers, test runners, language servers)
+/// inherits the group, so [`terminate_group`] can signal all of it at once.
+/// A side effect: the terminal's Ctrl-C no longer reaches the agent directly;
+/// the loop forwards it through the group instead.
+pub fn isolate_group(cmd: &mut Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    #[cfg(not(unix))]
+    let _ = cmd;
+}
+
+/// Live members of process group `pgid`, as `pid command...` descriptions.
+///
+/// Lists via `ps` (portable across Linux and macOS); fails soft to an empty
+/// list, which only costs the session log its detail.
+pub fn group_members(pgid: u32) -> Vec<String> {
+    let output = match Command::new("ps").args(["-A", "-o", "pid=,pgid=,stat=,args="]).output() {
+        Ok(output) => output,
+        Err(e) => {
+            log::debug!("group_members: ps failed: {}", e);
+            return Vec::new();
+        }
+    };
+    parse_members(&String::from_utf8_lossy(&output.stdout), pgid)
+}
+
+/// Pick the non-zombie members of `pgid` out of `ps -o pid=,pgid=,stat=,args=`.
+fn parse_members(ps: &str, pgid: u32) -> Vec<String> {
+    ps.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pid = fields.next()?;
+            let group = fields.next()?.parse::<u32>().ok()?;
+            let stat = fields.next()?;
+            let args = fields.collect::<Vec<_>>().join(" ");
+            (group == pgid && !stat.starts_with('Z')).then(|| {
+                let mut description = format!("{} {}", pid, args);
+                if description.len() > MAX_DESCRIPTION_LEN {
+                    let mut end = MAX_DESCRIPTION_LEN;
+                    while !description.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    description.truncate(end);
+                    description.push_str("...");
+                }
+                description
+            })
+        })
+        .collect()
+}
+
+/// Whether any live (non-zombie) process remains in group `pgid`.
+///
+/// `killpg(pgid, 0)` is the cheap check, but it also counts zombies, which
+/// linger forever when PID 1 does not reap orphans (common in containers), so
+/// a positive answer is confirmed against `ps`.
+#[cfg(unix)]
+fn group_alive(pgid: u32) -> bool {
+    // SAFETY: killpg with signal 0 only checks for existence/permission.
+    let exists = unsafe { libc::killpg(pgid as libc::pid_t, 0) == 0 };
+    exists && !group_members(pgid).is_empty()
+}
+
+#[cfg(unix)]
+fn signal_group(pgid: u32, signal: libc::c_int) {
+    // SAFETY: plain syscall on a process group we created; errors (the group
+    // already gone) are expected and ignored.
+    unsafe {
+        libc::killpg(pgid as libc::pid_t, signal);
+    }
+}
+
+/// Stop the child's whole process group: SIGTERM, up to `grace` for it to
+/// exit, then SIGKILL for whatever is left. Reaps the child itself.
+///
+/// Returns the members found in the group before signalling, for the session
+/// log. On non-unix platforms this degrades to killing the direct child.
+pub fn terminate_group(child: &mut Child, grace: Duration) -> Vec<String> {
+    let pgid = child.id();
+    let members = group_members(pgid);
+
+    #[cfg(unix)]
+    {
+        log::debug!(
+            "terminate_group: pgid={} members={} grace_secs={}",
+            pgid,
+            members.len(),
+            grace.as_secs()
+        );
+        signal_group(pgid, libc::SIGTERM);
+        let deadline = Instant::now() + grace;
+        loop {
+            // Reap the leader as soon as it exits.
+            let _ = child.try_wait();
+            if !group_alive(pgid) {
+                break;
+            }
+            if Instant::now() >= deadline {
+                log::warn!("terminate_group: pgid={} ignored SIGTERM, sending SIGKILL", pgid);
+                signal_group(pgid, libc::SIGKILL);
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(GRACE_POLL_MS));
+        }
+        // SIGKILL cannot be ignored; give the kernel a moment to deliver it.
+        let settle = Instant::now() + Duration::from_secs(1);
+        while group_alive(pgid) && Instant::now() < settle {
+            let _ = child.try_wait();
+            std::thread::sleep(Duration::from_millis(GRACE_POLL_MS));
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = grace;
+        let _ = child.kill();
+    }
+
+    let _ = child.wait();
+    members
+}
+
+/// After the agent itself has exited, stop anything it left running in its
+/// group (background servers, watchers). Returns what was found; empty when
+/// the agent cleaned up after itself.
+pub fn reap_leftovers(pgid: u32, grace: Duration) -> Vec<String> {
+    let leftovers = group_members(pgid);
+    if leftovers.is_empty() {
+        return leftovers;
+    }
+    log::warn!("reap_leftovers: pgid={} leftovers={:?}", pgid, leftovers);
+
+    #[cfg(unix)]
+    {
+        signal_group(pgid, libc::SIGTERM);
+        let deadline = Instant::now() + grace;
+        while group_alive(pgid) {
+            if Instant::now() >= deadline {
+                log::warn!("reap_leftovers: pgid={} ignored SIGTERM, sending SIGKILL", pgid);
+                signal_group(pgid, libc::SIGKILL);
+                let settle = Instant::now() + Duration::from_secs(1);
+                while group_alive(pgid) && Instant::now() < settle {
+                    std::thread::sleep(Duration::from_millis(GRACE_POLL_MS));
+                }
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(GRACE_POLL_MS));
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = grace;
+
+    leftovers
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use std::process::Stdio;
+
+    #[test]
+    fn test_parse_members_filters_group_and_zombies() {
+        let ps = "  100   100 Ss   claude --print\n  101   100 S    node server.js --port 3000\n  \
+                  102   100 Z    [sh] <defunct>\n  200   200 S    other\n";
+        assert_eq!(
+            parse_members(ps, 100),
+            vec!["100 claude --print", "101 node server.js --port 3000"]
+        );
+        assert!(parse_members(ps, 999).is_empty());
+    }
+
+    #[test]
+    fn test_parse_members_truncates_long_commands() {
+        let ps = format!("  7 7 S {}\n", "x".repeat(500));
+        let members = parse_members(&ps, 7);
+        assert!(members[0].len() <= MAX_DESCRIPTION_LEN + 3);
+        assert!(members[0].ends_with("..."));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_terminate_group_kills_grandchildren() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "sleep 30 & sleep 30"]).stdout(Stdio::null());
+        isolate_group(&mut cmd);
+        let mut child = cmd.spawn().unwrap();
+        let pgid = child.id();
+        std::thread::sleep(Duration::from_millis(200));
+
+        let members = terminate_group(&mut child, Duration::from_secs(5));
+        assert!(members.len() >= 2, "members: {:?}", members);
+        assert!(group_members(pgid).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_terminate_group_escalates_to_sigkill() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "trap '' TERM; sleep 30"]).stdout(Stdio::null());
+        isolate_group(&mut cmd);
+        let mut child = cmd.spawn().unwrap();
+        let pgid = child.id();
+        std::thread::sleep(Duration::from_millis(200));
+
+        let started = Instant::now();
+        terminate_group(&mut child, Duration::from_millis(500));
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(group_members(pgid).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reap_leftovers_after_leader_exits() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "sleep 30 >/dev/null 2>&1 &"]);
+        isolate_group(&mut cmd);
+        let mut child = cmd.spawn().unwrap();
+        let pgid = child.id();
+        child.wait().unwrap();
+
+        let leftovers = reap_leftovers(pgid, Duration::from_secs(5));
+        assert_eq!(leftovers.len(), 1, "leftovers: {:?}", leftovers);
+        assert!(leftovers[0].contains("sleep 30"));
+        assert!(group_members(pgid).is_empty());
+        assert!(reap_leftovers(pgid, Duration::from_secs(5)).is_empty());
+    }
+}
@@ -1,154 +1,520 @@
+use crate::config::ProgressConfig;
+use crate::plan::PhaseStatus;
 use chrono::{DateTime, Utc};
 use eyre::{Context, Result};
-use std::fs::{self, OpenOptions};
-use std::io::Write;
+use serde::{Deserialize, Serialize};
+use std::fs;
 use std::path::{Path, PathBuf};
 
+/// Where the progress log lives, relative to the work dir (the worktree, or
+/// the project under `isolation: none`): one JSON record per line. This is
+/// the one copy and the source of truth; only `rwl` writes it - agent edits
+/// are undone by the runner.
+pub const PROGRESS_LOG_PATH: &str = ".rwl/progress.jsonl";
+
+/// The markdown rendering of [`PROGRESS_LOG_PATH`], regenerated on every
+/// write. This is what the agent reads and the prompt inlines.
+pub const PROGRESS_PATH: &str = ".rwl/progress.txt";
+
+/// Both progress files, for the guard and the local git exclude.
+pub const PROGRESS_FILES: [&str; 2] = [PROGRESS_LOG_PATH, PROGRESS_PATH];
+
+/// Failing validation output kept in a record: its last 2000 bytes.
+const MAX_ERRORS_BYTES: usize = 2000;
+
+/// Delimiters of the summary block the agent ends its output with.
+pub const SUMMARY_OPEN: &str = "<rwl-summary>";
+pub const SUMMARY_CLOSE: &str = "</rwl-summary>";
+
+/// An agent summary kept in a record: its first 1000 bytes.
+const MAX_SUMMARY_BYTES: usize = 1000;
+
 pub struct ProgressTracker {
-    path: PathBuf,
+    log: PathBuf,
+    rendered: PathBuf,
+}
+
+/// One line of the progress log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Record {
+    /// The first line: when the run started, on which plan.
+    Start {
+        started: DateTime<Utc>,
+        plan: String,
+    },
+    Iteration(Box<IterationRecord>),
 }
 
-#[derive(Debug, Clone)]
-pub struct IterationResult {
+/// The outcome of one iteration. Written once validation has run (or the
+/// agent stalled or was stopped) and rewritten as quality gates and the
+/// judge add their verdicts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IterationRecord {
     pub iteration: u32,
-    pub validation_passed: bool,
+    pub started: DateTime<Utc>,
+    pub finished: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// `None` when validation did not run (the agent stalled or the run
+    /// was stopped).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_passed: Option<bool>,
+    #[serde(default)]
+    pub stalled: bool,
+    #[serde(default)]
     pub promise_found: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gates: Vec<GateOutcome>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub judge: Option<JudgeVerdict>,
+    /// The plan's phases as the agent left them (phased plans only).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub phases: Vec<PhaseStatus>,
+    /// The verdict on the current phase of a plan with phase checks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phase_check: Option<PhaseCheck>,
+    /// Plan items still unchecked when the agent signalled completion
+    /// (`loop.require-checklist`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unchecked: Vec<String>,
+    /// Protected paths the agent edited and the guard reverted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reverted_paths: Vec<String>,
     pub summary: String,
-    /// Validation output (errors) to feed back into next iteration's prompt
-    pub validation_output: String,
+    /// Feedback for the next iteration: the tail of failing validation
+    /// output, or why the agent was killed.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub errors: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GateOutcome {
+    pub name: String,
+    pub passed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhaseCheck {
+    pub phase: String,
+    pub passed: bool,
+    /// What held the phase back: `checklist`, `signal`, `validation` or a
+    /// gate name.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JudgeVerdict {
+    pub passed: bool,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub explanation: String,
+}
+
+impl IterationRecord {
+    /// A record for `iteration` with no outcomes yet.
+    pub fn new(iteration: u32, started: DateTime<Utc>, model: &str) -> Self {
+        Self {
+            iteration,
+            started,
+            finished: Utc::now(),
+            model: Some(model.to_string()),
+            validation_passed: None,
+            stalled: false,
+            promise_found: false,
+            gates: Vec::new(),
+            judge: None,
+            phases: Vec::new(),
+            phase_check: None,
+            unchecked: Vec::new(),
+            reverted_paths: Vec::new(),
+            summary: String::new(),
+            errors: String::new(),
+        }
+    }
+}
+
+/// The tail of failing validation output worth keeping in a record.
+pub fn errors_excerpt(output: &str) -> String {
+    let mut start = output.len().saturating_sub(MAX_ERRORS_BYTES);
+    while !output.is_char_boundary(start) {
+        start += 1;
+    }
+    output[start..].to_string()
+}
+
+/// The agent's account of its iteration: the text of the last
+/// `<rwl-summary>...</rwl-summary>` block in its output, trimmed and capped.
+/// `None` when there is no closed, non-empty block.
+pub fn agent_summary(output: &str) -> Option<String> {
+    let start = output.rfind(SUMMARY_OPEN)? + SUMMARY_OPEN.len();
+    let len = output[start..].find(SUMMARY_CLOSE)?;
+    let text = output[start..start + len].trim();
+    if text.is_empty() {
+        return None;
+    }
+    let mut end = text.len().min(MAX_SUMMARY_BYTES);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    Some(text[..end].to_string())
+}
+
+/// One iteration of the log and its `## Iteration N` rendering.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry<'a> {
+    pub record: &'a IterationRecord,
+    pub text: String,
 }
 
-#[derive(Debug)]
+/// A model-written digest of the log's entries up to and including iteration
+/// `through` (`progress.strategy: summarize`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Digest {
+    pub through: u32,
+    pub text: String,
+}
+
+/// Entries an oversized log should fold into its digest next.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fold {
+    /// The digest will cover iterations up to and including this one.
+    pub through: u32,
+    /// The entries to fold, verbatim.
+    pub entries: String,
+}
+
+#[derive(Debug, Default)]
 pub struct Progress {
     pub started: Option<DateTime<Utc>>,
     pub plan_path: Option<String>,
-    pub last_status: Option<String>,
+    pub iterations: Vec<IterationRecord>,
 }
 
 impl ProgressTracker {
-    pub fn new(path: &Path) -> Self {
+    /// The tracker for the log at `log`; its rendering sits next to it as
+    /// `.txt`.
+    pub fn new(log: &Path) -> Self {
         Self {
-            path: path.to_path_buf(),
+            log: log.to_path_buf(),
+            rendered: log.with_extension("txt"),
         }
     }
 
-    /// Initialize progress file with header
-    pub fn init(&self, plan_path: &Path) -> Result<()> {
-        let header = format!(
-            "# RWL Progress Log\n\
-             # Started: {}\n\
-             # Plan: {}\n\
-             # ----------------------------------------\n\n",
-            Utc::now().format("%Y-%m-%d %H:%M:%S UTC"),
-            plan_path.display()
-        );
+    /// The progress log of the run working in `work_dir`
+    pub fn in_work_dir(work_dir: &Path) -> Self {
+        Self::new(&work_dir.join(PROGRESS_LOG_PATH))
+    }
 
-        fs::write(&self.path, header).context("Failed to initialize progress file")?;
+    pub fn path(&self) -> &Path {
+        &self.log
+    }
 
-        Ok(())
+    /// Start a fresh log for a run on `plan_path`
+    pub fn init(&self, plan_path: &Path) -> Result<()> {
+        if let Some(parent) = self.log.parent() {
+            fs::create_dir_all(parent).context("Failed to create progress directory")?;
+        }
+        self.write(&[Self::start(plan_path)])
     }
 
-    /// Log an iteration result
-    pub fn log_iteration(&self, result: &IterationResult) -> Result<()> {
-        let mut file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&self.path)
-            .context("Failed to open progress file")?;
-
-        let mut entry = format!(
-            "## Iteration {}\n\
-             Timestamp: {}\n\
-             Validation: {}\n\
-             Promise: {}\n\
-             Summary: {}\n",
-            result.iteration,
-            Utc::now().format("%Y-%m-%d %H:%M:%S UTC"),
-            if result.validation_passed { "PASSED" } else { "FAILED" },
-            if result.promise_found { "FOUND" } else { "NOT FOUND" },
-            result.summary
-        );
+    /// The rendering of a fresh log: its header
+    pub fn header(plan_path: &Path) -> String {
+        render(&[Self::start(plan_path)])
+    }
 
-        // Append validation output for failed iterations to enable feedback accumulation
-        if !result.validation_passed && !result.validation_output.trim().is_empty() {
-            // Truncate to avoid unbounded growth (keep last 2000 chars)
-            let output = &result.validation_output;
-            let truncated = if output.len() > 2000 { &output[output.len() - 2000..] } else { output };
-            entry.push_str(&format!("Errors:\n{}\n", truncated));
+    fn start(plan_path: &Path) -> Record {
+        Record::Start {
+            started: Utc::now(),
+            plan: plan_path.display().to_string(),
         }
-        entry.push('\n');
+    }
 
-        file.write_all(entry.as_bytes())
-            .context("Failed to write to progress file")?;
+    /// Record an iteration's outcome. A record for the same iteration as the
+    /// last one replaces it, so verdicts can be added as they come in.
+    pub fn record(&self, record: &IterationRecord) -> Result<()> {
+        let mut records = self.records()?;
+        match records.last_mut() {
+            Some(Record::Iteration(last)) if last.iteration == record.iteration => **last = record.clone(),
+            _ => records.push(Record::Iteration(Box::new(record.clone()))),
+        }
+        self.write(&records)
+    }
 
+    /// Rewrite the log and its rendering
+    fn write(&self, records: &[Record]) -> Result<()> {
+        let mut jsonl = String::new();
+        for record in records {
+            jsonl.push_str(&serde_json::to_string(record)?);
+            jsonl.push('\n');
+        }
+        fs::write(&self.log, jsonl).context("Failed to write progress log")?;
+        fs::write(&self.rendered, render(records)).context("Failed to write progress file")?;
         Ok(())
     }
 
+    /// Every record in the log; unparsable lines are skipped
+    pub fn records(&self) -> Result<Vec<Record>> {
+        if !self.log.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.log).context("Failed to read progress log")?;
+        Ok(content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(record) => Some(record),
+                Err(e) => {
+                    log::warn!("records: path={} skipping bad line: {}", self.log.display(), e);
+                    None
+                }
+            })
+            .collect())
+    }
+
     /// Read current progress
     pub fn read(&self) -> Result<Progress> {
-        if !self.path.exists() {
-            return Ok(Progress {
-                started: None,
-                plan_path: None,
-                last_status: None,
-            });
+        let mut progress = Progress::default();
+        for record in self.records()? {
+            match record {
+                Record::Start { started, plan } => {
+                    progress.started = Some(started);
+                    progress.plan_path = Some(plan);
+                }
+                Record::Iteration(iteration) => progress.iterations.push(*iteration),
+            }
         }
+        Ok(progress)
+    }
 
-        let content = fs::read_to_string(&self.path).context("Failed to read progress file")?;
+    /// Check if the progress log exists
+    pub fn exists(&self) -> bool {
+        self.log.exists()
+    }
+}
 
-        let mut progress = Progress {
-            started: None,
-            plan_path: None,
-            last_status: None,
-        };
+/// The markdown rendering of `records`: a header, then one `## Iteration N`
+/// entry per iteration.
+pub fn render(records: &[Record]) -> String {
+    let mut out = String::new();
+    for record in records {
+        match record {
+            Record::Start { started, plan } => out.push_str(&format!(
+                "# RWL Progress Log\n\
+                 # Started: {}\n\
+                 # Plan: {}\n\
+                 # ----------------------------------------\n\n",
+                started.format("%Y-%m-%d %H:%M:%S UTC"),
+                plan
+            )),
+            Record::Iteration(record) => out.push_str(&render_iteration(record)),
+        }
+    }
+    out
+}
 
-        // Parse header
-        for line in content.lines() {
-            if line.starts_with("# Started:") {
-                let date_str = line.trim_start_matches("# Started:").trim();
-                progress.started = DateTime::parse_from_str(
-                    &format!("{} +0000", date_str.trim_end_matches(" UTC")),
-                    "%Y-%m-%d %H:%M:%S %z",
-                )
-                .ok()
-                .map(|dt| dt.with_timezone(&Utc));
-            } else if line.starts_with("# Plan:") {
-                progress.plan_path = Some(line.trim_start_matches("# Plan:").trim().to_string());
-            }
+fn render_iteration(record: &IterationRecord) -> String {
+    let validation = match record.validation_passed {
+        Some(true) => "PASSED",
+        Some(false) => "FAILED",
+        None if record.stalled => "SKIPPED (agent stalled)",
+        None => "NOT RUN",
+    };
+    let mut entry = format!(
+        "## Iteration {}\n\
+         Timestamp: {}\n\
+         Validation: {}\n\
+         Promise: {}\n\
+         Summary: {}\n",
+        record.iteration,
+        record.finished.format("%Y-%m-%d %H:%M:%S UTC"),
+        validation,
+        if record.promise_found { "FOUND" } else { "NOT FOUND" },
+        record.summary.replace('\n', "\n  ")
+    );
+    if let Some(model) = &record.model {
+        entry.push_str(&format!("Model: {}\n", model));
+    }
+    if !record.gates.is_empty() {
+        let gates: Vec<String> = record
+            .gates
+            .iter()
+            .map(|g| format!("{} {}", g.name, if g.passed { "PASSED" } else { "FAILED" }))
+            .collect();
+        entry.push_str(&format!("Quality gates: {}\n", gates.join(", ")));
+    }
+    if let Some(judge) = &record.judge {
+        entry.push_str(&format!(
+            "Judge: {}\n",
+            if judge.passed { "PASSED" } else { "REJECTED" }
+        ));
+    }
+    if !record.phases.is_empty() {
+        let complete = record.phases.iter().filter(|p| p.complete).count();
+        entry.push_str(&format!("Phases complete: {}/{}\n", complete, record.phases.len()));
+    }
+    if let Some(check) = &record.phase_check {
+        if check.passed {
+            entry.push_str(&format!("Phase check: {} PASSED\n", check.phase));
+        } else {
+            entry.push_str(&format!(
+                "Phase check: {} FAILED ({})\n",
+                check.phase,
+                check.failed.join(", ")
+            ));
         }
+    }
 
-        // Count iterations (simple count of "## Iteration" lines)
-        let iteration_count = content.matches("## Iteration").count() as u32;
-        if iteration_count > 0 {
-            progress.last_status = Some(format!("{} iterations completed", iteration_count));
+    // Feedback for the next iteration
+    if !record.errors.trim().is_empty() {
+        entry.push_str(&format!("Errors:\n{}\n", indent(&record.errors)));
+    }
+    if let Some(judge) = record.judge.as_ref().filter(|j| !j.passed) {
+        entry.push_str(&format!(
+            "The LLM-as-judge reviewed your work and found it incomplete.\n\
+             Judge feedback:\n{}\n",
+            indent(&judge.explanation)
+        ));
+    }
+    if !record.unchecked.is_empty() {
+        entry.push_str("The completion signal was rejected: these plan items are not checked off yet:\n");
+        for item in &record.unchecked {
+            entry.push_str(&format!("  - [ ] {}\n", item));
         }
+    }
+    if !record.reverted_paths.is_empty() {
+        entry.push_str(
+            "You modified protected (off-limits) paths; these edits were reverted and NOT committed.\n\
+             Do not modify these paths again:\n",
+        );
+        for path in &record.reverted_paths {
+            entry.push_str(&format!("  - {}\n", path));
+        }
+    }
+    entry.push('\n');
+    entry
+}
 
-        Ok(progress)
+/// Every line of a multi-line field indented, so no line of tool output or
+/// model prose can read as a heading of the log.
+fn indent(text: &str) -> String {
+    text.trim_end()
+        .lines()
+        .map(|line| format!("  {}", line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The rendered header of `records` and their iterations, each with its
+/// rendering.
+pub fn split_entries(records: &[Record]) -> (String, Vec<Entry<'_>>) {
+    let mut header = String::new();
+    let mut entries = Vec::new();
+    for record in records {
+        match record {
+            Record::Start { .. } => header.push_str(&render(std::slice::from_ref(record))),
+            Record::Iteration(iteration) => entries.push(Entry {
+                record: iteration,
+                text: render_iteration(iteration),
+            }),
+        }
     }
+    (header, entries)
+}
 
-    /// Get iteration count
-    pub fn iteration_count(&self) -> Result<u32> {
-        if !self.path.exists() {
-            return Ok(0);
+/// The progress to inline into a prompt, rendered from `records`.
+///
+/// Within `progress.max-bytes` (or with it at `0`) the whole log is rendered.
+/// Otherwise only the last `keep-iterations` iterations are kept verbatim
+/// (fewer if even those overflow, but at least one): earlier entries are
+/// replaced by `digest` where it covers them and by a one-line count where it
+/// does not.
+pub fn compact(records: &[Record], config: &ProgressConfig, digest: Option<&Digest>) -> String {
+    let (header, entries) = split_entries(records);
+    let full = header.len() + entries.iter().map(|e| e.text.len()).sum::<usize>();
+    let last = match entries.last() {
+        Some(entry) if config.max_bytes > 0 && full > config.max_bytes => entry.record.iteration,
+        _ => return entries.iter().fold(header, |log, e| log + &e.text),
+    };
+
+    let mut view = String::new();
+    for keep in (1..=config.keep_iterations.max(1)).rev() {
+        view = compact_view(&header, &entries, last.saturating_sub(keep), digest);
+        if view.len() <= config.max_bytes {
+            break;
         }
+    }
+    log::debug!(
+        "compact: bytes={} max_bytes={} compacted_bytes={}",
+        full,
+        config.max_bytes,
+        view.len()
+    );
+    view
+}
 
-        let content = fs::read_to_string(&self.path).context("Failed to read progress file")?;
+/// The header, the digest, one line for undigested entries up to `cutoff`, and
+/// the entries after `cutoff` verbatim.
+fn compact_view(header: &str, entries: &[Entry], cutoff: u32, digest: Option<&Digest>) -> String {
+    let digested = digest.map_or(0, |d| d.through);
+    let mut view = header.to_string();
+    if let Some(digest) = digest.filter(|d| d.through > 0) {
+        view.push_str(&format!(
+            "## Digest of iterations 1-{}\n{}\n\n",
+            digest.through,
+            digest.text.trim_end()
+        ));
+    }
 
-        Ok(content.matches("## Iteration").count() as u32)
+    let omitted: Vec<&IterationRecord> = entries
+        .iter()
+        .map(|e| e.record)
+        .filter(|r| r.iteration > digested && r.iteration <= cutoff)
+        .collect();
+    if let (Some(first), Some(last)) = (omitted.first(), omitted.last()) {
+        let passed = omitted.iter().filter(|r| r.validation_passed == Some(true)).count();
+        let stalled = omitted.iter().filter(|r| r.stalled).count();
+        view.push_str(&format!(
+            "[Iterations {}-{} omitted: {} entries, {} with validation passed, {} stalled. \
+             Full history: {}]\n\n",
+            first.iteration,
+            last.iteration,
+            omitted.len(),
+            passed,
+            stalled,
+            PROGRESS_PATH
+        ));
     }
 
-    /// Check if progress file exists
-    pub fn exists(&self) -> bool {
-        self.path.exists()
+    for entry in entries.iter().filter(|e| e.record.iteration > cutoff.max(digested)) {
+        view.push_str(&entry.text);
     }
+    view
+}
 
-    /// Get the raw content of the progress file
-    pub fn raw_content(&self) -> Result<String> {
-        if !self.path.exists() {
-            return Ok(String::new());
-        }
-        fs::read_to_string(&self.path).context("Failed to read progress file")
+/// Under `progress.strategy: summarize`, the entries to fold into the digest
+/// before the next prompt: everything older than the last `keep-iterations`
+/// iterations that `digest` does not cover yet. `None` while the log, with
+/// the digest standing in for what it covers, still fits in `max-bytes` -
+/// so the digest is refreshed in batches, not every iteration.
+pub fn fold_candidates(records: &[Record], config: &ProgressConfig, digest: Option<&Digest>) -> Option<Fold> {
+    let (header, entries) = split_entries(records);
+    let full = header.len() + entries.iter().map(|e| e.text.len()).sum::<usize>();
+    if config.max_bytes == 0 || full <= config.max_bytes {
+        return None;
+    }
+    let last = entries.last()?.record.iteration;
+    let digested = digest.map_or(0, |d| d.through);
+    if compact_view(&header, &entries, digested, digest).len() <= config.max_bytes {
+        return None;
     }
+    let through = last.saturating_sub(config.keep_iterations.max(1));
+    let fold: String = entries
+        .iter()
+        .filter(|e| e.record.iteration > digested && e.record.iteration <= through)
+        .map(|e| e.text.as_str())
+        .collect();
+    (!fold.is_empty()).then_some(Fold { through, entries: fold })
 }
 
 #[cfg(test)]
@@ -156,67 +522,310 @@ mod tests {
     use super::*;
     use tempfile::tempdir;
 
+    fn tracker(dir: &Path) -> ProgressTracker {
+        ProgressTracker::new(&dir.join("progress.jsonl"))
+    }
+
+    /// The markdown rendering, as the agent reads it.
+    fn rendered(tracker: &ProgressTracker) -> String {
+        fs::read_to_string(&tracker.rendered).unwrap()
+    }
+
+    fn record(iteration: u32, validation_passed: Option<bool>, summary: &str) -> IterationRecord {
+        IterationRecord {
+            validation_passed,
+            summary: summary.to_string(),
+            ..IterationRecord::new(iteration, Utc::now(), "sonnet")
+        }
+    }
+
     #[test]
-    fn test_init_creates_file() {
+    fn test_init_writes_start_record_and_header() {
         let dir = tempdir().unwrap();
-        let progress_path = dir.path().join("progress.txt");
-        let tracker = ProgressTracker::new(&progress_path);
+        let tracker = tracker(dir.path());
 
         tracker.init(Path::new("test-plan.md")).unwrap();
 
-        assert!(progress_path.exists());
-        let content = fs::read_to_string(&progress_path).unwrap();
-        assert!(content.contains("# RWL Progress Log"));
-        assert!(content.contains("test-plan.md"));
+        let log = fs::read_to_string(dir.path().join("progress.jsonl")).unwrap();
+        assert!(log.starts_with(r#"{"kind":"start","#), "{}", log);
+        let content = fs::read_to_string(dir.path().join("progress.txt")).unwrap();
+        assert!(content.starts_with("# RWL Progress Log"));
+        assert!(content.contains("# Plan: test-plan.md"));
+
+        let progress = tracker.read().unwrap();
+        assert_eq!(progress.plan_path.as_deref(), Some("test-plan.md"));
+        assert!(progress.started.is_some());
+        assert!(progress.iterations.is_empty());
     }
 
     #[test]
-    fn test_log_iteration() {
+    fn test_record_renders_markdown() {
         let dir = tempdir().unwrap();
-        let progress_path = dir.path().join("progress.txt");
-        let tracker = ProgressTracker::new(&progress_path);
-
+        let tracker = tracker(dir.path());
         tracker.init(Path::new("test-plan.md")).unwrap();
 
-        let result = IterationResult {
-            iteration: 1,
-            validation_passed: true,
-            promise_found: false,
-            summary: "Fixed a bug".to_string(),
-            validation_output: String::new(),
-        };
-
-        tracker.log_iteration(&result).unwrap();
+        tracker.record(&record(1, Some(true), "Fixed a bug")).unwrap();
 
-        let content = fs::read_to_string(&progress_path).unwrap();
+        let content = rendered(&tracker);
         assert!(content.contains("## Iteration 1"));
         assert!(content.contains("Validation: PASSED"));
         assert!(content.contains("Promise: NOT FOUND"));
         assert!(content.contains("Fixed a bug"));
+        assert!(content.contains("Model: sonnet"));
     }
 
     #[test]
-    fn test_iteration_count() {
+    fn test_record_replaces_same_iteration_and_round_trips() {
         let dir = tempdir().unwrap();
-        let progress_path = dir.path().join("progress.txt");
-        let tracker = ProgressTracker::new(&progress_path);
-
+        let tracker = tracker(dir.path());
         tracker.init(Path::new("test-plan.md")).unwrap();
 
-        assert_eq!(tracker.iteration_count().unwrap(), 0);
-
         for i in 1..=3 {
             tracker
-                .log_iteration(&IterationResult {
-                    iteration: i,
-                    validation_passed: true,
-                    promise_found: false,
-                    summary: format!("Iteration {}", i),
-                    validation_output: String::new(),
+                .record(&record(i, Some(false), &format!("Iteration {}", i)))
+                .unwrap();
+        }
+        let mut last = record(3, Some(true), "Judged");
+        last.promise_found = true;
+        last.gates = vec![
+            GateOutcome {
+                name: "clippy".to_string(),
+                passed: true,
+            },
+            GateOutcome {
+                name: "fmt".to_string(),
+                passed: false,
+            },
+        ];
+        last.judge = Some(JudgeVerdict {
+            passed: false,
+            explanation: "Tests are missing.".to_string(),
+        });
+        last.reverted_paths = vec!["docs/design/plan.md".to_string()];
+        tracker.record(&last).unwrap();
+
+        let progress = tracker.read().unwrap();
+        assert_eq!(progress.iterations.len(), 3);
+        assert_eq!(progress.iterations[2], last);
+
+        let content = rendered(&tracker);
+        assert_eq!(content.matches("## Iteration 3\n").count(), 1);
+        assert!(content.contains("Quality gates: clippy PASSED, fmt FAILED\n"));
+        assert!(content.contains("Judge: REJECTED\n"));
+        assert!(content.contains("Judge feedback:\n  Tests are missing.\n"));
+        assert!(content.contains("Do not modify these paths again:\n  - docs/design/plan.md\n"));
+    }
+
+    #[test]
+    fn test_record_stalled_iteration() {
+        let dir = tempdir().unwrap();
+        let tracker = tracker(dir.path());
+
+        tracker
+            .record(&IterationRecord {
+                stalled: true,
+                summary: "Stalled: claude stalled: no output for 5 minutes".to_string(),
+                errors: "The agent produced no output and was killed.".to_string(),
+                ..IterationRecord::new(2, Utc::now(), "sonnet")
+            })
+            .unwrap();
+
+        let content = rendered(&tracker);
+        assert!(content.contains("Validation: SKIPPED (agent stalled)"));
+        assert!(content.contains("Summary: Stalled:"));
+        assert!(content.contains("The agent produced no output"));
+        assert_eq!(tracker.read().unwrap().iterations.len(), 1);
+    }
+
+    #[test]
+    fn test_records_skip_bad_lines() {
+        let dir = tempdir().unwrap();
+        let tracker = tracker(dir.path());
+        tracker.init(Path::new("plan.md")).unwrap();
+        tracker.record(&record(1, Some(true), "ok")).unwrap();
+        let mut log = fs::read_to_string(tracker.path()).unwrap();
+        log.push_str("not json\n");
+        fs::write(tracker.path(), log).unwrap();
+
+        assert_eq!(tracker.records().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_errors_excerpt_keeps_tail_on_char_boundary() {
+        assert_eq!(errors_excerpt("short"), "short");
+        let long = format!("{}é{}", "a".repeat(10), "b".repeat(MAX_ERRORS_BYTES - 1));
+        let excerpt = errors_excerpt(&long);
+        assert_eq!(excerpt, "b".repeat(MAX_ERRORS_BYTES - 1));
+    }
+
+    #[test]
+    fn test_agent_summary_takes_last_closed_block() {
+        let output = "Working...\n<rwl-summary>draft</rwl-summary>\n\
+                      Done.\n<rwl-summary>\nAdded the lexer.\nNext: parser.\n</rwl-summary>\n";
+        assert_eq!(
+            agent_summary(output).as_deref(),
+            Some("Added the lexer.\nNext: parser.")
+        );
+        assert_eq!(agent_summary("no block"), None);
+        assert_eq!(agent_summary("<rwl-summary>  </rwl-summary>"), None);
+        assert_eq!(agent_summary("<rwl-summary>cut off"), None);
+
+        let long = format!("<rwl-summary>{}</rwl-summary>", "é".repeat(MAX_SUMMARY_BYTES));
+        assert_eq!(agent_summary(&long).unwrap().len(), MAX_SUMMARY_BYTES);
+    }
+
+    #[test]
+    fn test_multi_line_summary_stays_inside_its_entry() {
+        let dir = tempdir().unwrap();
+        let tracker = tracker(dir.path());
+        tracker.init(Path::new("plan.md")).unwrap();
+        tracker
+            .record(&record(
+                1,
+                Some(true),
+                "Wrote the lexer.\n## Iteration 9\nNext: parser.",
+            ))
+            .unwrap();
+
+        let content = rendered(&tracker);
+        assert!(content.contains("Summary: Wrote the lexer.\n  ## Iteration 9\n  Next: parser.\n"));
+    }
+
+    #[test]
+    fn test_multi_line_feedback_stays_inside_its_entry() {
+        let dir = tempdir().unwrap();
+        let tracker = tracker(dir.path());
+        tracker.init(Path::new("plan.md")).unwrap();
+        tracker
+            .record(&IterationRecord {
+                errors: "error: expected `;`\n## Iteration 9\n".to_string(),
+                judge: Some(JudgeVerdict {
+                    passed: false,
+                    explanation: "Missing tests.\n## Iteration 10".to_string(),
+                }),
+                ..record(1, Some(false), "Wrote the lexer.")
+            })
+            .unwrap();
+        tracker.record(&record(2, Some(true), "Fixed it.")).unwrap();
+
+        let content = rendered(&tracker);
+        assert!(content.contains("Errors:\n  error: expected `;`\n  ## Iteration 9\n"));
+        assert!(content.contains("Judge feedback:\n  Missing tests.\n  ## Iteration 10\n"));
+        let lines: Vec<&str> = content.lines().filter(|l| l.starts_with("## Iteration ")).collect();
+        assert_eq!(lines, vec!["## Iteration 1", "## Iteration 2"]);
+
+        let records = tracker.records().unwrap();
+        let (_, entries) = split_entries(&records);
+        assert_eq!(
+            entries.iter().map(|e| e.record.iteration).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+    }
+
+    /// A log with `n` iterations, each failing with `noise` bytes of errors.
+    fn long_log(n: u32, noise: usize) -> Vec<Record> {
+        let dir = tempdir().unwrap();
+        let tracker = tracker(dir.path());
+        tracker.init(Path::new("plan.md")).unwrap();
+        for i in 1..=n {
+            tracker
+                .record(&IterationRecord {
+                    errors: if i % 3 == 0 { String::new() } else { "e".repeat(noise) },
+                    model: None,
+                    ..record(i, Some(i % 3 == 0), &format!("Iteration {}", i))
                 })
                 .unwrap();
         }
+        tracker.records().unwrap()
+    }
+
+    fn compaction(max_bytes: usize, keep_iterations: u32) -> ProgressConfig {
+        ProgressConfig {
+            max_bytes,
+            keep_iterations,
+            ..ProgressConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_split_entries() {
+        let log = long_log(3, 10);
+        let (header, entries) = split_entries(&log);
+        assert!(header.starts_with("# RWL Progress Log"));
+        assert_eq!(
+            entries.iter().map(|e| e.record.iteration).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert!(entries[1].text.starts_with("## Iteration 2\n"));
+        assert_eq!(
+            header.len() + entries.iter().map(|e| e.text.len()).sum::<usize>(),
+            render(&log).len()
+        );
+    }
+
+    #[test]
+    fn test_compact_is_verbatim_within_limit_or_when_off() {
+        let log = long_log(10, 200);
+        let full = render(&log);
+        assert_eq!(compact(&log, &compaction(0, 2), None), full);
+        assert_eq!(compact(&log, &compaction(full.len(), 2), None), full);
+    }
+
+    #[test]
+    fn test_compact_tail_keeps_header_and_last_iterations() {
+        let log = long_log(10, 200);
+        let view = compact(&log, &compaction(2000, 3), None);
+        assert!(view.len() <= 2000, "{}", view.len());
+        assert!(view.starts_with("# RWL Progress Log"));
+        assert!(view.contains(
+            "[Iterations 1-7 omitted: 7 entries, 2 with validation passed, 0 stalled. \
+             Full history: .rwl/progress.txt]"
+        ));
+        assert!(!view.contains("## Iteration 7\n"));
+        for i in 8..=10 {
+            assert!(view.contains(&format!("## Iteration {}\n", i)));
+        }
+
+        // Keeps fewer iterations when the window itself overflows, never none.
+        let tight = compact(&log, &compaction(300, 3), None);
+        assert!(tight.contains("## Iteration 10\n"));
+        assert!(!tight.contains("## Iteration 9\n"));
+    }
 
-        assert_eq!(tracker.iteration_count().unwrap(), 3);
+    #[test]
+    fn test_compact_uses_digest_for_what_it_covers() {
+        let log = long_log(10, 200);
+        let digest = Digest {
+            through: 5,
+            text: "Iterations 1-5 fought the lexer.".to_string(),
+        };
+        let view = compact(&log, &compaction(2000, 3), Some(&digest));
+        assert!(view.contains("## Digest of iterations 1-5\nIterations 1-5 fought the lexer.\n"));
+        assert!(view.contains("[Iterations 6-7 omitted: 2 entries"));
+        assert!(view.contains("## Iteration 8\n"));
+    }
+
+    #[test]
+    fn test_fold_candidates_batches_refreshes() {
+        let log = long_log(10, 200);
+        let config = compaction(2000, 3);
+        assert!(fold_candidates(&log, &compaction(0, 3), None).is_none());
+
+        let fold = fold_candidates(&log, &config, None).unwrap();
+        assert_eq!(fold.through, 7);
+        assert!(fold.entries.starts_with("## Iteration 1\n"));
+        assert!(fold.entries.contains("## Iteration 7\n"));
+        assert!(!fold.entries.contains("## Iteration 8\n"));
+
+        // With a fresh digest the rest fits: nothing to fold until it grows.
+        let digest = Digest {
+            through: 7,
+            text: "short".to_string(),
+        };
+        assert!(fold_candidates(&log, &config, Some(&digest)).is_none());
+        let longer = long_log(16, 200);
+        let next = fold_candidates(&longer, &config, Some(&digest)).unwrap();
+        assert_eq!(next.through, 13);
+        assert!(next.entries.starts_with("## Iteration 8\n"));
     }
 }
@@ -1,7 +1,12 @@
+use crate::escalation::IterationModel;
+use crate::plan::PhaseStatus;
+use crate::retry::RateLimitPause;
+use crate::usage::RunUsage;
+use chrono::{DateTime, Utc};
 use eyre::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RunResult {
@@ -19,10 +24,36 @@ pub struct RunResult {
     /// The worktree branch the run committed to, when isolation produced one.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub branch: Option<String>,
+    /// The model each iteration ran with (climbs under `llm.escalation`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<IterationModel>,
+    /// What the agent reported doing, for iterations that emitted a summary
+    /// block.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub summaries: Vec<IterationSummary>,
+    /// The plan's phases at the end of the run (phased plans only).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub phases: Vec<PhaseStatus>,
+    /// Agent attempts retried under the `llm.retry` policy, across the run.
+    #[serde(default)]
+    pub retries: u32,
+    /// Pauses taken while the agent was rate-limited (they use no iteration).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rate_limit_pauses: Vec<RateLimitPause>,
+    /// Reported cost/token usage, present only in `stream-json` mode.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<RunUsage>,
     #[serde(skip)]
     pub session_dir: PathBuf,
 }
 
+/// An agent-authored iteration summary (`<rwl-summary>...</rwl-summary>`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IterationSummary {
+    pub iteration: u32,
+    pub summary: String,
+}
+
 impl RunResult {
     pub fn write_json(&self) -> Result<()> {
         let path = self.session_dir.join("result.json");
@@ -32,11 +63,114 @@ impl RunResult {
     }
 }
 
+/// The summary of a queue of plans (`rwl run` with several `--plan`s, or
+/// `rwl batch`), written next to their session directories.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchResult {
+    /// `complete` when every plan completed, else the outcome of the first
+    /// plan that did not.
+    pub outcome: String,
+    pub exit_code: i32,
+    pub started: String,
+    pub finished: String,
+    pub duration_secs: u64,
+    /// Each plan started from the previous plan's branch.
+    pub chain: bool,
+    pub stop_on_failure: bool,
+    pub plans: Vec<QueuedPlan>,
+}
+
+/// One plan of a queue and how its run ended.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedPlan {
+    pub plan: String,
+    /// The run's outcome; `error` when the run failed to start or crashed,
+    /// `skipped` when the queue stopped before reaching the plan.
+    pub outcome: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    pub iterations: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_dir: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Exit code of a run that failed with an error rather than an outcome, as
+/// `main` reports it.
+const ERROR_EXIT_CODE: i32 = 4;
+
+impl QueuedPlan {
+    pub fn finished(plan: &Path, result: &RunResult) -> Self {
+        Self {
+            plan: plan.display().to_string(),
+            outcome: result.outcome.clone(),
+            exit_code: Some(result.exit_code),
+            iterations: result.iterations,
+            session_dir: Some(result.session_dir.display().to_string()),
+            branch: result.branch.clone(),
+            error: result.error.clone(),
+        }
+    }
+
+    pub fn failed(plan: &Path, error: &eyre::Report) -> Self {
+        Self {
+            outcome: "error".to_string(),
+            exit_code: Some(ERROR_EXIT_CODE),
+            error: Some(format!("{:#}", error)),
+            ..Self::skipped(plan)
+        }
+    }
+
+    pub fn skipped(plan: &Path) -> Self {
+        Self {
+            plan: plan.display().to_string(),
+            outcome: "skipped".to_string(),
+            exit_code: None,
+            iterations: 0,
+            session_dir: None,
+            branch: None,
+            error: None,
+        }
+    }
+
+    pub fn complete(&self) -> bool {
+        self.outcome == "complete"
+    }
+}
+
+impl BatchResult {
+    /// Sum up `plans`, run between `started` and now.
+    pub fn new(plans: Vec<QueuedPlan>, started: DateTime<Utc>, chain: bool, stop_on_failure: bool) -> Self {
+        let finished = Utc::now();
+        let first_failed = plans.iter().find(|plan| !plan.complete() && plan.exit_code.is_some());
+        Self {
+            outcome: first_failed
+                .map_or("complete", |plan| plan.outcome.as_str())
+                .to_string(),
+            exit_code: first_failed.and_then(|plan| plan.exit_code).unwrap_or(0),
+            started: started.to_rfc3339(),
+            finished: finished.to_rfc3339(),
+            duration_secs: finished.signed_duration_since(started).num_seconds().max(0) as u64,
+            chain,
+            stop_on_failure,
+            plans,
+        }
+    }
+
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
     use super::*;
-    use std::path::Path;
 
     fn sample_result(dir: &Path) -> RunResult {
         RunResult {
@@ -51,6 +185,12 @@ mod tests {
             validation_passed: true,
             quality_gates_passed: true,
             branch: None,
+            models: Vec::new(),
+            summaries: Vec::new(),
+            phases: Vec::new(),
+            retries: 0,
+            rate_limit_pauses: Vec::new(),
+            usage: None,
             session_dir: dir.to_path_buf(),
         }
     }
@@ -121,6 +261,32 @@ mod tests {
         assert!(json.contains("rwl/my-plan-20260629-120000"));
     }
 
+    #[test]
+    fn test_usage_field_skipped_when_none() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = sample_result(dir.path());
+        let json = serde_json::to_string(&result).unwrap();
+        assert!(!json.contains("usage"));
+    }
+
+    #[test]
+    fn test_usage_field_present_when_some() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut result = sample_result(dir.path());
+        let mut usage = RunUsage::default();
+        usage.record(
+            1,
+            crate::usage::Usage {
+                cost_usd: 0.041,
+                ..Default::default()
+            },
+        );
+        result.usage = Some(usage);
+        let parsed: serde_json::Value = serde_json::to_value(&result).unwrap();
+        assert_eq!(parsed["usage"]["total"]["cost_usd"], 0.041);
+        assert_eq!(parsed["usage"]["iterations"][0]["iteration"], 1);
+    }
+
     #[test]
     fn test_roundtrip_serialization() {
         let dir = tempfile::tempdir().unwrap();
@@ -134,4 +300,35 @@ mod tests {
         assert_eq!(deserialized.quality_gates_passed, result.quality_gates_passed);
         assert_eq!(deserialized.duration_secs, result.duration_secs);
     }
+
+    #[test]
+    fn test_batch_result_takes_first_incomplete_plan() {
+        let dir = tempfile::tempdir().unwrap();
+        let complete = QueuedPlan::finished(Path::new("a.md"), &sample_result(dir.path()));
+        let mut exhausted = sample_result(dir.path());
+        exhausted.outcome = "max-iterations".to_string();
+        exhausted.exit_code = 1;
+        let plans = vec![
+            complete.clone(),
+            QueuedPlan::finished(Path::new("b.md"), &exhausted),
+            QueuedPlan::failed(Path::new("c.md"), &eyre::eyre!("boom")),
+            QueuedPlan::skipped(Path::new("d.md")),
+        ];
+
+        let batch = BatchResult::new(plans, Utc::now(), true, false);
+        assert_eq!(batch.outcome, "max-iterations");
+        assert_eq!(batch.exit_code, 1);
+        assert_eq!(batch.plans[2].exit_code, Some(4));
+
+        let all_done = BatchResult::new(vec![complete], Utc::now(), false, false);
+        assert_eq!(all_done.outcome, "complete");
+        assert_eq!(all_done.exit_code, 0);
+
+        let path = dir.path().join("batch.json");
+        batch.write_json(&path).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(parsed["plans"][3]["outcome"], "skipped");
+        assert!(parsed["plans"][3].get("exit_code").is_none());
+        assert_eq!(parsed["plans"][2]["error"], "boom");
+    }
 }
@@ -0,0 +1,431 @@
+use crate::agent::{AgentOutput, TimedOut};
+use crate::config::{RateLimitConfig, RetryConfig, RetryOn};
+use chrono::{DateTime, Duration as ChronoDuration, Local, NaiveTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Slack added to a parsed reset time so the re-run lands after the reset.
+const RESET_MARGIN_SECS: u64 = 30;
+
+/// Lines at the end of stdout and of stderr searched for a rate-limit
+/// message. A CLI reports the limit as it exits; earlier lines are the
+/// agent's own work, which may well mention rate limits.
+const RATE_LIMIT_TAIL_LINES: usize = 5;
+
+/// One rate-limit pause, as recorded in `result.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitPause {
+    pub iteration: u32,
+    pub waited_secs: u64,
+    /// The reset time parsed from the agent's message, when it carried one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reset_at: Option<String>,
+}
+
+/// Classify one agent attempt into a retryable failure class, if any.
+///
+/// A timeout error is [`RetryOn::Timeout`]; any other error (agent missing
+/// from `PATH`, spawn failure, a stall) is not a transient blip and is never
+/// classified. A non-zero exit is [`RetryOn::RateLimit`] when the tail of its
+/// output (see [`failure_tail`]) matches a rate-limit pattern, else
+/// [`RetryOn::NonZeroExit`]. A clean exit is never a failure, even if the
+/// agent's prose mentions rate limits.
+pub fn classify(attempt: &eyre::Result<AgentOutput>, patterns: &[String]) -> Option<RetryOn> {
+    match attempt {
+        Err(e) if e.downcast_ref::<TimedOut>().is_some() => Some(RetryOn::Timeout),
+        Err(_) => None,
+        Ok(output) if output.exit_code == Some(0) => None,
+        Ok(output) if is_rate_limited(&failure_tail(output), patterns) => Some(RetryOn::RateLimit),
+        Ok(_) => Some(RetryOn::NonZeroExit),
+    }
+}
+
+/// The last lines of a failed attempt's stdout and stderr: where the CLI
+/// reports why it exited (the stream-json `result` event is the last stdout
+/// line).
+pub fn failure_tail(output: &AgentOutput) -> String {
+    let tail = |text: &str| {
+        let lines: Vec<&str> = text.lines().collect();
+        lines[lines.len().saturating_sub(RATE_LIMIT_TAIL_LINES)..].join("\n")
+    };
+    format!("{}\n{}", tail(&output.stdout), tail(&output.stderr))
+}
+
+/// Whether `text` contains any rate-limit pattern as a whole word or phrase
+/// (case-insensitive): `rate limit` matches `Rate limit reached`, not
+/// `RateLimiter` or `rate limiting`.
+pub fn is_rate_limited(text: &str, patterns: &[String]) -> bool {
+    let text = text.to_lowercase();
+    patterns
+        .iter()
+        .map(|p| p.trim().to_lowercase())
+        .filter(|p| !p.is_empty())
+        .any(|p| contains_word(&text, &p))
+}
+
+fn contains_word(text: &str, word: &str) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    text.match_indices(word).any(|(start, _)| {
+        let end = start + word.len();
+        !text[..start].chars().next_back().is_some_and(is_word) && !text[end..].chars().next().is_some_and(is_word)
+    })
+}
+
+/// Whether `class` may be retried after `attempt` (1-based) under `policy`.
+pub fn should_retry(policy: &RetryConfig, class: RetryOn, attempt: u32) -> bool {
+    attempt < policy.max_attempts && policy.retry_on.contains(&class)
+}
+
+/// Backoff before retry number `retry` (1-based): `backoff-secs` doubled per
+/// retry, capped at `max-backoff-secs`.
+pub fn backoff(policy: &RetryConfig, retry: u32) -> Duration {
+    let factor = 2u64.saturating_pow(retry.saturating_sub(1));
+    Duration::from_secs(policy.backoff_secs.saturating_mul(factor).min(policy.max_backoff_secs))
+}
+
+/// How long to pause for a rate-limited attempt, and the reset it targets.
+///
+/// Waits until the reset time parsed from the output (plus a small margin), or
+/// `default-wait-minutes` when none is found, clamped to what is left of the
+/// iteration's `max-wait-minutes` allowance. `None` when pausing is disabled
+/// or the allowance is spent.
+pub fn rate_limit_wait(
+    config: &RateLimitConfig,
+    output: &str,
+    patterns: &[String],
+    already_waited: Duration,
+    now: DateTime<Local>,
+) -> Option<(Duration, Option<DateTime<Local>>)> {
+    let allowance = Duration::from_secs(config.max_wait_minutes * 60).saturating_sub(already_waited);
+    if allowance.is_zero() {
+        return None;
+    }
+    let reset = parse_reset(output, patterns, now);
+    let wanted = match reset {
+        Some(reset) => (reset - now).to_std().unwrap_or_default() + Duration::from_secs(RESET_MARGIN_SECS),
+        None => Duration::from_secs(config.default_wait_minutes * 60),
+    };
+    Some((wanted.min(allowance), reset))
+}
+
+/// Parse a reset time out of the rate-limit lines of `output`.
+///
+/// Only lines matching a rate-limit pattern are considered. Understands
+/// `...limit reached|<unix-seconds>` (a time after `now`), relative `in 30 seconds` / `in 2 hours`,
+/// and a clock time after `reset(s) (at)` (`3pm`, `3:30 pm`, `15:00`, read as
+/// local time, rolling to tomorrow if already past).
+pub fn parse_reset(output: &str, patterns: &[String], now: DateTime<Local>) -> Option<DateTime<Local>> {
+    output
+        .lines()
+        .filter(|line| is_rate_limited(line, patterns))
+        .find_map(|line| parse_reset_line(line, now))
+}
+
+fn parse_reset_line(line: &str, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    if let Some((_, tail)) = line.rsplit_once('|') {
+        let digits: String = tail.trim().chars().take_while(|c| c.is_ascii_digit()).collect();
+        if let Some(reset) = digits
+            .parse::<i64>()
+            .ok()
+            .filter(|secs| *secs > now.timestamp())
+            .and_then(|secs| Local.timestamp_opt(secs, 0).single())
+        {
+            return Some(reset);
+        }
+    }
+
+    let lower = line.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == ':'))
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    for (i, word) in words.iter().enumerate() {
+        if *word == "in"
+            && let (Some(n), Some(unit)) = (words.get(i + 1).and_then(|n| n.parse::<i64>().ok()), words.get(i + 2))
+        {
+            // `n` is the agent's text: out of range means no usable reset.
+            let delta = match unit.trim_end_matches('s') {
+                "second" | "sec" => ChronoDuration::try_seconds(n)?,
+                "minute" | "min" => ChronoDuration::try_minutes(n)?,
+                "hour" | "hr" => ChronoDuration::try_hours(n)?,
+                _ => continue,
+            };
+            return now.checked_add_signed(delta);
+        }
+        if word.starts_with("reset") {
+            let rest = &words[i + 1..];
+            let rest = rest.strip_prefix(&["at"]).unwrap_or(rest);
+            if let Some(time) = parse_clock(rest) {
+                let mut reset = now.date_naive().and_time(time).and_local_timezone(Local).earliest()?;
+                if reset <= now {
+                    reset += ChronoDuration::days(1);
+                }
+                return Some(reset);
+            }
+        }
+    }
+    None
+}
+
+/// Parse `3pm`, `3:30pm`, `3 pm`, `3:30 am` or `15:00` from the head of `words`.
+fn parse_clock(words: &[&str]) -> Option<NaiveTime> {
+    let first = *words.first()?;
+    let (clock, meridiem) = if let Some(clock) = first.strip_suffix("am") {
+        (clock, Some(false))
+    } else if let Some(clock) = first.strip_suffix("pm") {
+        (clock, Some(true))
+    } else {
+        let meridiem = match words.get(1) {
+            Some(&"am") => Some(false),
+            Some(&"pm") => Some(true),
+            _ => None,
+        };
+        (first, meridiem)
+    };
+    let (hour, minute) = match clock.split_once(':') {
+        Some((h, m)) => (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?),
+        None => (clock.parse::<u32>().ok()?, 0),
+    };
+    let hour = match meridiem {
+        Some(pm) if (1..=12).contains(&hour) => hour % 12 + if pm { 12 } else { 0 },
+        Some(_) => return None,
+        // A bare number is only a clock time with minutes ("15:00").
+        None if clock.contains(':') => hour,
+        None => return None,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// Render a parsed reset time for `result.json` (UTC, RFC 3339).
+pub fn reset_to_rfc3339(reset: DateTime<Local>) -> String {
+    reset.with_timezone(&Utc).to_rfc3339()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn exited(code: Option<i32>, stdout: &str) -> eyre::Result<AgentOutput> {
+        Ok(AgentOutput {
+            stdout: stdout.to_string(),
+            exit_code: code,
+            ..AgentOutput::default()
+        })
+    }
+
+    fn patterns() -> Vec<String> {
+        RetryConfig::default().rate_limit_patterns
+    }
+
+    #[test]
+    fn test_clean_exit_is_never_a_failure() {
+        assert_eq!(
+            classify(&exited(Some(0), "added rate limit middleware"), &patterns()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_non_zero_exit_classes() {
+        assert_eq!(
+            classify(
+                &exited(
+                    Some(1),
+                    r#"API Error: 529 {"type":"error","error":{"type":"overloaded_error"}}"#
+                ),
+                &patterns()
+            ),
+            Some(RetryOn::RateLimit)
+        );
+        assert_eq!(
+            classify(&exited(Some(2), "panic"), &patterns()),
+            Some(RetryOn::NonZeroExit)
+        );
+        assert_eq!(classify(&exited(None, ""), &patterns()), Some(RetryOn::NonZeroExit));
+    }
+
+    #[test]
+    fn test_rate_limit_only_in_the_output_tail_as_a_word() {
+        // The agent's own work on rate limiting, HTTP 429 handling or
+        // overloaded operators is not a rate limit.
+        for work in [
+            "error[E0034]: multiple applicable items: overloaded `add`",
+            "test http::returns_429 ... FAILED",
+            "error: cannot find type `RateLimiter`; add the rate_limiter crate",
+            "thread 'rate limiting' panicked",
+        ] {
+            assert_eq!(
+                classify(&exited(Some(1), work), &patterns()),
+                Some(RetryOn::NonZeroExit),
+                "{}",
+                work
+            );
+        }
+
+        // Only the last lines count.
+        let early = format!("Rate limit reached\n{}", "compiling...\n".repeat(RATE_LIMIT_TAIL_LINES));
+        assert_eq!(
+            classify(&exited(Some(1), &early), &patterns()),
+            Some(RetryOn::NonZeroExit)
+        );
+        let late = format!("{}Claude AI usage limit reached|1", "compiling...\n".repeat(50));
+        assert_eq!(classify(&exited(Some(1), &late), &patterns()), Some(RetryOn::RateLimit));
+        let stderr = Ok(AgentOutput {
+            stdout: "compiling...\n".repeat(50),
+            stderr: "Error: 429 Too Many Requests".to_string(),
+            exit_code: Some(1),
+            ..AgentOutput::default()
+        });
+        assert_eq!(classify(&stderr, &patterns()), Some(RetryOn::RateLimit));
+    }
+
+    #[test]
+    fn test_errors_only_timeout_is_retryable() {
+        let timed_out: eyre::Result<AgentOutput> = Err(TimedOut {
+            backend: "claude".to_string(),
+            after: Duration::from_secs(600),
+            killed: Vec::new(),
+            stdout: String::new(),
+            stderr: String::new(),
+        }
+        .into());
+        assert_eq!(classify(&timed_out, &patterns()), Some(RetryOn::Timeout));
+        let missing: eyre::Result<AgentOutput> = Err(eyre::eyre!("claude not found on PATH"));
+        assert_eq!(classify(&missing, &patterns()), None);
+    }
+
+    #[test]
+    fn test_should_retry_respects_attempts_and_classes() {
+        let policy = RetryConfig {
+            max_attempts: 3,
+            retry_on: vec![RetryOn::Timeout],
+            ..RetryConfig::default()
+        };
+        assert!(should_retry(&policy, RetryOn::Timeout, 1));
+        assert!(should_retry(&policy, RetryOn::Timeout, 2));
+        assert!(!should_retry(&policy, RetryOn::Timeout, 3));
+        assert!(!should_retry(&policy, RetryOn::NonZeroExit, 1));
+        assert!(!should_retry(&RetryConfig::default(), RetryOn::Timeout, 1));
+    }
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        let policy = RetryConfig {
+            backoff_secs: 10,
+            max_backoff_secs: 35,
+            ..RetryConfig::default()
+        };
+        assert_eq!(backoff(&policy, 1), Duration::from_secs(10));
+        assert_eq!(backoff(&policy, 2), Duration::from_secs(20));
+        assert_eq!(backoff(&policy, 3), Duration::from_secs(35));
+        assert_eq!(backoff(&policy, 40), Duration::from_secs(35));
+    }
+
+    fn at(h: u32, m: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 3, 30, h, m, 0).earliest().unwrap()
+    }
+
+    #[test]
+    fn test_parse_reset_epoch_suffix() {
+        let now = at(10, 0);
+        let epoch = (now + ChronoDuration::minutes(90)).timestamp();
+        let output = format!("Claude AI usage limit reached|{}", epoch);
+        assert_eq!(
+            parse_reset(&output, &patterns(), now),
+            Some(now + ChronoDuration::minutes(90))
+        );
+    }
+
+    #[test]
+    fn test_parse_reset_ignores_implausible_epoch() {
+        let now = at(10, 0);
+        assert_eq!(parse_reset("rate limit | 429", &patterns(), now), None);
+        let past = (now - ChronoDuration::minutes(5)).timestamp();
+        let output = format!("Claude AI usage limit reached|{}", past);
+        assert_eq!(parse_reset(&output, &patterns(), now), None);
+    }
+
+    #[test]
+    fn test_parse_reset_relative_and_clock() {
+        let now = at(10, 0);
+        assert_eq!(
+            parse_reset("Rate limit hit, try again in 45 seconds", &patterns(), now),
+            Some(now + ChronoDuration::seconds(45))
+        );
+        assert_eq!(
+            parse_reset("5-hour usage limit reached - resets 3pm", &patterns(), now),
+            Some(at(15, 0))
+        );
+        assert_eq!(
+            parse_reset("usage limit reached. Your limit resets at 9:30 am", &patterns(), now),
+            Some(at(9, 30) + ChronoDuration::days(1))
+        );
+        assert_eq!(
+            parse_reset("rate limit; reset at 17:45", &patterns(), now),
+            Some(at(17, 45))
+        );
+    }
+
+    #[test]
+    fn test_parse_reset_ignores_out_of_range_relative_time() {
+        let now = at(10, 0);
+        assert_eq!(
+            parse_reset("Rate limit hit, try again in 99999999999999 hours", &patterns(), now),
+            None
+        );
+        assert_eq!(
+            parse_reset("Rate limit hit, try again in 9223372036854775807 seconds", &patterns(), now),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_reset_ignores_non_rate_limit_lines() {
+        let now = at(10, 0);
+        assert_eq!(parse_reset("retrying in 5 seconds", &patterns(), now), None);
+        assert_eq!(parse_reset("Rate limit reached", &patterns(), now), None);
+    }
+
+    #[test]
+    fn test_rate_limit_wait_uses_reset_default_and_allowance() {
+        let now = at(10, 0);
+        let config = RateLimitConfig {
+            max_wait_minutes: 60,
+            default_wait_minutes: 5,
+        };
+        let (wait, reset) = rate_limit_wait(
+            &config,
+            "rate limit, try again in 10 minutes",
+            &patterns(),
+            Duration::ZERO,
+            now,
+        )
+        .unwrap();
+        assert_eq!(wait, Duration::from_secs(10 * 60 + RESET_MARGIN_SECS));
+        assert!(reset.is_some());
+
+        let (wait, reset) =
+            rate_limit_wait(&config, "429 Too Many Requests", &patterns(), Duration::ZERO, now).unwrap();
+        assert_eq!(wait, Duration::from_secs(5 * 60));
+        assert!(reset.is_none());
+
+        let (wait, _) = rate_limit_wait(
+            &config,
+            "usage limit, resets 3pm",
+            &patterns(),
+            Duration::from_secs(50 * 60),
+            now,
+        )
+        .unwrap();
+        assert_eq!(wait, Duration::from_secs(10 * 60));
+
+        assert!(rate_limit_wait(&config, "429", &patterns(), Duration::from_secs(3600), now).is_none());
+        let disabled = RateLimitConfig {
+            max_wait_minutes: 0,
+            ..config
+        };
+        assert!(rate_limit_wait(&disabled, "429", &patterns(), Duration::ZERO, now).is_none());
+    }
+}
@@ -1,26 +1,33 @@
+use crate::agent::{self, AgentRequest};
+use crate::artifacts::{GateMeta, IterationArtifacts, IterationMeta};
 use crate::budget::Budget;
-use crate::config::Config;
+use crate::config::{Config, ProgressStrategy, RetryOn};
+use crate::escalation::{IterationModel, ModelLadder};
 use crate::git::GitManager;
+use crate::interrupt;
 use crate::judge;
-use crate::progress::{IterationResult, ProgressTracker};
-use crate::result::RunResult;
+use crate::plan::{PhaseStatus, Plan};
+use crate::progress::{
+    self, Digest, GateOutcome, IterationRecord, JudgeVerdict, PROGRESS_FILES, PROGRESS_PATH, PhaseCheck,
+    ProgressTracker, Record,
+};
+use crate::result::{IterationSummary, RunResult};
+use crate::retry::{self, RateLimitPause};
 use crate::session::SessionLog;
-use crate::templates::PROMPT_TEMPLATE;
-use crate::validation::ValidationRunner;
-use chrono::{DateTime, Utc};
+use crate::summarize;
+use crate::templates::PromptTemplate;
+use crate::usage::{RunUsage, Usage};
+use crate::validation::{ValidationResult, ValidationRunner};
+use chrono::{DateTime, Local, Utc};
 use colored::*;
 use eyre::{Context, Result};
-use handlebars::Handlebars;
 use indicatif::{ProgressBar, ProgressStyle};
+use serde_json::Value;
 use std::collections::HashMap;
-use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
-/// Detect a signal token in Claude's output using a line-exact match.
+/// Detect a signal token in the agent's output using a line-exact match.
 ///
 /// The signal must appear on its own line (after trimming) so that the model
 /// merely mentioning the token in prose (e.g. "I have not yet emitted
@@ -87,41 +94,242 @@ impl LoopOutcome {
 pub struct LoopRunner {
     work_dir: PathBuf,
     plan_path: PathBuf,
-    progress_path: PathBuf,
     config_path: PathBuf,
     session_dir: PathBuf,
     branch: Option<String>,
-    stop_flag: Arc<AtomicBool>,
+    /// The prompt template, resolved and parsed once at startup.
+    prompt: PromptTemplate,
     session: SessionLog,
+    /// Per-iteration spend, populated only in `stream-json` mode.
+    usage: RunUsage,
+    /// The model each iteration ran with.
+    models: Vec<IterationModel>,
+    /// Summaries the agent wrote, by iteration.
+    summaries: Vec<IterationSummary>,
+    /// The plan's phases as of the last iteration.
+    phases: Vec<PhaseStatus>,
+    /// Where a plan with phase checks has got: phases before this index
+    /// have passed their checks. `None` for other plans.
+    phase_gate: Option<usize>,
+    /// Agent attempts retried under `llm.retry`, across the run.
+    retries: u32,
+    /// Rate-limit pauses taken under `llm.rate-limit`, across the run.
+    rate_limit_pauses: Vec<RateLimitPause>,
+    /// State from earlier iterations exposed to the next prompt.
+    carryover: Carryover,
+    /// The progress files as `rwl` last left them, taken before each agent
+    /// run so agent edits to them can be undone.
+    progress_snapshot: Vec<(&'static str, String)>,
+    /// Digest of older progress entries (`progress.strategy: summarize`).
+    digest: Option<Digest>,
+}
+
+/// What earlier iterations leave behind for the next prompt's variables.
+#[derive(Debug, Default)]
+pub(crate) struct Carryover {
+    /// `git diff --stat` of the previous iteration (`last_diffstat`).
+    pub diffstat: Option<String>,
+    /// Quality gates that failed the last time they ran (`failed_gates`).
+    pub failed_gates: Vec<String>,
+    /// The judge's explanation the last time it rejected (`judge_rejection`).
+    pub judge_rejection: Option<String>,
+}
+
+impl Carryover {
+    /// The carry-over `session_dir` would hand its next iteration, rebuilt
+    /// from the recorded iteration artifacts: the last iteration's
+    /// `diff.patch`, the last gate run's failures and the last judge
+    /// rejection. Best-effort; missing artifacts leave the field empty.
+    pub(crate) fn replay(session_dir: &Path, metas: &[IterationMeta], config: &Config) -> Self {
+        let mut carryover = Carryover::default();
+        if let Some(last) = metas.last() {
+            let patch = IterationArtifacts::dir_for(session_dir, last.iteration).join("diff.patch");
+            if std::fs::metadata(&patch).is_ok_and(|m| m.len() > 0) {
+                match GitManager::new(session_dir).patch_stat(&patch) {
+                    Ok(stat) => carryover.diffstat = Some(stat.trim_end().to_string()),
+                    Err(e) => log::warn!("Carryover::replay: path={} error={}", patch.display(), e),
+                }
+            }
+        }
+        if let Some(gated) = metas.iter().rev().find(|m| !m.quality_gates.is_empty()) {
+            carryover.failed_gates = gated
+                .quality_gates
+                .iter()
+                .filter(|gate| !gate.passed)
+                .map(|gate| gate.name.clone())
+                .collect();
+        }
+        if let Some(rejected) = metas.iter().rev().find(|m| m.judge_passed == Some(false)) {
+            let log = IterationArtifacts::dir_for(session_dir, rejected.iteration).join("judge.log");
+            let signal = config
+                .judge
+                .as_ref()
+                .map(|judge| judge.signal.as_str())
+                .unwrap_or_default();
+            let explanation = std::fs::read_to_string(log)
+                .map(|output| judge::extract_explanation(&output, signal))
+                .unwrap_or_default();
+            carryover.judge_rejection = Some(if explanation.is_empty() {
+                "(no explanation provided)".to_string()
+            } else {
+                explanation
+            });
+        }
+        carryover
+    }
+}
+
+/// Everything besides the config that one iteration's prompt is rendered from.
+pub(crate) struct PromptInputs<'a> {
+    pub template: &'a PromptTemplate,
+    /// Where the `file` and `git-log` helpers and `recent_commits` resolve.
+    pub work_dir: &'a Path,
+    pub plan_path: &'a Path,
+    /// The phase to show, for a plan with phase checks; otherwise the plan's
+    /// first unchecked phase is shown.
+    pub phase: Option<usize>,
+    pub iteration: u32,
+    /// Contents of the run's progress log.
+    pub progress: &'a str,
+    /// Wall-clock budget left, when `budget.max-total-minutes` is set.
+    pub remaining: Option<Duration>,
+    pub carryover: &'a Carryover,
+}
+
+/// Render the prompt for one iteration, injecting accumulated
+/// progress/feedback and the loop state the agent would otherwise rediscover
+/// with tool calls.
+///
+/// Variables: `completion_signal`, `plan_path`, `progress_path` (the progress
+/// log, relative to the work dir), `iteration`, `max_iterations`, and when
+/// available `remaining_minutes` (wall-clock budget), `progress`,
+/// `protected_paths`, `last_diffstat` (previous iteration), `recent_commits`
+/// (list, `prompt.recent-commits` long), `failed_gates` (list, from the last
+/// gate run), `judge_rejection`, and for a phased plan `phase` (the current
+/// phase: `number`, `count`, `completed`, `title` and its markdown `text`).
+pub(crate) fn render_prompt(inputs: &PromptInputs, config: &Config) -> Result<String> {
+    // The template (project PROMPT.md, `prompt.template` or built-in),
+    // its partials and the prompt helpers.
+    let handlebars = inputs.template.engine(inputs.work_dir)?;
+
+    // Build template data
+    let mut data: HashMap<&str, Value> = HashMap::new();
+    data.insert("completion_signal", config.loop_config.completion_signal.clone().into());
+    data.insert("plan_path", inputs.plan_path.display().to_string().into());
+    data.insert("progress_path", PROGRESS_PATH.into());
+    data.insert("iteration", inputs.iteration.into());
+    data.insert("max_iterations", config.loop_config.max_iterations.into());
+    if let Some(remaining) = inputs.remaining {
+        data.insert("remaining_minutes", (remaining.as_secs() / 60).into());
+    }
+    if !inputs.progress.trim().is_empty() {
+        data.insert("progress", inputs.progress.into());
+    }
+
+    // The current phase of a phased plan, so the agent sees only its part.
+    match Plan::load(inputs.plan_path) {
+        Ok(plan) => {
+            let current = match inputs.phase {
+                Some(index) => plan.phases.get(index).map(|phase| (index, phase)),
+                None => plan.current_phase(),
+            };
+            if let Some((index, phase)) = current {
+                let completed = match inputs.phase {
+                    Some(index) => index,
+                    None => plan.phases.iter().filter(|p| p.complete()).count(),
+                };
+                let phase = serde_json::json!({
+                    "number": index + 1,
+                    "count": plan.phases.len(),
+                    "completed": completed,
+                    "title": phase.title,
+                    "text": phase.text.trim_end(),
+                    "signal": phase.signal,
+                });
+                data.insert("phase", phase);
+            }
+        }
+        Err(e) => log::warn!("render_prompt: plan not parsed: {:#}", e),
+    }
+
+    // Loop state carried over from earlier iterations.
+    let carryover = inputs.carryover;
+    if let Some(diffstat) = carryover.diffstat.as_ref().filter(|s| !s.is_empty()) {
+        data.insert("last_diffstat", diffstat.clone().into());
+    }
+    if !carryover.failed_gates.is_empty() {
+        data.insert("failed_gates", carryover.failed_gates.clone().into());
+    }
+    if let Some(rejection) = &carryover.judge_rejection {
+        data.insert("judge_rejection", rejection.clone().into());
+    }
+    let git = GitManager::new(inputs.work_dir);
+    if config.prompt.recent_commits > 0
+        && git.is_repo()
+        && let Ok(commits) = git.recent_commits(config.prompt.recent_commits)
+        && !commits.is_empty()
+    {
+        data.insert("recent_commits", commits.into());
+    }
+
+    // Inject the protected-path list so the agent knows which paths are
+    // off-limits (edits are reverted by the protected-path guard).
+    if !config.safety.protected_paths.is_empty() {
+        let list = config
+            .safety
+            .protected_paths
+            .iter()
+            .map(|p| format!("- {}", p))
+            .collect::<Vec<_>>()
+            .join("\n");
+        data.insert("protected_paths", list.into());
+    }
+
+    // Render the template
+    let prompt = handlebars
+        .render(PromptTemplate::NAME, &data)
+        .context("Failed to render prompt template")?;
+
+    Ok(prompt)
 }
 
 impl LoopRunner {
-    pub fn new(work_dir: &Path, plan_path: PathBuf, session_dir: PathBuf, branch: Option<String>) -> Result<Self> {
+    pub fn new(
+        work_dir: &Path,
+        plan_path: PathBuf,
+        session_dir: PathBuf,
+        branch: Option<String>,
+        prompt: PromptTemplate,
+    ) -> Result<Self> {
         log::debug!(
-            "LoopRunner::new: work_dir={} plan_path={} session_dir={} branch={:?}",
+            "LoopRunner::new: work_dir={} plan_path={} session_dir={} branch={:?} prompt={}",
             work_dir.display(),
             plan_path.display(),
             session_dir.display(),
-            branch
+            branch,
+            prompt.source
         );
         let session = SessionLog::new(&session_dir)?;
-        let stop_flag = Arc::new(AtomicBool::new(false));
-        let flag_clone = stop_flag.clone();
-        ctrlc::set_handler(move || {
-            eprintln!("\n{} Received Ctrl-C, finishing current iteration...", "⚠".yellow());
-            flag_clone.store(true, Ordering::SeqCst);
-        })
-        .context("Failed to set Ctrl-C handler")?;
+        interrupt::install()?;
 
         Ok(Self {
             work_dir: work_dir.to_path_buf(),
             plan_path,
-            progress_path: session_dir.join("progress.txt"),
             config_path: Config::local_config_path(work_dir),
             session_dir,
             branch,
-            stop_flag,
+            prompt,
             session,
+            usage: RunUsage::default(),
+            models: Vec::new(),
+            summaries: Vec::new(),
+            phases: Vec::new(),
+            phase_gate: None,
+            retries: 0,
+            rate_limit_pauses: Vec::new(),
+            carryover: Carryover::default(),
+            progress_snapshot: Vec::new(),
+            digest: None,
         })
     }
 
@@ -133,11 +341,22 @@ impl LoopRunner {
         // Load initial config
         let mut config = Config::load(Some(&self.config_path))?;
 
-        // Start the wall-clock budget (monotonic). A cap of 0 = unlimited.
-        let budget = Budget::start(config.budget.max_total_minutes);
+        // Start the run budget (monotonic wall-clock, plus reported cost and
+        // tokens in stream-json mode). A cap of 0 = unlimited.
+        let mut budget = Budget::start(&config.budget);
+
+        // Model ladder (`llm.escalation`), fixed for the run at start.
+        let mut ladder = ModelLadder::new(&config.llm);
 
         // Initialize progress tracker
-        let progress = ProgressTracker::new(&self.progress_path);
+        let progress = ProgressTracker::in_work_dir(&self.work_dir);
+
+        // A plan whose phases carry their own checks is verified phase by
+        // phase, starting from the first one not already ticked off.
+        self.phase_gate = Plan::load(&self.plan_path)
+            .ok()
+            .filter(Plan::gated)
+            .map(|plan| plan.first_unfinished());
 
         // Create progress bar
         let pb = ProgressBar::new(config.loop_config.max_iterations as u64);
@@ -154,27 +373,16 @@ impl LoopRunner {
         ))?;
 
         for iteration in 1..=config.loop_config.max_iterations {
-            // 0. Check for stop signal (Ctrl-C)
-            if self.stop_flag.load(Ordering::SeqCst) {
+            // 0. Check for stop signal (Ctrl-C, SIGTERM, SIGHUP)
+            if interrupt::stop_requested() {
                 pb.finish_with_message("stopped");
-                self.session.println(&format!(
-                    "{} Stopped by user (Ctrl-C) after {} iterations",
-                    "⚠".yellow(),
-                    iteration - 1
-                ))?;
-                // Auto-commit WIP before exiting
-                if config.git.auto_commit {
-                    let _ = self.git_auto_commit(iteration, &config);
-                }
-                let outcome = LoopOutcome::Stopped {
-                    iterations: iteration - 1,
-                    reason: "Received Ctrl-C".to_string(),
-                };
+                let outcome = self.stop(iteration, &config)?;
                 return Ok(self.build_result(&outcome, started, last_validation_passed, last_gates_passed));
             }
 
-            // 0b. Wall-clock budget check (before building the prompt /
-            // spawning Claude), per the Architecture diagram.
+            // 0b. Budget check (before building the prompt / spawning the
+            // agent), per the Architecture diagram. Spend recorded by the
+            // previous iteration is charged here, before the next one starts.
             if let Some(reason) = budget.exceeded() {
                 log::debug!("run: budget exceeded at iteration={} reason={}", iteration, reason);
                 pb.finish_with_message("budget exceeded");
@@ -195,18 +403,25 @@ impl LoopRunner {
                 };
                 return Ok(self.build_result(&outcome, started, last_validation_passed, last_gates_passed));
             }
-            log::trace!("run: iteration={} within wall-clock budget", iteration);
+            log::trace!("run: iteration={} within budget", iteration);
 
             pb.set_message(format!("iteration {}", iteration));
             pb.set_position((iteration - 1) as u64);
 
             // 1. Re-read config (live editing support)
             config = Config::load(Some(&self.config_path)).unwrap_or(config.clone());
+            if !config.llm.escalation.is_empty() {
+                config.llm.model = ladder.current().to_string();
+            }
+            self.models.push(IterationModel {
+                iteration,
+                model: config.llm.model.clone(),
+            });
 
             // 2. Build prompt
-            let prompt = self.build_prompt(&config)?;
+            let prompt = self.build_prompt(iteration, &config, &budget)?;
 
-            // 3. Run Claude with timeout
+            // 3. Run the agent with timeout
             self.session.println("")?;
             self.session.println(&format!(
                 "{} Running iteration {}...",
@@ -219,9 +434,44 @@ impl LoopRunner {
                 Utc::now().format("%Y-%m-%d %H:%M:%S UTC")
             ))?;
 
-            let output = match self.run_claude(&prompt, &config) {
+            // Everything this iteration produces goes under iterations/NNN/.
+            let mut artifacts = IterationArtifacts::new(&self.session_dir, iteration, &config.llm.model);
+            let start_rev = self.head_rev();
+            let mut record = IterationRecord::new(iteration, Utc::now(), &config.llm.model);
+            self.snapshot_progress();
+
+            let attempt = self.run_agent(iteration, &prompt, &config, &mut budget, &mut artifacts);
+            artifacts.write_meta()?;
+
+            // A second Ctrl-C (or SIGTERM/SIGHUP) has already stopped the
+            // agent's group: keep its work and stop without validating.
+            if interrupt::abort_requested() {
+                pb.finish_with_message("stopped");
+                if let Err(e) = &attempt {
+                    self.session.log(&format!("Agent stopped: {}", e))?;
+                }
+                record.reverted_paths = self.guard_protected_paths(iteration, &config)?;
+                let outcome = self.abort_iteration(iteration, record, &progress, &config)?;
+                self.record_diff(&artifacts, start_rev.as_deref());
+                return Ok(self.build_result(&outcome, started, last_validation_passed, last_gates_passed));
+            }
+
+            let output = match attempt.map_err(|e| e.downcast::<agent::Stalled>()) {
                 Ok(output) => output,
-                Err(e) => {
+                Err(Ok(stalled)) => {
+                    // A hung agent is a failed iteration, not a failed run:
+                    // record it as stalled and move on to a fresh invocation.
+                    self.record_stall(record, &stalled, &progress, &config)?;
+                    artifacts.meta().stalled = true;
+                    artifacts.write_meta()?;
+                    self.record_diff(&artifacts, start_rev.as_deref());
+                    self.climb_ladder(&mut ladder, false, iteration)?;
+                    if iteration < config.loop_config.max_iterations {
+                        self.sleep_unless_stopped(Duration::from_secs(config.loop_config.sleep_between_secs));
+                    }
+                    continue;
+                }
+                Err(Err(e)) => {
                     pb.finish_with_message("error");
                     self.session.log(&format!("ERROR: {}", e))?;
                     let outcome = LoopOutcome::Error {
@@ -235,51 +485,119 @@ impl LoopRunner {
             // 4. Protected-path guard: revert any agent edits to protected
             // paths BEFORE auto-commit, so they are never committed, and feed
             // the reverted paths back into the next iteration's prompt.
-            self.guard_protected_paths(iteration, &config)?;
+            record.reverted_paths = self.guard_protected_paths(iteration, &config)?;
 
             // 5. Auto-commit changes if enabled
             if config.git.auto_commit {
                 self.git_auto_commit(iteration, &config)?;
             }
+            self.record_diff(&artifacts, start_rev.as_deref());
 
             // 5. Run validation
             let validation_runner = ValidationRunner::new(&self.work_dir);
-            let validation_result = validation_runner.run_validation(&config.validation.command)?;
+            let validation_started = std::time::Instant::now();
+            let validation_command = self.validation_command(&config);
+            let validation_result = validation_runner.run_validation(&validation_command)?;
             let validation_passed = validation_result.passed;
             last_validation_passed = validation_passed;
             validation_runner.print_validation_result(&validation_result);
+            artifacts.write(
+                "validation.log",
+                &format!(
+                    "$ {}\nexit code: {}\n\n{}",
+                    validation_command, validation_result.exit_code, validation_result.output
+                ),
+            )?;
 
             // Log validation to session
             self.session.log(&format!(
-                "Validation: {} (exit code: {})",
+                "Validation: {} (exit code: {}, output: {})",
                 if validation_passed { "PASSED" } else { "FAILED" },
-                validation_result.exit_code
+                validation_result.exit_code,
+                artifacts.display("validation.log")
             ))?;
-            if !validation_passed && !validation_result.output.trim().is_empty() {
-                self.session.log(&validation_result.output)?;
+            if interrupt::abort_requested() {
+                pb.finish_with_message("stopped");
+                record.validation_passed = Some(validation_passed);
+                let outcome = self.abort_iteration(iteration, record, &progress, &config)?;
+                return Ok(self.build_result(&outcome, started, last_validation_passed, last_gates_passed));
             }
 
             // 6. Check for completion promise
             let promise_found = self.find_promise(&output, &config);
+            let meta = artifacts.meta();
+            meta.validation_exit_code = Some(validation_result.exit_code);
+            meta.validation_passed = Some(validation_passed);
+            meta.validation_secs = validation_started.elapsed().as_secs();
+            meta.promise_found = promise_found;
+            artifacts.write_meta()?;
+
+            // 6b. A plan with phase checks: verify the current phase, and
+            // accept the promise only once every phase has passed.
+            record.phase_check = self.check_phase(&output, &validation_result, &config, &mut artifacts)?;
+            let claimed = validation_passed && promise_found;
+            let phases_done = self.phases_done();
+            if claimed && !phases_done {
+                record.errors.push_str(
+                    "You signalled completion, but the plan's phases have not all passed their checks. \
+                     Finish the current phase first.\n",
+                );
+            }
 
-            // 7. Log progress (including validation errors for feedback)
-            let result = IterationResult {
-                iteration,
-                validation_passed,
-                promise_found,
-                summary: if validation_passed && promise_found {
-                    "Complete".to_string()
-                } else if validation_passed {
-                    "Validation passed, waiting for completion".to_string()
-                } else {
-                    "Validation failed".to_string()
-                },
-                validation_output: if validation_passed { String::new() } else { validation_result.output.clone() },
+            // 6c. `loop.require-checklist`: refuse completion while the plan,
+            // as the agent left it, still has unchecked items.
+            if claimed && config.loop_config.require_checklist {
+                record.unchecked = self.unchecked_items();
+            }
+            let rejection = if !claimed {
+                None
+            } else if !phases_done {
+                Some("plan phases remain".to_string())
+            } else if !record.unchecked.is_empty() {
+                Some(format!("{} plan item(s) unchecked", record.unchecked.len()))
+            } else {
+                None
+            };
+            if let Some(reason) = &rejection {
+                log::warn!("run: promise rejected iteration={} reason={}", iteration, reason);
+                self.session
+                    .println(&format!("{} Completion promise rejected: {}", "⚠".yellow(), reason))?;
+            }
+
+            // 7. Log progress (including validation errors for feedback),
+            // summarized in the agent's own words when it wrote a summary.
+            let agent_summary = progress::agent_summary(&output);
+            if let Some(summary) = &agent_summary {
+                self.summaries.push(IterationSummary {
+                    iteration,
+                    summary: summary.clone(),
+                });
+            }
+            record.validation_passed = Some(validation_passed);
+            record.promise_found = promise_found;
+            record.phases = self.phase_status();
+            record.summary = if let Some(summary) = &agent_summary {
+                summary.clone()
+            } else if let Some(reason) = &rejection {
+                format!("Completion promise rejected: {}", reason)
+            } else if validation_passed && promise_found {
+                "Complete".to_string()
+            } else if validation_passed {
+                "Validation passed, waiting for completion".to_string()
+            } else {
+                "Validation failed".to_string()
             };
-            progress.log_iteration(&result)?;
+            if !validation_passed {
+                record
+                    .errors
+                    .push_str(&progress::errors_excerpt(&validation_result.output));
+            }
+            record.finished = Utc::now();
+            progress.record(&record)?;
+            self.climb_ladder(&mut ladder, validation_passed, iteration)?;
 
             // Print and log status
-            self.print_iteration_status(&result)?;
+            self.print_iteration_status(&record)?;
 
             self.session.log(&format!(
                 "--- iteration {} completed at {} ---",
@@ -288,7 +606,7 @@ impl LoopRunner {
             ))?;
 
             // 8. Check exit conditions
-            if validation_passed && promise_found {
+            if claimed && rejection.is_none() {
                 self.session.println("")?;
                 self.session.println(&format!(
                     "{} Validation passed and completion promise found!",
@@ -303,20 +621,51 @@ impl LoopRunner {
 
                 validation_runner.print_quality_gate_results(&gate_result);
 
-                // Log quality gates to session
+                // Log quality gates to session; each gate's output goes to
+                // its own file in the iteration directory.
                 self.session.log("Quality Gates:")?;
                 for (name, passed, output) in &gate_result.results {
-                    self.session
-                        .log(&format!("  {} {}", if *passed { "PASS" } else { "FAIL" }, name))?;
-                    if !passed && !output.trim().is_empty() {
-                        self.session.log(output)?;
-                    }
+                    let log = IterationArtifacts::gate_file(name);
+                    artifacts.write(&log, output)?;
+                    self.session.log(&format!(
+                        "  {} {} (output: {})",
+                        if *passed { "PASS" } else { "FAIL" },
+                        name,
+                        artifacts.display(&log)
+                    ))?;
+                    artifacts.meta().quality_gates.push(GateMeta {
+                        name: name.clone(),
+                        passed: *passed,
+                        log,
+                    });
+                }
+                artifacts.write_meta()?;
+                record.gates = gate_result
+                    .results
+                    .iter()
+                    .map(|(name, passed, _)| GateOutcome {
+                        name: name.clone(),
+                        passed: *passed,
+                    })
+                    .collect();
+                record.finished = Utc::now();
+                progress.record(&record)?;
+                self.carryover.failed_gates = gate_result
+                    .results
+                    .iter()
+                    .filter(|(_, passed, _)| !passed)
+                    .map(|(name, _, _)| name.clone())
+                    .collect();
+                if interrupt::abort_requested() {
+                    pb.finish_with_message("stopped");
+                    let outcome = self.abort_iteration(iteration, record, &progress, &config)?;
+                    return Ok(self.build_result(&outcome, started, last_validation_passed, last_gates_passed));
                 }
 
                 if gate_result.all_passed {
                     // Judge gate: run only when configured, as the FINAL gate
                     // before declaring Complete. Absent -> no-op; present -> a
-                    // fresh Claude invocation that must emit the judge signal on
+                    // fresh agent invocation that must emit the judge signal on
                     // its own line to pass.
                     if let Some(judge_cfg) = &config.judge {
                         log::debug!(
@@ -327,11 +676,41 @@ impl LoopRunner {
                         self.session
                             .println(&format!("{} Running LLM-as-judge gate...", "→".cyan()))?;
 
-                        match judge::run_judge(judge_cfg, &self.work_dir, config.llm.dangerously_skip_permissions) {
+                        let judge_result = agent::backend_for(&config.llm).and_then(|backend| {
+                            judge::run_judge(
+                                judge_cfg,
+                                backend.as_ref(),
+                                &self.work_dir,
+                                artifacts.dir(),
+                                config.llm.dangerously_skip_permissions,
+                                config.llm.prompt_transport,
+                            )
+                        });
+                        artifacts.write(
+                            "judge.log",
+                            &match &judge_result {
+                                Ok((_, judge_output)) => judge_output.clone(),
+                                Err(e) => format!("Judge gate error: {:#}", e),
+                            },
+                        )?;
+                        artifacts.meta().judge_passed = judge_result.as_ref().ok().map(|(passed, _)| *passed);
+                        artifacts.write_meta()?;
+                        if interrupt::abort_requested() {
+                            pb.finish_with_message("stopped");
+                            let outcome = self.abort_iteration(iteration, record, &progress, &config)?;
+                            return Ok(self.build_result(&outcome, started, last_validation_passed, last_gates_passed));
+                        }
+                        match judge_result {
                             Ok((true, _judge_output)) => {
                                 log::debug!("run: judge PASS iteration={}", iteration);
                                 self.session.println(&format!("{} Judge gate passed!", "✓".green()))?;
                                 self.session.log("Judge gate: PASS")?;
+                                record.judge = Some(JudgeVerdict {
+                                    passed: true,
+                                    explanation: String::new(),
+                                });
+                                record.finished = Utc::now();
+                                progress.record(&record)?;
                             }
                             Ok((false, judge_output)) => {
                                 log::warn!("run: judge FAIL iteration={}", iteration);
@@ -339,25 +718,22 @@ impl LoopRunner {
                                     .println(&format!("{} Judge gate failed, continuing loop...", "⚠".yellow()))?;
                                 self.session.log("Judge gate: FAIL")?;
 
-                                // Append judge explanation to progress.txt so
-                                // the next iteration's prompt sees why it failed.
-                                let explanation = judge::extract_explanation(&judge_output, &judge_cfg.signal);
-                                let feedback = IterationResult {
-                                    iteration,
-                                    validation_passed: true,
-                                    promise_found: true,
-                                    summary: "LLM-as-judge gate rejected this iteration".to_string(),
-                                    validation_output: format!(
-                                        "The LLM-as-judge reviewed your work and found it incomplete.\n\
-                                         Judge feedback:\n{}",
-                                        if explanation.is_empty() {
-                                            "(no explanation provided)".to_string()
-                                        } else {
-                                            explanation
-                                        }
-                                    ),
-                                };
-                                ProgressTracker::new(&self.progress_path).log_iteration(&feedback)?;
+                                // Record the judge's explanation so the next
+                                // iteration's prompt sees why it failed.
+                                let mut explanation = judge::extract_explanation(&judge_output, &judge_cfg.signal);
+                                if explanation.is_empty() {
+                                    explanation = "(no explanation provided)".to_string();
+                                }
+                                self.carryover.judge_rejection = Some(explanation.clone());
+                                if agent_summary.is_none() {
+                                    record.summary = "LLM-as-judge gate rejected this iteration".to_string();
+                                }
+                                record.judge = Some(JudgeVerdict {
+                                    passed: false,
+                                    explanation,
+                                });
+                                record.finished = Utc::now();
+                                progress.record(&record)?;
                                 // Skip to next iteration - do NOT declare Complete.
                                 continue;
                             }
@@ -388,7 +764,7 @@ impl LoopRunner {
 
             // 9. Sleep before next iteration
             if iteration < config.loop_config.max_iterations {
-                std::thread::sleep(Duration::from_secs(config.loop_config.sleep_between_secs));
+                self.sleep_unless_stopped(Duration::from_secs(config.loop_config.sleep_between_secs));
             }
         }
 
@@ -400,148 +776,582 @@ impl LoopRunner {
         Ok(self.build_result(&outcome, started, last_validation_passed, last_gates_passed))
     }
 
-    /// Build the prompt for Claude, injecting accumulated progress/feedback
-    fn build_prompt(&self, config: &Config) -> Result<String> {
-        let mut handlebars = Handlebars::new();
+    /// Build the prompt for the agent from the session's progress and the
+    /// carried-over loop state. See [`render_prompt`].
+    fn build_prompt(&mut self, iteration: u32, config: &Config, budget: &Budget) -> Result<String> {
+        // Rendered from the typed records, not the markdown the agent can see
+        let records = ProgressTracker::in_work_dir(&self.work_dir)
+            .records()
+            .unwrap_or_default();
+        let progress = self.compact_progress(&records, config)?;
+
+        let inputs = PromptInputs {
+            template: &self.prompt,
+            work_dir: &self.work_dir,
+            plan_path: &self.plan_path,
+            phase: self.phase_gate,
+            iteration,
+            progress: &progress,
+            remaining: budget.remaining(),
+            carryover: &self.carryover,
+        };
+        render_prompt(&inputs, config)
+    }
 
-        // Register the template
-        handlebars
-            .register_template_string("prompt", PROMPT_TEMPLATE)
-            .context("Failed to register prompt template")?;
+    /// The progress to inline into the prompt, compacted per `progress:`.
+    ///
+    /// Under `summarize`, an oversized log first has its older entries folded
+    /// into the digest (kept in the session dir); if that call fails they are
+    /// only counted, as under `tail`, and the fold is retried next iteration.
+    fn compact_progress(&mut self, records: &[Record], config: &Config) -> Result<String> {
+        if config.progress.strategy != ProgressStrategy::Summarize {
+            return Ok(progress::compact(records, &config.progress, None));
+        }
+        if let Some(fold) = progress::fold_candidates(records, &config.progress, self.digest.as_ref()) {
+            let model = config.progress.summary_model.as_deref().unwrap_or(&config.llm.model);
+            self.session.println(&format!(
+                "{} Summarizing progress through iteration {} ({})...",
+                "→".cyan(),
+                fold.through,
+                model
+            ))?;
+            let summary = agent::backend_for(&config.llm).and_then(|backend| {
+                summarize::run_summary(
+                    backend.as_ref(),
+                    &config.llm,
+                    model,
+                    self.digest.as_ref(),
+                    &fold,
+                    &self.work_dir,
+                    &self.session_dir,
+                )
+            });
+            match summary.and_then(|digest| summarize::save_digest(&self.session_dir, &digest).map(|()| digest)) {
+                Ok(digest) => {
+                    self.session
+                        .log(&format!("Progress digest now covers iterations 1-{}", digest.through))?;
+                    self.digest = Some(digest);
+                }
+                Err(e) => {
+                    log::warn!("compact_progress: summary failed: {:#}", e);
+                    self.session.println(&format!(
+                        "{} Progress summary failed, keeping the tail only: {}",
+                        "⚠".yellow(),
+                        e
+                    ))?;
+                }
+            }
+        }
+        Ok(progress::compact(records, &config.progress, self.digest.as_ref()))
+    }
 
-        // Read progress content to inject into prompt
-        let progress_content = if self.progress_path.exists() {
-            std::fs::read_to_string(&self.progress_path).unwrap_or_default()
-        } else {
-            String::new()
+    /// Run the configured agent backend with the given prompt, streaming
+    /// output and enforcing the iteration timeout and stall watchdog.
+    ///
+    /// A rate-limited attempt first pauses until the limit resets and re-runs
+    /// the same iteration (bounded by `llm.rate-limit` and the remaining
+    /// wall-clock budget); other failures go through the `llm.retry` policy.
+    ///
+    /// Every attempt's reported usage (stream-json only) is charged against
+    /// the budget as soon as it ends, retried and rate-limited ones included;
+    /// an attempt killed on timeout or stall never reports any. Returns the
+    /// combined output of the last attempt.
+    fn run_agent(
+        &mut self,
+        iteration: u32,
+        prompt: &str,
+        config: &Config,
+        budget: &mut Budget,
+        artifacts: &mut IterationArtifacts,
+    ) -> Result<String> {
+        let backend = agent::backend_for(&config.llm)?;
+        let timeout = Duration::from_secs((config.loop_config.iteration_timeout_minutes * 60) as u64);
+        let stall_timeout = match config.loop_config.stall_timeout_minutes {
+            0 => None,
+            minutes => Some(Duration::from_secs(minutes as u64 * 60)),
+        };
+        let prompt_file = artifacts.path("prompt.md");
+        let work_dir = self.work_dir.clone();
+        let request = AgentRequest {
+            model: &config.llm.model,
+            prompt,
+            work_dir: &work_dir,
+            prompt_file: &prompt_file,
+            skip_permissions: config.llm.dangerously_skip_permissions,
+            output_format: config.llm.output_format,
+            transport: config.llm.prompt_transport,
         };
 
-        // Build template data
-        let mut data = HashMap::new();
-        data.insert(
-            "completion_signal".to_string(),
-            config.loop_config.completion_signal.clone(),
-        );
-        data.insert("plan_path".to_string(), self.plan_path.display().to_string());
-        if !progress_content.trim().is_empty() {
-            data.insert("progress".to_string(), progress_content);
-        }
+        // An abort (second Ctrl-C, SIGTERM, SIGHUP) stops the agent's whole
+        // process group, not just the agent.
+        let limits = agent::Limits {
+            stall_timeout,
+            grace: Duration::from_secs(config.loop_config.kill_grace_secs),
+            cancel: Some(interrupt::abort_flag()),
+            ..agent::Limits::new(timeout)
+        };
+
+        let policy = &config.llm.retry;
+        // `attempt` counts against `llm.retry`; `runs` counts every
+        // invocation, rate-limit re-runs included, and numbers their files.
+        let mut attempt = 1;
+        let mut runs = 0;
+        let mut rate_limit_waited = Duration::ZERO;
+        let started = std::time::Instant::now();
+        let output = loop {
+            runs += 1;
+            artifacts.meta().agent_attempts = runs;
+            let result = agent::run_agent(backend.as_ref(), &request, &limits, true);
+            artifacts.meta().agent_secs = started.elapsed().as_secs();
+            self.log_stopped_processes(&result)?;
+            if let Ok(output) = &result
+                && let Some(usage) = output.usage
+            {
+                self.charge(iteration, usage, budget)?;
+            }
+            let Some(class) = retry::classify(&result, &policy.rate_limit_patterns) else {
+                break result;
+            };
+
+            if class == RetryOn::RateLimit
+                && let Ok(output) = &result
+                && let Some((wait, reset)) = retry::rate_limit_wait(
+                    &config.llm.rate_limit,
+                    &retry::failure_tail(output),
+                    &policy.rate_limit_patterns,
+                    rate_limit_waited,
+                    Local::now(),
+                )
+            {
+                // The wall-clock budget wins: never pause past it.
+                let wait = budget.remaining().map_or(wait, |left| wait.min(left));
+                if !wait.is_zero() && !interrupt::stop_requested() {
+                    self.log_agent_output(artifacts, backend.name(), output, Some(runs))?;
+                    let until = reset
+                        .map(|r| format!(" (limit resets {})", r.format("%Y-%m-%d %H:%M:%S %Z")))
+                        .unwrap_or_default();
+                    log::warn!(
+                        "run_agent: iteration={} rate-limited, pausing {}s{}",
+                        iteration,
+                        wait.as_secs(),
+                        until
+                    );
+                    self.session.println(&format!(
+                        "{} Rate-limited, pausing {}s{}...",
+                        "⏸".yellow(),
+                        wait.as_secs(),
+                        until
+                    ))?;
+                    let paused = std::time::Instant::now();
+                    self.sleep_unless_stopped(wait);
+                    let waited = paused.elapsed();
+                    rate_limit_waited += waited;
+                    self.session.log(&format!(
+                        "RATE LIMIT: iteration {} paused {}s{}",
+                        iteration,
+                        waited.as_secs(),
+                        until
+                    ))?;
+                    self.rate_limit_pauses.push(RateLimitPause {
+                        iteration,
+                        waited_secs: waited.as_secs(),
+                        reset_at: reset.map(retry::reset_to_rfc3339),
+                    });
+                    if interrupt::stop_requested() || budget.exceeded().is_some() {
+                        // Let the loop's own stop / budget checks end the run.
+                        break result;
+                    }
+                    continue;
+                }
+            }
+            if !retry::should_retry(policy, class, attempt)
+                || interrupt::stop_requested()
+                || budget.exceeded().is_some()
+            {
+                // Out of attempts: a timeout still ends the run, a non-zero
+                // exit still proceeds to validation, exactly as without a policy.
+                break result;
+            }
+
+            let delay = retry::backoff(policy, attempt);
+            let detail = match &result {
+                Ok(output) => format!(
+                    "exit code {}",
+                    output
+                        .exit_code
+                        .map(|c| c.to_string())
+                        .unwrap_or_else(|| "signal".to_string())
+                ),
+                Err(e) => e.to_string(),
+            };
+            match &result {
+                Ok(output) => self.log_agent_output(artifacts, backend.name(), output, Some(runs))?,
+                Err(e) => {
+                    if let Some(partial) = agent::partial_output(e) {
+                        self.log_agent_output(artifacts, backend.name(), &partial, Some(runs))?;
+                    }
+                }
+            }
+            log::warn!(
+                "run_agent: iteration={} attempt={} class={} retrying in {}s",
+                iteration,
+                attempt,
+                class,
+                delay.as_secs()
+            );
+            self.session.println(&format!(
+                "{} Attempt {}/{} failed ({}: {}), retrying in {}s...",
+                "⚠".yellow(),
+                attempt,
+                policy.max_attempts,
+                class,
+                detail,
+                delay.as_secs()
+            ))?;
+            self.session.log(&format!(
+                "RETRY: iteration {} attempt {} failed ({}: {}); retrying in {}s",
+                iteration,
+                attempt,
+                class,
+                detail,
+                delay.as_secs()
+            ))?;
+            self.retries += 1;
+            attempt += 1;
+            self.sleep_unless_stopped(delay);
+        };
 
-        // Inject the protected-path list so the agent knows which paths are
-        // off-limits (edits are reverted by the protected-path guard).
-        if !config.safety.protected_paths.is_empty() {
-            let list = config
-                .safety
-                .protected_paths
+        // A killed agent (timeout, stall) leaves what it printed before it
+        // was stopped.
+        let output = match output {
+            Ok(output) => output,
+            Err(e) => {
+                if let Some(partial) = agent::partial_output(&e) {
+                    self.log_agent_output(artifacts, backend.name(), &partial, None)?;
+                }
+                return Err(e);
+            }
+        };
+        artifacts.meta().agent_exit_code = output.exit_code;
+        self.log_agent_output(artifacts, backend.name(), &output, None)?;
+
+        Ok(output.combined())
+    }
+
+    /// stream-json diagnostics: surface one attempt's reported spend, record
+    /// it and charge it against the budget.
+    fn charge(&mut self, iteration: u32, usage: Usage, budget: &mut Budget) -> Result<()> {
+        budget.record(&usage);
+        self.usage.record(iteration, usage);
+        self.session.println(&format!(
+            "  {} {} (run total: ${:.4})",
+            "Usage:".dimmed(),
+            usage.to_string().dimmed(),
+            self.usage.total.cost_usd
+        ))
+    }
+
+    /// Record processes the agent left behind, or that were stopped with it.
+    fn log_stopped_processes(&mut self, attempt: &Result<agent::AgentOutput>) -> Result<()> {
+        let (when, processes) = match attempt {
+            Ok(output) if output.cancelled => ("on abort", &output.reaped),
+            Ok(output) => ("left running after exit", &output.reaped),
+            Err(e) => match (e.downcast_ref::<agent::TimedOut>(), e.downcast_ref::<agent::Stalled>()) {
+                (Some(timed_out), _) => ("on timeout", &timed_out.killed),
+                (_, Some(stalled)) => ("on stall", &stalled.killed),
+                _ => return Ok(()),
+            },
+        };
+        if processes.is_empty() {
+            return Ok(());
+        }
+        self.session.println(&format!(
+            "  {} stopped {} process(es) in the agent's group {}",
+            "⚠".yellow(),
+            processes.len(),
+            when
+        ))?;
+        self.session.log(&format!(
+            "PROCESS GROUP: stopped {} process(es) {}:\n{}",
+            processes.len(),
+            when,
+            processes
                 .iter()
-                .map(|p| format!("- {}", p))
+                .map(|p| format!("  {}", p))
                 .collect::<Vec<_>>()
-                .join("\n");
-            data.insert("protected_paths".to_string(), list);
+                .join("\n")
+        ))
+    }
+
+    /// Save one agent run's stdout and stderr to the iteration directory
+    /// (`retried_attempt` numbers a run that was re-run) and log its
+    /// exit code with a pointer to the files.
+    fn log_agent_output(
+        &mut self,
+        artifacts: &IterationArtifacts,
+        name: &str,
+        output: &agent::AgentOutput,
+        retried_attempt: Option<u32>,
+    ) -> Result<()> {
+        let stdout = artifacts.write_agent_output(output, retried_attempt)?;
+        self.session.log(&format!(
+            "--- {} exit code: {} (output: {}) ---",
+            name,
+            output
+                .exit_code
+                .map(|c| c.to_string())
+                .unwrap_or_else(|| "signal".to_string()),
+            stdout
+        ))
+    }
+
+    /// The commit HEAD points at, when the work dir is a git repository.
+    fn head_rev(&self) -> Option<String> {
+        let git = GitManager::new(&self.work_dir);
+        if !git.is_repo() {
+            return None;
         }
+        git.head().ok()
+    }
 
-        // Render the template
-        let prompt = handlebars
-            .render("prompt", &data)
-            .context("Failed to render prompt template")?;
+    /// Write the iteration's `diff.patch` (everything changed since
+    /// `start_rev`, committed or not) and keep its diffstat for the next
+    /// prompt. Best-effort; a failure only costs the artifact.
+    fn record_diff(&mut self, artifacts: &IterationArtifacts, start_rev: Option<&str>) {
+        self.carryover.diffstat = None;
+        let Some(rev) = start_rev else {
+            return;
+        };
+        let git = GitManager::new(&self.work_dir);
+        let written = git
+            .diff_since(rev)
+            .and_then(|diff| artifacts.write("diff.patch", &diff));
+        if let Err(e) = written {
+            log::warn!("record_diff: iteration_dir={} error={}", artifacts.dir().display(), e);
+        }
+        match git.diff_stat_since(rev) {
+            Ok(stat) => self.carryover.diffstat = Some(stat.trim_end().to_string()),
+            Err(e) => log::warn!("record_diff: diffstat failed: {}", e),
+        }
+    }
 
-        Ok(prompt)
+    /// Sleep for `duration`, waking early if a stop is requested.
+    fn sleep_unless_stopped(&self, duration: Duration) {
+        let deadline = std::time::Instant::now() + duration;
+        while !interrupt::stop_requested() {
+            let now = std::time::Instant::now();
+            if now >= deadline {
+                break;
+            }
+            std::thread::sleep((deadline - now).min(Duration::from_millis(250)));
+        }
     }
 
-    /// Run Claude CLI with the given prompt, streaming output and enforcing timeout
-    fn run_claude(&mut self, prompt: &str, config: &Config) -> Result<String> {
-        // Check if claude binary exists
-        which::which("claude")
-            .context("claude CLI not found. Please install it from https://github.com/anthropics/claude-code")?;
+    /// Record an iteration whose agent was killed by the stall watchdog.
+    ///
+    /// Reverts any protected-path edits the agent made before it hung, then
+    /// records the iteration as `stalled` (validation is not run) so the next
+    /// iteration's prompt knows the previous attempt hung, and where: the
+    /// tail of what it printed is kept with the feedback.
+    fn record_stall(
+        &mut self,
+        mut record: IterationRecord,
+        stalled: &agent::Stalled,
+        progress: &ProgressTracker,
+        config: &Config,
+    ) -> Result<()> {
+        let iteration = record.iteration;
+        let reason = stalled.to_string();
+        log::warn!("record_stall: iteration={} reason={}", iteration, reason);
+        self.session.println(&format!(
+            "{} Iteration {} {} - agent killed, continuing loop...",
+            "⚠".yellow(),
+            iteration,
+            "stalled".bold()
+        ))?;
+        self.session.log(&format!("STALLED: {}", reason))?;
+
+        record.reverted_paths = self.guard_protected_paths(iteration, config)?;
+        record.phases = self.phase_status();
+        record.stalled = true;
+        record.summary = format!("Stalled: {}", reason);
+        record.errors = format!(
+            "The previous attempt stopped producing output for {} minute(s) and was killed.\n\
+             Avoid long-running or interactive commands that print nothing; keep steps small.\n",
+            config.loop_config.stall_timeout_minutes
+        );
+        let printed = format!("{}{}", stalled.stdout, stalled.stderr);
+        if !printed.trim().is_empty() {
+            record.errors.push_str("Its last output:\n");
+            record.errors.push_str(&progress::errors_excerpt(&printed));
+        }
+        record.finished = Utc::now();
+        progress.record(&record)?;
 
-        let timeout = Duration::from_secs((config.loop_config.iteration_timeout_minutes * 60) as u64);
+        self.session.log(&format!(
+            "--- iteration {} stalled at {} ---",
+            iteration,
+            Utc::now().format("%Y-%m-%d %H:%M:%S UTC")
+        ))?;
+        Ok(())
+    }
 
-        let mut cmd = Command::new("claude");
-        cmd.arg("--print").arg("--model").arg(&config.llm.model);
+    /// The plan's phases as the agent left them, kept for `result.json`.
+    /// Under phase checks, a phase is complete once it has passed them.
+    fn phase_status(&mut self) -> Vec<PhaseStatus> {
+        match Plan::load(&self.plan_path) {
+            Ok(plan) => self.phases = plan.status(),
+            Err(e) => log::warn!("phase_status: {:#}", e),
+        }
+        if let Some(passed) = self.phase_gate {
+            for (index, phase) in self.phases.iter_mut().enumerate() {
+                phase.complete = index < passed;
+            }
+        }
+        self.phases.clone()
+    }
 
-        if config.llm.dangerously_skip_permissions {
-            cmd.arg("--dangerously-skip-permissions");
+    /// The plan's unchecked items as the agent left them, as `text (line N)`.
+    fn unchecked_items(&self) -> Vec<String> {
+        match Plan::load(&self.plan_path) {
+            Ok(plan) => plan
+                .unchecked()
+                .iter()
+                .map(|item| format!("{} (line {})", item.text, item.line))
+                .collect(),
+            Err(e) => {
+                log::warn!("unchecked_items: {:#}", e);
+                Vec::new()
+            }
         }
+    }
 
-        cmd.arg(prompt)
-            .current_dir(&self.work_dir)
-            .stdin(Stdio::null())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-
-        let mut child = cmd.spawn().context("Failed to spawn claude command")?;
-
-        // Stream stdout in a background thread while capturing it
-        let child_stdout = child.stdout.take();
-        let stdout_handle = std::thread::spawn(move || {
-            let mut captured = String::new();
-            if let Some(stdout) = child_stdout {
-                let reader = BufReader::new(stdout);
-                for line in reader.lines() {
-                    match line {
-                        Ok(line) => {
-                            println!("  {}", line.dimmed());
-                            captured.push_str(&line);
-                            captured.push('\n');
-                        }
-                        Err(_) => break,
-                    }
-                }
+    /// The validation command for this iteration: the current phase's
+    /// `Validation:` under phase checks, else `validation.command`. Later
+    /// phases' failures must not count against the one being worked on.
+    fn validation_command(&self, config: &Config) -> String {
+        self.phase_gate
+            .and_then(|index| {
+                let plan = Plan::load(&self.plan_path).ok()?;
+                plan.phases.get(index)?.validation.clone()
+            })
+            .unwrap_or_else(|| config.validation.command.clone())
+    }
+
+    /// Every phase of a plan with phase checks has passed them (always true
+    /// for other plans). A plan that can no longer be read has not.
+    fn phases_done(&self) -> bool {
+        self.phase_gate
+            .is_none_or(|passed| Plan::load(&self.plan_path).is_ok_and(|plan| passed >= plan.phases.len()))
+    }
+
+    /// Verify the current phase of a plan with phase checks, moving on to
+    /// the next phase when it passes.
+    ///
+    /// The phase must have its checklist ticked off and its signal in the
+    /// agent's output (the completion signal stands in for a phase with
+    /// neither), then pass the iteration's validation (its own command, see
+    /// [`LoopRunner::validation_command`]) and its gates. Gate outputs go to
+    /// `phase-gate-*.log` in the iteration directory.
+    fn check_phase(
+        &mut self,
+        output: &str,
+        validation: &ValidationResult,
+        config: &Config,
+        artifacts: &mut IterationArtifacts,
+    ) -> Result<Option<PhaseCheck>> {
+        let Some(index) = self.phase_gate else {
+            return Ok(None);
+        };
+        let plan = match Plan::load(&self.plan_path) {
+            Ok(plan) => plan,
+            Err(e) => {
+                log::warn!("check_phase: {:#}", e);
+                return Ok(None);
             }
-            captured
-        });
-
-        // Stream stderr in a background thread while capturing it
-        let child_stderr = child.stderr.take();
-        let stderr_handle = std::thread::spawn(move || {
-            let mut captured = String::new();
-            if let Some(stderr) = child_stderr {
-                let reader = BufReader::new(stderr);
-                for line in reader.lines() {
-                    match line {
-                        Ok(line) => {
-                            eprintln!("  {}", line.dimmed());
-                            captured.push_str(&line);
-                            captured.push('\n');
-                        }
-                        Err(_) => break,
-                    }
+        };
+        let Some(phase) = plan.phases.get(index) else {
+            return Ok(None);
+        };
+        log::debug!("check_phase: index={} title={} line={}", index, phase.title, phase.line);
+
+        let mut failed = Vec::new();
+        if !phase.items.is_empty() && !phase.complete() {
+            failed.push("checklist".to_string());
+        }
+        let signal = match &phase.signal {
+            Some(signal) => Some(signal.as_str()),
+            None if phase.items.is_empty() => Some(config.loop_config.completion_signal.as_str()),
+            None => None,
+        };
+        if let Some(signal) = signal
+            && !signal_on_own_line(output, signal)
+        {
+            failed.push("signal".to_string());
+        }
+
+        // Run the checks only once the phase claims to be done.
+        if failed.is_empty() {
+            self.session
+                .println(&format!("{} Checking {}...", "→".cyan(), phase.title.bold()))?;
+            // The iteration's validation already ran the phase's own command.
+            let runner = ValidationRunner::new(&self.work_dir);
+            if !validation.passed {
+                failed.push("validation".to_string());
+            }
+            let gates = runner.run_quality_gates(&phase.gates)?;
+            runner.print_quality_gate_results(&gates);
+            for (name, passed, output) in &gates.results {
+                artifacts.write(&format!("phase-{}", IterationArtifacts::gate_file(name)), output)?;
+                if !passed {
+                    failed.push(name.clone());
                 }
             }
-            captured
-        });
-
-        // Wait with timeout
-        let start = std::time::Instant::now();
-        loop {
-            match child.try_wait().context("Failed to check claude process status")? {
-                Some(_status) => {
-                    let stdout = stdout_handle.join().unwrap_or_default();
-                    let stderr = stderr_handle.join().unwrap_or_default();
-
-                    // Log Claude output to session
-                    self.session.log("--- claude output ---")?;
-                    self.session.log(&stdout)?;
-                    if !stderr.trim().is_empty() {
-                        self.session.log("--- claude stderr ---")?;
-                        self.session.log(&stderr)?;
-                    }
+        }
 
-                    return Ok(format!("{}\n{}", stdout, stderr));
-                }
-                None => {
-                    if start.elapsed() >= timeout {
-                        let _ = child.kill();
-                        let _ = child.wait();
-                        return Err(eyre::eyre!(
-                            "Claude timed out after {} minutes",
-                            config.loop_config.iteration_timeout_minutes
-                        ));
-                    }
-                    std::thread::sleep(Duration::from_millis(500));
-                }
+        let passed = failed.is_empty();
+        if passed {
+            self.phase_gate = Some(index + 1);
+            self.session
+                .println(&format!("{} {} passed its checks", "✓".green(), phase.title))?;
+        } else {
+            self.session.println(&format!(
+                "{} {} not done: {}",
+                "⚠".yellow(),
+                phase.title,
+                failed.join(", ").dimmed()
+            ))?;
+        }
+        self.session.log(&format!(
+            "Phase check: {} {}",
+            phase.title,
+            if passed {
+                "PASS".to_string()
+            } else {
+                format!("FAIL ({})", failed.join(", "))
             }
+        ))?;
+        Ok(Some(PhaseCheck {
+            phase: phase.title.clone(),
+            passed,
+            failed,
+        }))
+    }
+
+    /// Feed one iteration's outcome to the model ladder, announcing a climb.
+    fn climb_ladder(&mut self, ladder: &mut ModelLadder, passed: bool, iteration: u32) -> Result<()> {
+        if let Some(model) = ladder.record(passed) {
+            log::warn!("climb_ladder: iteration={} escalating to model={}", iteration, model);
+            self.session.println(&format!(
+                "{} Stuck after iteration {}, escalating to {}",
+                "⇧".yellow(),
+                iteration,
+                model.bold()
+            ))?;
+            self.session
+                .log(&format!("ESCALATE: after iteration {} -> model {}", iteration, model))?;
         }
+        Ok(())
     }
 
     /// Check for completion promise in output.
@@ -549,13 +1359,13 @@ impl LoopRunner {
         signal_on_own_line(output, &config.loop_config.completion_signal)
     }
 
-    /// Revert agent edits to protected paths and record them as feedback.
+    /// Revert agent edits to protected paths, returning what was reverted.
     ///
-    /// Delegates to [`crate::safety::guard_protected`], then appends a feedback
-    /// entry to `progress.txt` (via the progress tracker) so the next
-    /// iteration's prompt explains the boundary that was enforced. A no-op when
-    /// nothing protected was touched.
-    fn guard_protected_paths(&mut self, iteration: u32, config: &Config) -> Result<()> {
+    /// Delegates to [`crate::safety::guard_protected`] and restores the progress
+    /// files (always protected, whatever `safety.protected-paths` says). The
+    /// caller records the reverted paths in the iteration's progress record so
+    /// the next iteration's prompt explains the boundary that was enforced.
+    fn guard_protected_paths(&mut self, iteration: u32, config: &Config) -> Result<Vec<String>> {
         log::debug!(
             "guard_protected_paths: iteration={} work_dir={} protected_count={}",
             iteration,
@@ -563,11 +1373,12 @@ impl LoopRunner {
             config.safety.protected_paths.len()
         );
 
-        let reverted = crate::safety::guard_protected(&self.work_dir, &config.safety.protected_paths)?;
+        let mut reverted = crate::safety::guard_protected(&self.work_dir, &config.safety.protected_paths)?;
+        reverted.extend(self.restore_progress()?);
 
         if reverted.is_empty() {
             log::debug!("guard_protected_paths: iteration={} nothing reverted", iteration);
-            return Ok(());
+            return Ok(reverted);
         }
 
         self.session.println(&format!(
@@ -579,31 +1390,44 @@ impl LoopRunner {
         self.session
             .log(&format!("Protected-path guard reverted: {}", reverted.join(", ")))?;
 
-        // Record as a feedback entry so the NEXT iteration's prompt explains the boundary.
-        let progress = ProgressTracker::new(&self.progress_path);
-        let feedback = IterationResult {
-            iteration,
-            validation_passed: false,
-            promise_found: false,
-            summary: "Protected-path guard reverted off-limits edits".to_string(),
-            validation_output: format!(
-                "You modified protected (off-limits) paths; these edits were reverted and NOT committed.\n\
-                 Do not modify these paths again:\n{}",
-                reverted
-                    .iter()
-                    .map(|p| format!("  - {}", p))
-                    .collect::<Vec<_>>()
-                    .join("\n")
-            ),
-        };
-        progress.log_iteration(&feedback)?;
-
         log::warn!(
             "guard_protected_paths: iteration={} reverted {} path(s)",
             iteration,
             reverted.len()
         );
-        Ok(())
+        Ok(reverted)
+    }
+
+    /// Snapshot the progress files before the agent runs.
+    fn snapshot_progress(&mut self) {
+        self.progress_snapshot = PROGRESS_FILES
+            .iter()
+            .map(|path| {
+                (
+                    *path,
+                    std::fs::read_to_string(self.work_dir.join(path)).unwrap_or_default(),
+                )
+            })
+            .collect();
+    }
+
+    /// Undo agent edits to the progress files, returning the ones restored.
+    /// `rwl` is their only writer, so any difference from the snapshot taken
+    /// before the agent ran is the agent's. A file is replaced rather than
+    /// written through, in case the agent swapped it for a symlink.
+    fn restore_progress(&self) -> Result<Vec<String>> {
+        let mut restored = Vec::new();
+        for (path, snapshot) in &self.progress_snapshot {
+            let full = self.work_dir.join(path);
+            if std::fs::read_to_string(&full).ok().as_deref() == Some(snapshot.as_str()) {
+                continue;
+            }
+            log::warn!("restore_progress: path={} was modified by the agent", full.display());
+            let _ = std::fs::remove_file(&full);
+            std::fs::write(&full, snapshot).context("Failed to restore progress file")?;
+            restored.push(path.to_string());
+        }
+        Ok(restored)
     }
 
     /// Auto-commit changes
@@ -651,19 +1475,68 @@ impl LoopRunner {
             validation_passed,
             quality_gates_passed: gates_passed,
             branch: self.branch.clone(),
+            models: self.models.clone(),
+            summaries: self.summaries.clone(),
+            phases: self.phases.clone(),
+            retries: self.retries,
+            rate_limit_pauses: self.rate_limit_pauses.clone(),
+            usage: if self.usage.iterations.is_empty() { None } else { Some(self.usage.clone()) },
             session_dir: self.session_dir.clone(),
         }
     }
 
+    /// Stop the run on an interrupt before `iteration` completes: auto-commit
+    /// any WIP so it is not stranded, and log why.
+    fn stop(&mut self, iteration: u32, config: &Config) -> Result<LoopOutcome> {
+        let reason = interrupt::reason();
+        log::debug!("stop: iteration={} reason={}", iteration, reason);
+        self.session.println(&format!(
+            "{} {} - stopped after {} iterations",
+            "⚠".yellow(),
+            reason,
+            iteration - 1
+        ))?;
+        if config.git.auto_commit {
+            let _ = self.git_auto_commit(iteration, config);
+        }
+        self.session.log(&format!("=== Stopped: {} ===", reason))?;
+        Ok(LoopOutcome::Stopped {
+            iterations: iteration - 1,
+            reason,
+        })
+    }
+
+    /// A second Ctrl-C (or SIGTERM/SIGHUP) mid-iteration: record the
+    /// iteration as stopped and stop, skipping whatever of it is left.
+    fn abort_iteration(
+        &mut self,
+        iteration: u32,
+        mut record: IterationRecord,
+        progress: &ProgressTracker,
+        config: &Config,
+    ) -> Result<LoopOutcome> {
+        record.summary = format!("Stopped: {}", interrupt::reason());
+        record.finished = Utc::now();
+        progress.record(&record)?;
+        self.stop(iteration, config)
+    }
+
     /// Print iteration status
-    fn print_iteration_status(&mut self, result: &IterationResult) -> Result<()> {
-        let validation_status = if result.validation_passed { "✓".green() } else { "✗".red() };
+    fn print_iteration_status(&mut self, result: &IterationRecord) -> Result<()> {
+        let validation_status = if result.validation_passed == Some(true) { "✓".green() } else { "✗".red() };
         let promise_status = if result.promise_found { "✓".green() } else { "-".dimmed() };
+        let phases = if result.phases.is_empty() {
+            String::new()
+        } else {
+            let complete = result.phases.iter().filter(|p| p.complete).count();
+            format!("Phases: {}/{}  ", complete, result.phases.len())
+        };
 
         self.session.println(&format!(
-            "  Validation: {}  Promise: {}  {}",
+            "  Validation: {}  Promise: {}  {}{}",
             validation_status,
             promise_status,
+            phases,
             result.summary.dimmed()
         ))
     }
@@ -819,4 +1692,36 @@ mod tests {
             Some("timeout")
         );
     }
+
+    /// The prompt is not HTML: plan text and progress reach it unescaped.
+    #[test]
+    fn test_render_prompt_keeps_plan_text_verbatim() {
+        let dir = tempfile::tempdir().unwrap();
+        let plan_path = dir.path().join("plan.md");
+        std::fs::write(
+            &plan_path,
+            "# Plan\n\n## Phase 1: Parser\n\n- [ ] Parse `a < b && c > \"d\"` and 'e'\n",
+        )
+        .unwrap();
+        let inputs = PromptInputs {
+            template: &PromptTemplate::built_in(),
+            work_dir: dir.path(),
+            plan_path: &plan_path,
+            phase: None,
+            iteration: 2,
+            progress: "Errors:\n  expected `Vec<&str>`, found \"String\"\n",
+            remaining: None,
+            carryover: &Carryover::default(),
+        };
+
+        let prompt = render_prompt(&inputs, &Config::default()).unwrap();
+        assert!(
+            prompt.contains("- [ ] Parse `a < b && c > \"d\"` and 'e'\n"),
+            "{}",
+            prompt
+        );
+        assert!(prompt.contains("expected `Vec<&str>`, found \"String\""), "{}", prompt);
+        assert!(prompt.contains("`<promise>COMPLETE</promise>`"), "{}", prompt);
+        assert!(!prompt.contains("&lt;") && !prompt.contains("&amp;") && !prompt.contains("&quot;"));
+    }
 }
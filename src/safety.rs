@@ -1,5 +1,7 @@
 use crate::config::{Isolation, SafetyConfig};
 use crate::git::GitManager;
+use crate::progress::PROGRESS_FILES;
+use crate::session::LAST_SESSION_PATH;
 use eyre::{Context, Result};
 use log::{debug, trace, warn};
 use std::path::{Path, PathBuf};
@@ -218,7 +220,7 @@ fn create_worktree(cwd: &Path, worktree_path: &Path, branch: &str) -> Result<()>
 /// everything beneath it; a non-slash entry matches that exact path or anything
 /// beneath it as a directory. Comparison is on the repo-relative path git emits
 /// (always forward-slash separated), normalized to strip any leading `./`.
-fn is_protected(rel_path: &str, protected: &[String]) -> bool {
+pub(crate) fn is_protected(rel_path: &str, protected: &[String]) -> bool {
     let candidate = rel_path.trim_start_matches("./");
     protected.iter().any(|entry| {
         let entry = entry.trim_start_matches("./");
@@ -278,7 +280,8 @@ fn parse_status_line(line: &str) -> Option<StatusEntry> {
 
 /// Revert agent edits to protected paths after an iteration's Claude run.
 ///
-/// Runs `git status --porcelain --ignored` in `work_dir`, filters changed paths
+/// Runs `git status --porcelain --ignored --untracked-files=all` in `work_dir`
+/// (every untracked file listed on its own, never a collapsed directory), filters changed paths
 /// against the protected-path prefixes, and reverts each match: tracked
 /// modifications via `git checkout -- <path>`, new untracked files via
 /// `git clean -f -d`, and git-ignored files via `git clean -f -d -x` (the `-x`
@@ -291,6 +294,11 @@ fn parse_status_line(line: &str) -> Option<StatusEntry> {
 /// the destination removed, so the protected file is back and the move does not
 /// survive the subsequent auto-commit.
 ///
+/// The progress files ([`PROGRESS_FILES`]) are written by `rwl` between agent
+/// runs, so they always differ from `HEAD`; the guard leaves them alone and the
+/// runner restores them from its own snapshot instead. [`LAST_SESSION_PATH`]
+/// is left alone too: an in-place run writes it before the first iteration.
+///
 /// Safety invariants (mandatory, per the design's Security section):
 /// * each candidate is canonicalized and asserted to resolve UNDER the worktree
 ///   root before it is touched — a path escaping via `..` or a symlink is
@@ -323,7 +331,7 @@ pub fn guard_protected(work_dir: &Path, protected: &[String]) -> Result<Vec<Stri
     // `--ignored` surfaces git-ignored files (status `!!`); without it an agent
     // writing into an ignored subtree under a protected path is invisible here.
     let output = Command::new("git")
-        .args(["status", "--porcelain", "--ignored"])
+        .args(["status", "--porcelain", "--ignored", "--untracked-files=all"])
         .current_dir(work_dir)
         .output()
         .context("Failed to run git status for protected-path guard")?;
@@ -348,6 +356,12 @@ pub fn guard_protected(work_dir: &Path, protected: &[String]) -> Result<Vec<Stri
             continue;
         };
 
+        let rwl_written = PROGRESS_FILES.contains(&entry.dest.as_str()) || entry.dest == LAST_SESSION_PATH;
+        if rwl_written && entry.orig.is_none() {
+            trace!("guard_protected: {} is written by rwl, leaving", entry.dest);
+            continue;
+        }
+
         let dest_protected = is_protected(&entry.dest, protected);
         let orig_protected = entry.orig.as_deref().is_some_and(|o| is_protected(o, protected));
 
@@ -932,6 +946,25 @@ mod tests {
         assert!(!repo.path().join(".rwl/sneaky.txt").exists());
     }
 
+    #[test]
+    fn test_guard_leaves_rwl_files_but_cleans_their_neighbours() {
+        let repo = init_repo_with_protected();
+        // rwl's own files sit next to an agent-created file in a new,
+        // wholly untracked protected directory.
+        for path in PROGRESS_FILES {
+            std::fs::write(repo.path().join(path), "## Iteration 1\n").unwrap();
+        }
+        std::fs::write(repo.path().join(LAST_SESSION_PATH), "/tmp/rwl/session\n").unwrap();
+        std::fs::create_dir_all(repo.path().join("docs/design/new")).unwrap();
+        std::fs::write(repo.path().join("docs/design/new/notes.md"), "evil").unwrap();
+
+        let reverted = guard_protected(repo.path(), &protected_paths()).unwrap();
+
+        assert_eq!(reverted, vec!["docs/design/new/notes.md"]);
+        assert!(PROGRESS_FILES.iter().all(|path| repo.path().join(path).exists()));
+        assert!(repo.path().join(LAST_SESSION_PATH).exists());
+    }
+
     #[test]
     fn test_guard_leaves_unprotected_changes() {
         let repo = init_repo_with_protected();
@@ -3,6 +3,11 @@ use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::path::Path;
 
+/// The project file naming the session directory of its most recent run, so
+/// `rwl status` finds a session under `--session-path` too. Written by `rwl`,
+/// so the protected-path guard leaves it alone.
+pub const LAST_SESSION_PATH: &str = ".rwl/last-session";
+
 pub struct SessionLog {
     file: BufWriter<File>,
 }
@@ -0,0 +1,153 @@
+use crate::agent::{self, AgentBackend, AgentRequest};
+use crate::config::{LlmConfig, OutputFormat};
+use crate::interrupt;
+use crate::progress::{Digest, Fold};
+use eyre::{Context, Result};
+use std::path::Path;
+use std::time::Duration;
+
+/// Session file holding the current digest, so it survives between prompts
+/// and `rwl prompt --session` can show it.
+pub const DIGEST_FILE: &str = "progress-digest.json";
+
+/// Session file the summary prompt is written to; the payload of the `file`
+/// transport and a record of what the summary model was sent.
+const DIGEST_PROMPT_FILE: &str = "progress-digest-prompt.md";
+
+/// Summary call timeout: 10 minutes, as for the judge.
+const SUMMARY_TIMEOUT_SECS: u64 = 600;
+
+/// The session's digest, if one has been written.
+pub fn load_digest(session_dir: &Path) -> Option<Digest> {
+    let json = std::fs::read_to_string(session_dir.join(DIGEST_FILE)).ok()?;
+    match serde_json::from_str(&json) {
+        Ok(digest) => Some(digest),
+        Err(e) => {
+            log::warn!("load_digest: session_dir={} error={}", session_dir.display(), e);
+            None
+        }
+    }
+}
+
+pub fn save_digest(session_dir: &Path, digest: &Digest) -> Result<()> {
+    let json = serde_json::to_string_pretty(digest)?;
+    std::fs::write(session_dir.join(DIGEST_FILE), json).context("Failed to write progress digest")
+}
+
+/// The instructions the summary model gets: the previous digest (if any) and
+/// the entries to fold into it.
+fn summary_prompt(previous: Option<&Digest>, fold: &Fold) -> String {
+    let mut prompt = String::from(
+        "You are compacting the progress log of an iterative coding loop. Each loop \
+         iteration is a fresh agent with no memory; this log is how it learns what \
+         happened before.\n\n\
+         Write a concise digest (at most 30 lines) of the material below: what was \
+         done, what keeps failing and why, and what the next iteration must not \
+         repeat. Keep file names, commands and error messages that matter. Output \
+         only the digest - no preamble, and do not modify any files.\n\n",
+    );
+    if let Some(previous) = previous {
+        prompt.push_str(&format!(
+            "## Current digest (iterations 1-{})\n\n{}\n\n",
+            previous.through,
+            previous.text.trim_end()
+        ));
+    }
+    prompt.push_str(&format!("## Entries to fold in (up to iteration {})\n\n", fold.through));
+    prompt.push_str(&fold.entries);
+    prompt
+}
+
+/// Fold `fold` into `previous` with one invocation of the configured backend
+/// on `model` (same permissions and prompt transport as the loop's agent),
+/// returning the digest that covers iterations up to `fold.through`.
+pub fn run_summary(
+    backend: &dyn AgentBackend,
+    llm: &LlmConfig,
+    model: &str,
+    previous: Option<&Digest>,
+    fold: &Fold,
+    work_dir: &Path,
+    session_dir: &Path,
+) -> Result<Digest> {
+    let prompt = summary_prompt(previous, fold);
+    log::debug!(
+        "run_summary: backend={} model={} through={} prompt_len={}",
+        backend.name(),
+        model,
+        fold.through,
+        prompt.len()
+    );
+
+    let prompt_file = session_dir.join(DIGEST_PROMPT_FILE);
+    std::fs::write(&prompt_file, &prompt).context("Failed to write summary prompt")?;
+    let request = AgentRequest {
+        model,
+        prompt: &prompt,
+        work_dir,
+        prompt_file: &prompt_file,
+        skip_permissions: llm.dangerously_skip_permissions,
+        output_format: OutputFormat::Text,
+        transport: llm.prompt_transport,
+    };
+    let output = agent::run_agent(
+        backend,
+        &request,
+        &agent::Limits {
+            cancel: Some(interrupt::abort_flag()),
+            ..agent::Limits::new(Duration::from_secs(SUMMARY_TIMEOUT_SECS))
+        },
+        false,
+    )
+    .context("Progress summary invocation failed")?;
+
+    let text = output.stdout.trim();
+    if output.exit_code != Some(0) || text.is_empty() {
+        return Err(eyre::eyre!(
+            "Progress summary failed (exit code {:?}): {}",
+            output.exit_code,
+            output.stderr.trim()
+        ));
+    }
+    Ok(Digest {
+        through: fold.through,
+        text: text.to_string(),
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_summary_prompt_carries_previous_digest_and_entries() {
+        let fold = Fold {
+            through: 8,
+            entries: "## Iteration 7\nValidation: FAILED\n".to_string(),
+        };
+        let first = summary_prompt(None, &fold);
+        assert!(!first.contains("Current digest"));
+        assert!(first.ends_with("## Entries to fold in (up to iteration 8)\n\n## Iteration 7\nValidation: FAILED\n"));
+
+        let previous = Digest {
+            through: 6,
+            text: "Parser done; lexer tests flaky.".to_string(),
+        };
+        let next = summary_prompt(Some(&previous), &fold);
+        assert!(next.contains("## Current digest (iterations 1-6)\n\nParser done; lexer tests flaky.\n"));
+    }
+
+    #[test]
+    fn test_digest_round_trips_through_session_dir() {
+        let session = tempdir().unwrap();
+        assert!(load_digest(session.path()).is_none());
+        let digest = Digest {
+            through: 4,
+            text: "Did things.".to_string(),
+        };
+        save_digest(session.path(), &digest).unwrap();
+        assert_eq!(load_digest(session.path()), Some(digest));
+    }
+}
@@ -0,0 +1,290 @@
+use crate::git::GitManager;
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, HelperResult, Output, PathAndJson, RenderContext, RenderError,
+    RenderErrorReason, Renderable,
+};
+use std::path::{Component, Path, PathBuf};
+
+/// Register the helpers available to prompt templates.
+///
+/// * `{{truncate text 2000}}` - at most N characters of `text`.
+/// * `{{tail-lines text 40}}` - the last N lines of `text`.
+/// * `{{file "docs/CONVENTIONS.md"}}` - a file from the repo (the work dir).
+/// * `{{git-log 10}}` - the last N commits, one per line.
+/// * `{{#if-iteration-gt 3}}...{{else}}...{{/if-iteration-gt}}` - render the
+///   block only after iteration N.
+///
+/// Missing values render as empty text, so a helper over an optional variable
+/// (e.g. `progress` on the first iteration) never fails the render.
+pub fn register(handlebars: &mut Handlebars<'_>, work_dir: &Path) {
+    handlebars.register_helper(
+        "truncate",
+        Box::new(
+            |h: &Helper, _: &Handlebars, _: &Context, _: &mut RenderContext, out: &mut dyn Output| -> HelperResult {
+                let text = text_param(h, 0);
+                let limit = count_param(h, 1, "truncate")?;
+                out.write(&truncate(&text, limit))?;
+                Ok(())
+            },
+        ),
+    );
+
+    handlebars.register_helper(
+        "tail-lines",
+        Box::new(
+            |h: &Helper, _: &Handlebars, _: &Context, _: &mut RenderContext, out: &mut dyn Output| -> HelperResult {
+                let text = text_param(h, 0);
+                let count = count_param(h, 1, "tail-lines")?;
+                out.write(&tail_lines(&text, count))?;
+                Ok(())
+            },
+        ),
+    );
+
+    let root = work_dir.to_path_buf();
+    handlebars.register_helper(
+        "file",
+        Box::new(
+            move |h: &Helper,
+                  _: &Handlebars,
+                  _: &Context,
+                  _: &mut RenderContext,
+                  out: &mut dyn Output|
+                  -> HelperResult {
+                let relative = text_param(h, 0);
+                let path = repo_path(&root, &relative).ok_or_else(|| {
+                    RenderErrorReason::Other(format!("file: {:?} is not a path inside the repository", relative))
+                })?;
+                match std::fs::read_to_string(&path) {
+                    Ok(contents) => out.write(&contents)?,
+                    Err(e) => log::warn!("file helper: path={} error={}", path.display(), e),
+                }
+                Ok(())
+            },
+        ),
+    );
+
+    let root = work_dir.to_path_buf();
+    handlebars.register_helper(
+        "git-log",
+        Box::new(
+            move |h: &Helper,
+                  _: &Handlebars,
+                  _: &Context,
+                  _: &mut RenderContext,
+                  out: &mut dyn Output|
+                  -> HelperResult {
+                let count = count_param(h, 0, "git-log")?;
+                match GitManager::new(&root).recent_commits(count) {
+                    Ok(commits) => out.write(&commits.join("\n"))?,
+                    Err(e) => log::warn!("git-log helper: work_dir={} error={}", root.display(), e),
+                }
+                Ok(())
+            },
+        ),
+    );
+
+    handlebars.register_helper("if-iteration-gt", Box::new(IfIterationGt));
+}
+
+/// `{{#if-iteration-gt N}}`: the block when the `iteration` variable is
+/// greater than N, else the `{{else}}` block.
+struct IfIterationGt;
+
+impl HelperDef for IfIterationGt {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        r: &'reg Handlebars<'reg>,
+        ctx: &'rc Context,
+        rc: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let threshold = count_param(h, 0, "if-iteration-gt")? as u64;
+        let iteration = ctx.data().get("iteration").and_then(as_count).unwrap_or(0) as u64;
+        let block = if iteration > threshold { h.template() } else { h.inverse() };
+        match block {
+            Some(t) => t.render(r, ctx, rc, out),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Parameter `index` as text: strings verbatim, other values as JSON, missing
+/// or null as empty.
+fn text_param(h: &Helper, index: usize) -> String {
+    match h.param(index).map(PathAndJson::value) {
+        None | Some(serde_json::Value::Null) => String::new(),
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+/// Parameter `index` as a non-negative count (a number or numeric string).
+fn count_param(h: &Helper, index: usize, helper: &'static str) -> Result<usize, RenderError> {
+    let value = h
+        .param(index)
+        .map(PathAndJson::value)
+        .ok_or(RenderErrorReason::ParamNotFoundForIndex(helper, index))?;
+    as_count(value).ok_or_else(|| {
+        RenderErrorReason::ParamTypeMismatchForName(helper, index.to_string(), "non-negative integer".to_string())
+            .into()
+    })
+}
+
+/// A number, or a string holding one (template data is often all strings).
+fn as_count(value: &serde_json::Value) -> Option<usize> {
+    value
+        .as_u64()
+        .or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+        .map(|n| n as usize)
+}
+
+/// At most `limit` characters of `text`, marking a cut with `...`.
+fn truncate(text: &str, limit: usize) -> String {
+    match text.char_indices().nth(limit) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// The last `count` lines of `text`.
+fn tail_lines(text: &str, count: usize) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    lines[lines.len().saturating_sub(count)..].join("\n")
+}
+
+/// Resolve `relative` under `root`, refusing absolute paths, `..` and
+/// symlinks that lead out of `root`. A path that does not exist resolves
+/// as is (reading it fails).
+fn repo_path(root: &Path, relative: &str) -> Option<PathBuf> {
+    let path = Path::new(relative);
+    let inside = !relative.is_empty()
+        && path
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if !inside {
+        return None;
+    }
+    let joined = root.join(path);
+    match (joined.canonicalize(), root.canonicalize()) {
+        (Ok(canonical), Ok(root)) => canonical.starts_with(&root).then_some(canonical),
+        (Ok(_), Err(_)) => None,
+        (Err(_), _) => Some(joined),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::process::Command;
+    use tempfile::tempdir;
+
+    fn render(work_dir: &Path, template: &str, data: serde_json::Value) -> Result<String, RenderError> {
+        let mut handlebars = crate::templates::engine();
+        register(&mut handlebars, work_dir);
+        handlebars.render_template(template, &data)
+    }
+
+    #[test]
+    fn test_truncate_and_tail_lines() {
+        let dir = tempdir().unwrap();
+        let data = json!({"text": "a\nb\nc\nd", "long": "héllo world"});
+        assert_eq!(
+            render(dir.path(), "{{truncate long 5}}", data.clone()).unwrap(),
+            "héllo..."
+        );
+        assert_eq!(
+            render(dir.path(), "{{truncate long 50}}", data.clone()).unwrap(),
+            "héllo world"
+        );
+        assert_eq!(
+            render(dir.path(), "{{tail-lines text 2}}", data.clone()).unwrap(),
+            "c\nd"
+        );
+        assert_eq!(render(dir.path(), "{{tail-lines text 9}}", data).unwrap(), "a\nb\nc\nd");
+    }
+
+    #[test]
+    fn test_missing_values_render_empty() {
+        let dir = tempdir().unwrap();
+        assert_eq!(
+            render(dir.path(), "[{{tail-lines progress 5}}]", json!({})).unwrap(),
+            "[]"
+        );
+        assert!(render(dir.path(), "{{truncate progress}}", json!({})).is_err());
+    }
+
+    #[test]
+    fn test_file_includes_repo_file_only() {
+        let dir = tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("docs")).unwrap();
+        std::fs::write(dir.path().join("docs/style.md"), "Use tabs.").unwrap();
+        assert_eq!(
+            render(dir.path(), r#"{{file "docs/style.md"}}"#, json!({})).unwrap(),
+            "Use tabs."
+        );
+        assert_eq!(
+            render(dir.path(), r#"{{file "docs/missing.md"}}"#, json!({})).unwrap(),
+            ""
+        );
+        assert!(render(dir.path(), r#"{{file "../secret"}}"#, json!({})).is_err());
+        assert!(render(dir.path(), r#"{{file "/etc/passwd"}}"#, json!({})).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_refuses_symlink_out_of_repo() {
+        let dir = tempdir().unwrap();
+        let outside = tempdir().unwrap();
+        std::fs::write(outside.path().join("secret"), "hunter2").unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("linked")).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("secret"), dir.path().join("secret.md")).unwrap();
+        assert!(render(dir.path(), r#"{{file "linked/secret"}}"#, json!({})).is_err());
+        assert!(render(dir.path(), r#"{{file "secret.md"}}"#, json!({})).is_err());
+
+        // A link that stays inside the repository is followed.
+        std::fs::write(dir.path().join("style.md"), "Use tabs.").unwrap();
+        std::os::unix::fs::symlink(dir.path().join("style.md"), dir.path().join("alias.md")).unwrap();
+        assert_eq!(
+            render(dir.path(), r#"{{file "alias.md"}}"#, json!({})).unwrap(),
+            "Use tabs."
+        );
+    }
+
+    #[test]
+    fn test_git_log_lists_recent_commits() {
+        let dir = tempdir().unwrap();
+        for args in [
+            vec!["init", "-q"],
+            vec!["config", "user.email", "test@test.com"],
+            vec!["config", "user.name", "Test"],
+            vec!["commit", "-q", "--allow-empty", "-m", "first"],
+            vec!["commit", "-q", "--allow-empty", "-m", "second"],
+        ] {
+            Command::new("git")
+                .args(&args)
+                .current_dir(dir.path())
+                .output()
+                .unwrap();
+        }
+        let log = render(dir.path(), "{{git-log 1}}", json!({})).unwrap();
+        assert!(log.ends_with(" second"), "{}", log);
+        assert_eq!(log.lines().count(), 1);
+    }
+
+    #[test]
+    fn test_if_iteration_gt() {
+        let dir = tempdir().unwrap();
+        let template = "{{#if-iteration-gt 2}}late{{else}}early{{/if-iteration-gt}}";
+        assert_eq!(render(dir.path(), template, json!({"iteration": 2})).unwrap(), "early");
+        assert_eq!(render(dir.path(), template, json!({"iteration": 3})).unwrap(), "late");
+        assert_eq!(
+            render(dir.path(), template, json!({"iteration": "10"})).unwrap(),
+            "late"
+        );
+        assert_eq!(render(dir.path(), template, json!({})).unwrap(), "early");
+    }
+}
@@ -1,3 +1,356 @@
+mod helpers;
 mod prompt;
 
-pub use prompt::PROMPT_TEMPLATE;
+pub use prompt::{LEGACY_PROMPT_TEMPLATE, PROMPT_TEMPLATE};
+
+use crate::config::{Config, PromptConfig};
+use eyre::{Context, Result};
+use handlebars::{Handlebars, TemplateError};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The project's own prompt template, replacing the built-in one when present.
+pub const PROMPT_FILE: &str = "PROMPT.md";
+
+/// Directory under `.rwl/` whose `*.md` files are registered as partials.
+pub const PARTIALS_DIR: &str = "partials";
+
+/// The Handlebars engine shared by every template `rwl` renders (the prompt
+/// and `llm.command` argv).
+///
+/// HTML escaping is disabled: neither a prompt nor an argv element is HTML,
+/// and escaping would turn `<promise>COMPLETE</promise>` into
+/// `&lt;promise&gt;...` - a signal the agent could never echo back verbatim.
+pub fn engine() -> Handlebars<'static> {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_escape_fn(handlebars::no_escape);
+    handlebars
+}
+
+/// Where the prompt template was loaded from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptSource {
+    /// `prompt.template` in the config.
+    Config(PathBuf),
+    /// The project's `.rwl/PROMPT.md`.
+    Project(PathBuf),
+    /// The compiled-in [`PROMPT_TEMPLATE`].
+    BuiltIn,
+}
+
+impl fmt::Display for PromptSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PromptSource::Config(path) | PromptSource::Project(path) => write!(f, "{}", path.display()),
+            PromptSource::BuiltIn => write!(f, "built-in"),
+        }
+    }
+}
+
+/// A shared prompt fragment from `.rwl/partials/<name>.md`, included in a
+/// template with `{{> name}}`.
+#[derive(Debug, Clone)]
+pub struct Partial {
+    pub name: String,
+    pub path: PathBuf,
+    pub text: String,
+}
+
+/// The prompt template a run renders each iteration, with its partials.
+#[derive(Debug, Clone)]
+pub struct PromptTemplate {
+    pub source: PromptSource,
+    pub text: String,
+    pub partials: Vec<Partial>,
+}
+
+impl PromptTemplate {
+    pub fn built_in() -> Self {
+        Self {
+            source: PromptSource::BuiltIn,
+            text: PROMPT_TEMPLATE.to_string(),
+            partials: Vec::new(),
+        }
+    }
+
+    /// Name the template is registered under in [`PromptTemplate::engine`].
+    pub const NAME: &'static str = "prompt";
+
+    /// An engine with the prompt helpers (resolving `file` and `git-log`
+    /// against `work_dir`), the partials, and the template registered as
+    /// [`PromptTemplate::NAME`]. Parse errors are reported as
+    /// `<path>:<line>:<column>: <reason>`.
+    pub fn engine(&self, work_dir: &Path) -> Result<Handlebars<'static>> {
+        let mut handlebars = engine();
+        helpers::register(&mut handlebars, work_dir);
+        for partial in &self.partials {
+            handlebars
+                .register_partial(&partial.name, &partial.text)
+                .map_err(|e| eyre::eyre!("Invalid prompt partial {}", describe_error(&partial.path.display(), &e)))?;
+        }
+        handlebars
+            .register_template_string(Self::NAME, &self.text)
+            .map_err(|e| eyre::eyre!("Invalid prompt template {}", describe_error(&self.source, &e)))?;
+        Ok(handlebars)
+    }
+}
+
+/// Resolve the prompt template for the project at `project_dir`:
+/// `prompt.template` when configured (relative to the project), else the
+/// project's `.rwl/PROMPT.md`, else the built-in template.
+///
+/// A `.rwl/PROMPT.md` identical to [`LEGACY_PROMPT_TEMPLATE`] is the copy
+/// older `rwl init` wrote, not a customization, so the built-in template is
+/// used instead. Any other `.rwl/PROMPT.md` is used with a warning, since it
+/// may be a stale copy missing what the built-in template now asks for.
+///
+/// Every `.rwl/partials/*.md` is loaded as a partial named after its file
+/// stem. The template and partials are parsed here, so a syntax error fails
+/// the run before the loop starts rather than on the first iteration.
+pub fn load_prompt_template(project_dir: &Path, config: &PromptConfig) -> Result<PromptTemplate> {
+    let source = match &config.template {
+        Some(path) => PromptSource::Config(project_dir.join(path)),
+        None => {
+            let project = Config::local_config_dir(project_dir).join(PROMPT_FILE);
+            if !project.exists() {
+                PromptSource::BuiltIn
+            } else if is_legacy_copy(&project) {
+                log::debug!("load_prompt_template: path={} is the old built-in template", project.display());
+                PromptSource::BuiltIn
+            } else {
+                log::warn!("load_prompt_template: path={} overrides the built-in template", project.display());
+                PromptSource::Project(project)
+            }
+        }
+    };
+    log::debug!("load_prompt_template: source={}", source);
+
+    let mut template = match &source {
+        PromptSource::Config(path) | PromptSource::Project(path) => PromptTemplate {
+            text: std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read prompt template {}", path.display()))?,
+            source,
+            partials: Vec::new(),
+        },
+        PromptSource::BuiltIn => PromptTemplate::built_in(),
+    };
+    template.partials = load_partials(&Config::local_config_dir(project_dir).join(PARTIALS_DIR))?;
+    template.engine(project_dir)?;
+    Ok(template)
+}
+
+/// `path` holds exactly the template older `rwl init` copied there.
+fn is_legacy_copy(path: &Path) -> bool {
+    std::fs::read(path).is_ok_and(|text| text == LEGACY_PROMPT_TEMPLATE.as_bytes())
+}
+
+/// Load `dir/*.md` as partials, sorted by name. A missing dir means none.
+fn load_partials(dir: &Path) -> Result<Vec<Partial>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut partials = Vec::new();
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "md") {
+            continue;
+        }
+        let Some(name) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+            continue;
+        };
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read prompt partial {}", path.display()))?;
+        partials.push(Partial { name, path, text });
+    }
+    partials.sort_by(|a, b| a.name.cmp(&b.name));
+    log::debug!(
+        "load_partials: dir={} partials={:?}",
+        dir.display(),
+        partials.iter().map(|p| &p.name).collect::<Vec<_>>()
+    );
+    Ok(partials)
+}
+
+fn describe_error(source: &dyn fmt::Display, e: &TemplateError) -> String {
+    match e.pos() {
+        Some((line, column)) => format!("{}:{}:{}: {}", source, line, column, e.reason()),
+        None => format!("{}: {}", source, e.reason()),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_engine_renders_signal_verbatim() {
+        let mut data = HashMap::new();
+        data.insert("completion_signal", "<promise>COMPLETE</promise>");
+        data.insert("plan_path", "plan.md");
+        let rendered = engine().render_template(PROMPT_TEMPLATE, &data).unwrap();
+        assert!(rendered.contains("signal: `<promise>COMPLETE</promise>`"));
+        assert!(!rendered.contains("&lt;"));
+    }
+
+    #[test]
+    fn test_built_in_renders_loop_state() {
+        let data = serde_json::json!({
+            "completion_signal": "<promise>COMPLETE</promise>",
+            "plan_path": "plan.md",
+            "iteration": 4,
+            "max_iterations": 20,
+            "remaining_minutes": 35,
+            "last_diffstat": " src/lib.rs | 3 ++-",
+            "recent_commits": ["abc123 rwl: iteration 3", "def456 rwl: iteration 2"],
+            "failed_gates": ["clippy", "no_todos"],
+        });
+        let rendered = engine().render_template(PROMPT_TEMPLATE, &data).unwrap();
+        assert!(rendered.contains("This is iteration 4 of 20."));
+        assert!(rendered.contains("About 35 minute(s)"));
+        assert!(rendered.contains(" src/lib.rs | 3 ++-"));
+        assert!(rendered.contains("- abc123 rwl: iteration 3\n- def456 rwl: iteration 2\n"));
+        assert!(rendered.contains("failed last time: `clippy`, `no_todos`"));
+
+        let first = engine()
+            .render_template(
+                PROMPT_TEMPLATE,
+                &serde_json::json!({"iteration": 1, "max_iterations": 20, "plan_path": "plan.md"}),
+            )
+            .unwrap();
+        assert!(first.contains("This is iteration 1 of 20."));
+        assert!(!first.contains("minute(s)"));
+        assert!(!first.contains("Recent commits"));
+        assert!(!first.contains("failed last time"));
+    }
+
+    #[test]
+    fn test_built_in_shows_only_the_current_phase() {
+        let data = serde_json::json!({
+            "plan_path": "plan.md",
+            "phase": {
+                "number": 2,
+                "count": 3,
+                "completed": 1,
+                "title": "Phase 2: Parser",
+                "text": "## Phase 2: Parser\n\n- [ ] Parse sums",
+            },
+        });
+        let rendered = engine().render_template(PROMPT_TEMPLATE, &data).unwrap();
+        assert!(rendered.contains("`plan.md` has 3 phases, 1 complete."));
+        assert!(rendered.contains("Work ONLY on phase 2:\n\n## Phase 2: Parser\n\n- [ ] Parse sums\n"));
+        assert!(!rendered.contains("for what to build"));
+    }
+
+    #[test]
+    fn test_load_falls_back_to_built_in() {
+        let dir = tempdir().unwrap();
+        let template = load_prompt_template(dir.path(), &PromptConfig::default()).unwrap();
+        assert_eq!(template.source, PromptSource::BuiltIn);
+        assert_eq!(template.text, PROMPT_TEMPLATE);
+    }
+
+    #[test]
+    fn test_load_prefers_project_prompt_md() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(".rwl")).unwrap();
+        fs::write(dir.path().join(".rwl/PROMPT.md"), "Custom: {{plan_path}}").unwrap();
+
+        let template = load_prompt_template(dir.path(), &PromptConfig::default()).unwrap();
+        assert!(matches!(template.source, PromptSource::Project(_)));
+        assert_eq!(template.text, "Custom: {{plan_path}}");
+    }
+
+    #[test]
+    fn test_load_treats_old_init_copy_as_built_in() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(".rwl")).unwrap();
+        fs::write(dir.path().join(".rwl/PROMPT.md"), LEGACY_PROMPT_TEMPLATE).unwrap();
+
+        let template = load_prompt_template(dir.path(), &PromptConfig::default()).unwrap();
+        assert_eq!(template.source, PromptSource::BuiltIn);
+        assert_eq!(template.text, PROMPT_TEMPLATE);
+
+        fs::write(dir.path().join(".rwl/PROMPT.md"), format!("{}\nMore.\n", LEGACY_PROMPT_TEMPLATE)).unwrap();
+        let template = load_prompt_template(dir.path(), &PromptConfig::default()).unwrap();
+        assert!(matches!(template.source, PromptSource::Project(_)));
+    }
+
+    #[test]
+    fn test_load_configured_template_wins_and_must_exist() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(".rwl")).unwrap();
+        fs::write(dir.path().join(".rwl/PROMPT.md"), "project").unwrap();
+        fs::write(dir.path().join("team.md"), "team").unwrap();
+
+        let config = PromptConfig {
+            template: Some(PathBuf::from("team.md")),
+            ..PromptConfig::default()
+        };
+        let template = load_prompt_template(dir.path(), &config).unwrap();
+        assert_eq!(template.source, PromptSource::Config(dir.path().join("team.md")));
+        assert_eq!(template.text, "team");
+
+        let missing = PromptConfig {
+            template: Some(PathBuf::from("missing.md")),
+            ..PromptConfig::default()
+        };
+        assert!(load_prompt_template(dir.path(), &missing).is_err());
+    }
+
+    #[test]
+    fn test_parse_error_reports_line_and_column() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(".rwl")).unwrap();
+        fs::write(
+            dir.path().join(".rwl/PROMPT.md"),
+            "line one\nok {{name}} then {{#if progress}}x{{/each}}\n",
+        )
+        .unwrap();
+
+        let err = load_prompt_template(dir.path(), &PromptConfig::default())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("PROMPT.md:2:35:"), "{}", err);
+        assert!(err.contains("\"each\" is closing"), "{}", err);
+    }
+
+    #[test]
+    fn test_partials_are_registered_by_file_stem() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(".rwl/partials")).unwrap();
+        fs::write(dir.path().join(".rwl/PROMPT.md"), "Rules:\n{{> coding-standards}}").unwrap();
+        fs::write(
+            dir.path().join(".rwl/partials/coding-standards.md"),
+            "No unwrap in {{plan_path}}.",
+        )
+        .unwrap();
+        fs::write(dir.path().join(".rwl/partials/notes.txt"), "ignored").unwrap();
+
+        let template = load_prompt_template(dir.path(), &PromptConfig::default()).unwrap();
+        assert_eq!(template.partials.len(), 1);
+        let mut data = HashMap::new();
+        data.insert("plan_path", "plan.md");
+        let rendered = template
+            .engine(dir.path())
+            .unwrap()
+            .render(PromptTemplate::NAME, &data)
+            .unwrap();
+        assert_eq!(rendered, "Rules:\nNo unwrap in plan.md.");
+    }
+
+    #[test]
+    fn test_partial_parse_error_names_the_partial() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(".rwl/partials")).unwrap();
+        fs::write(dir.path().join(".rwl/partials/broken.md"), "{{#if x}}").unwrap();
+
+        let err = load_prompt_template(dir.path(), &PromptConfig::default())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("broken.md:1:"), "{}", err);
+    }
+}
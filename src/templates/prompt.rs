@@ -1,6 +1,103 @@
 /// Default PROMPT.md template content
 pub const PROMPT_TEMPLATE: &str = r#"# Ralph Wiggum Loop - ONE TASK THEN EXIT
 
+You are in a Ralph Wiggum loop. You have NO MEMORY of previous runs.
+Your state persists ONLY in `{{progress_path}}` (the loop maintains it; read-only to you).
+
+## CRITICAL RULES
+
+1. **READ {{progress_path}} FIRST** - It tells you what was done
+2. **DO ONE SMALL THING** - Not a phase. One file, one fix, one test.
+3. **EXIT IMMEDIATELY** - Do not retry errors. Just exit.
+
+The loop will restart you with fresh context. That's the whole point.
+Validation runs EXTERNALLY - you do NOT run tests or validation.
+
+---
+
+## Your Workflow
+
+1. Read state: `cat {{progress_path}} && git log --oneline -10`
+2. Do ONE small task
+3. Do not edit {{progress_path}} - the loop appends each iteration's outcome to it
+4. End your output with what you did, for the next iteration and the humans:
+   `<rwl-summary>One or two sentences: what you changed and what is left.</rwl-summary>`
+5. If ALL work is complete, signal: `{{completion_signal}}`
+6. EXIT - do nothing else
+
+---
+
+## Implementation Plan
+
+{{#if phase}}
+`{{plan_path}}` has {{phase.count}} phases, {{phase.completed}} complete.
+Work ONLY on phase {{phase.number}}:
+
+{{phase.text}}
+
+Check off its items (`- [x]`) in `{{plan_path}}` as you finish them.
+{{#if phase.signal}}
+When this phase is done, signal: `{{phase.signal}}`
+{{/if}}
+{{else}}
+Read `{{plan_path}}` for what to build.
+Each phase lists files and validation criteria.
+{{/if}}
+
+---
+
+## Loop State
+
+This is iteration {{iteration}} of {{max_iterations}}.
+{{#if remaining_minutes}}About {{remaining_minutes}} minute(s) of the run's time budget remain.
+{{/if}}
+{{#if last_diffstat}}
+The previous iteration changed:
+
+```
+{{last_diffstat}}
+```
+{{/if}}
+{{#if recent_commits}}
+Recent commits:
+
+{{#each recent_commits}}
+- {{this}}
+{{/each}}
+{{/if}}
+{{#if failed_gates}}
+Quality gates that failed last time: {{#each failed_gates}}{{#if @index}}, {{/if}}`{{this}}`{{/each}}
+{{/if}}
+
+{{#if protected_paths}}
+---
+
+## Off-Limits Paths (immutable boundaries)
+
+You may NOT modify these paths. Any edit you make to them will be reverted
+automatically and never committed - do not waste an iteration touching them:
+
+{{protected_paths}}
+{{/if}}
+
+{{#if progress}}
+---
+
+## Previous Iteration Feedback
+
+The following feedback accumulated from previous iterations:
+
+{{progress}}
+{{/if}}
+
+## Now: Read {{progress_path}} and do ONE thing
+"#;
+
+/// The built-in template before it gained loop state, phases and summaries.
+/// `rwl init` used to copy it to `.rwl/PROMPT.md`; an untouched copy is
+/// treated as the built-in template so those projects are not stuck on it.
+pub const LEGACY_PROMPT_TEMPLATE: &str = r#"# Ralph Wiggum Loop - ONE TASK THEN EXIT
+
 You are in a Ralph Wiggum loop. You have NO MEMORY of previous runs.
 Your state persists ONLY in `.rwl/progress.txt`.
 
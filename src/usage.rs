@@ -0,0 +1,220 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Spend reported by the agent for one invocation (or summed over a run).
+///
+/// These are Claude's *own* figures from the terminal `result` event of
+/// `--output-format stream-json` - advisory, not authoritative billing (see the
+/// containment design's Addendum).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    pub cost_usd: f64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_input_tokens: u64,
+    pub cache_read_input_tokens: u64,
+}
+
+impl Usage {
+    /// Every token the model processed or produced, cache traffic included.
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens + self.cache_creation_input_tokens + self.cache_read_input_tokens
+    }
+
+    /// Accumulate another invocation's usage into this one.
+    pub fn add(&mut self, other: &Usage) {
+        self.cost_usd += other.cost_usd;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+    }
+}
+
+impl std::fmt::Display for Usage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "${:.4} ({} tokens: {} in, {} out, {} cache write, {} cache read)",
+            self.cost_usd,
+            self.total_tokens(),
+            self.input_tokens,
+            self.output_tokens,
+            self.cache_creation_input_tokens,
+            self.cache_read_input_tokens
+        )
+    }
+}
+
+/// One iteration's usage, as recorded in `result.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IterationUsage {
+    pub iteration: u32,
+    #[serde(flatten)]
+    pub usage: Usage,
+}
+
+/// Run-level usage block of `result.json`: the total plus the per-iteration
+/// breakdown it was summed from.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunUsage {
+    pub total: Usage,
+    pub iterations: Vec<IterationUsage>,
+}
+
+impl RunUsage {
+    /// Record one agent attempt's usage and fold it into the total. Retried
+    /// attempts of the same iteration add up in its entry.
+    pub fn record(&mut self, iteration: u32, usage: Usage) {
+        self.total.add(&usage);
+        match self.iterations.last_mut() {
+            Some(last) if last.iteration == iteration => last.usage.add(&usage),
+            _ => self.iterations.push(IterationUsage { iteration, usage }),
+        }
+    }
+}
+
+/// A classified line of `--output-format stream-json` stdout.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamLine {
+    /// Assistant prose, reconstructed from `message.content[].text`.
+    Text(String),
+    /// The terminal `result` event, carrying the reported spend.
+    Result(Usage),
+    /// A JSON event with nothing to show (system init, tool use, tool results).
+    Event,
+    /// Not JSON at all (warnings Claude prints before the stream starts);
+    /// passed through verbatim rather than failing the parse.
+    Raw(String),
+}
+
+/// Classify one stdout line of a `stream-json` run, failing soft.
+///
+/// Never errors: a line that is not a JSON object becomes [`StreamLine::Raw`],
+/// and a `result` event missing any usage field reads that field as zero. The
+/// event schema is Claude Code's internal contract, so every lookup is
+/// defensive.
+pub fn parse_stream_line(line: &str) -> StreamLine {
+    let value: Value = match serde_json::from_str(line) {
+        Ok(value @ Value::Object(_)) => value,
+        _ => return StreamLine::Raw(line.to_string()),
+    };
+
+    match value.get("type").and_then(Value::as_str) {
+        Some("assistant") => {
+            let text = value
+                .pointer("/message/content")
+                .and_then(Value::as_array)
+                .map(|blocks| {
+                    blocks
+                        .iter()
+                        .filter(|b| b.get("type").and_then(Value::as_str) == Some("text"))
+                        .filter_map(|b| b.get("text").and_then(Value::as_str))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                })
+                .unwrap_or_default();
+            if text.is_empty() { StreamLine::Event } else { StreamLine::Text(text) }
+        }
+        Some("result") => {
+            let token = |name: &str| {
+                value
+                    .get("usage")
+                    .and_then(|u| u.get(name))
+                    .and_then(Value::as_u64)
+                    .unwrap_or(0)
+            };
+            StreamLine::Result(Usage {
+                cost_usd: value.get("total_cost_usd").and_then(Value::as_f64).unwrap_or(0.0),
+                input_tokens: token("input_tokens"),
+                output_tokens: token("output_tokens"),
+                cache_creation_input_tokens: token("cache_creation_input_tokens"),
+                cache_read_input_tokens: token("cache_read_input_tokens"),
+            })
+        }
+        _ => StreamLine::Event,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_non_json_preamble_passes_through() {
+        let line = "claude.ai connectors disabled";
+        assert_eq!(parse_stream_line(line), StreamLine::Raw(line.to_string()));
+        // Valid JSON that is not an object is still not an event.
+        assert_eq!(parse_stream_line("42"), StreamLine::Raw("42".to_string()));
+    }
+
+    #[test]
+    fn test_assistant_text_is_reconstructed() {
+        let line = r#"{"type":"assistant","message":{"content":[{"type":"text","text":"did one thing"},{"type":"text","text":"<promise>COMPLETE</promise>"}]}}"#;
+        assert_eq!(
+            parse_stream_line(line),
+            StreamLine::Text("did one thing\n<promise>COMPLETE</promise>".to_string())
+        );
+    }
+
+    #[test]
+    fn test_tool_use_only_is_silent_event() {
+        let line = r#"{"type":"assistant","message":{"content":[{"type":"tool_use","name":"Write","input":{}}]}}"#;
+        assert_eq!(parse_stream_line(line), StreamLine::Event);
+        assert_eq!(
+            parse_stream_line(r#"{"type":"system","subtype":"init"}"#),
+            StreamLine::Event
+        );
+    }
+
+    #[test]
+    fn test_result_event_captures_usage() {
+        let line = r#"{"type":"result","subtype":"success","total_cost_usd":0.041,"usage":{"input_tokens":10,"output_tokens":20,"cache_creation_input_tokens":30,"cache_read_input_tokens":40},"result":"done"}"#;
+        let StreamLine::Result(usage) = parse_stream_line(line) else {
+            panic!("expected a result line");
+        };
+        assert_eq!(usage.cost_usd, 0.041);
+        assert_eq!(usage.total_tokens(), 100);
+    }
+
+    #[test]
+    fn test_result_event_missing_fields_reads_zero() {
+        let StreamLine::Result(usage) = parse_stream_line(r#"{"type":"result"}"#) else {
+            panic!("expected a result line");
+        };
+        assert_eq!(usage, Usage::default());
+    }
+
+    #[test]
+    fn test_run_usage_record_sums_total() {
+        let mut run = RunUsage::default();
+        let one = Usage {
+            cost_usd: 0.5,
+            input_tokens: 1,
+            output_tokens: 2,
+            ..Usage::default()
+        };
+        run.record(1, one);
+        run.record(2, one);
+        run.record(2, one);
+        assert_eq!(run.iterations.len(), 2);
+        assert_eq!(run.iterations[1].usage.cost_usd, 1.0);
+        assert_eq!(run.total.cost_usd, 1.5);
+        assert_eq!(run.total.total_tokens(), 9);
+    }
+
+    #[test]
+    fn test_iteration_usage_serializes_flat() {
+        let entry = IterationUsage {
+            iteration: 3,
+            usage: Usage {
+                cost_usd: 0.25,
+                ..Usage::default()
+            },
+        };
+        let json: serde_json::Value = serde_json::to_value(&entry).unwrap();
+        assert_eq!(json["iteration"], 3);
+        assert_eq!(json["cost_usd"], 0.25);
+    }
+}
@@ -1,5 +1,5 @@
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use tempfile::TempDir;
 
@@ -45,19 +45,73 @@ git:
     fs::write(dir.join("plan.md"), "# Test Plan\nDo nothing.").unwrap();
 }
 
-fn create_mock_claude(dir: &Path, output: &str) -> String {
+/// Write `script` as an executable mock `claude` in `dir/mock-bin` and return
+/// that directory, for the front of `PATH`.
+fn write_mock_agent(dir: &Path, script: &str) -> PathBuf {
     let bin_dir = dir.join("mock-bin");
     fs::create_dir_all(&bin_dir).unwrap();
-    let script = bin_dir.join("claude");
-    fs::write(&script, format!("#!/bin/bash\necho '{}'\n", output)).unwrap();
+    let path = bin_dir.join("claude");
+    fs::write(&path, script).unwrap();
 
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
-        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
     }
 
-    bin_dir.display().to_string()
+    bin_dir
+}
+
+fn create_mock_claude(dir: &Path, output: &str) -> String {
+    write_mock_agent(dir, &format!("#!/bin/bash\necho '{}'\n", output))
+        .display()
+        .to_string()
+}
+
+/// A mock `claude` speaking `--output-format stream-json`: a non-JSON preamble
+/// line, one assistant event with `text` (JSON-escaped), and a result event
+/// reporting `cost_usd` and 120 tokens.
+fn create_mock_stream_json_claude(dir: &Path, text: &str, cost_usd: f64) -> String {
+    write_mock_agent(
+        dir,
+        &format!(
+            r#"#!/bin/bash
+echo 'Sandbox disabled'
+echo '{{"type":"assistant","message":{{"content":[{{"type":"text","text":"{}"}}]}}}}'
+echo '{{"type":"result","subtype":"success","total_cost_usd":{},"usage":{{"input_tokens":100,"output_tokens":20}}}}'
+"#,
+            text, cost_usd
+        ),
+    )
+    .display()
+    .to_string()
+}
+
+/// A mock agent that runs `cases`, a bash `case` body matched against the
+/// prompt.
+fn create_case_claude(dir: &Path, cases: &str) -> String {
+    write_mock_agent(
+        dir,
+        &format!(
+            "#!/bin/bash\nfor arg; do prompt=\"$arg\"; done\ncase \"$prompt\" in\n{}esac\n",
+            cases
+        ),
+    )
+    .display()
+    .to_string()
+}
+
+/// Make `dir` a git repo with everything in it committed.
+fn init_git_repo(dir: &Path) {
+    for args in [
+        vec!["init", "-q"],
+        vec!["config", "user.email", "test@test.com"],
+        vec!["config", "user.name", "Test"],
+        vec!["add", "-A"],
+        vec!["commit", "-qm", "initial"],
+    ] {
+        Command::new("git").args(&args).current_dir(dir).output().unwrap();
+    }
 }
 
 fn run_rwl(project_dir: &Path, mock_bin: &str, session_dir: &Path) -> std::process::Output {
@@ -121,8 +175,76 @@ fn test_complete_exits_0_and_writes_result_json() {
 
     // Verify session.log exists
     assert!(session_dir.join("session.log").exists());
-    // Verify progress.txt exists
-    assert!(session_dir.join("progress.txt").exists());
+    // The progress log lives in the work dir (here the project itself): typed
+    // records, with the markdown rendering next to them.
+    let log = fs::read_to_string(project.path().join(".rwl/progress.jsonl")).unwrap();
+    let records: Vec<serde_json::Value> = log.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(records.len(), 2, "{}", log);
+    assert_eq!(records[0]["kind"], "start");
+    assert_eq!(records[1]["kind"], "iteration");
+    assert_eq!(records[1]["iteration"], 1);
+    assert_eq!(records[1]["validation_passed"], true);
+    assert_eq!(records[1]["promise_found"], true);
+    assert!(project.path().join(".rwl/progress.txt").exists());
+    assert!(!session_dir.join("progress.txt").exists());
+
+    // ...which is where `rwl status` reads it.
+    let status = Command::new(rwl_binary())
+        .arg("status")
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&status.stdout);
+    assert!(stdout.contains("Iterations: 1"), "{}", stdout);
+    assert!(stdout.contains("Status: Complete"), "{}", stdout);
+    assert!(stdout.contains("Iteration 1"), "{}", stdout);
+}
+
+#[test]
+fn test_status_finds_worktree_session_under_session_path() {
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+    setup_project(project.path(), "true", 1, "<promise>COMPLETE</promise>");
+    let config_path = project.path().join(".rwl/rwl.yml");
+    let config = fs::read_to_string(&config_path)
+        .unwrap()
+        .replace("auto_commit: false", "auto_commit: true");
+    fs::write(&config_path, config).unwrap();
+    fs::write(project.path().join(".gitignore"), "mock-bin/\n").unwrap();
+    init_git_repo(project.path());
+    let mock_bin = create_mock_claude(project.path(), "<promise>COMPLETE</promise>");
+
+    let output = run_rwl(project.path(), &mock_bin, sessions.path());
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "stdout: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert!(!project.path().join(".rwl/progress.jsonl").exists());
+
+    let status = Command::new(rwl_binary())
+        .arg("status")
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&status.stdout);
+    let sessions_dir = sessions.path().canonicalize().unwrap();
+    assert!(
+        stdout.contains(&format!("Log: {}", sessions_dir.display())),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("worktree/.rwl/progress.jsonl"), "{}", stdout);
+    assert!(stdout.contains("Iterations: 1"), "{}", stdout);
+
+    // The pointer itself is never committed or reported as a change.
+    let porcelain = Command::new("git")
+        .args(["status", "--porcelain"])
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    assert!(!String::from_utf8_lossy(&porcelain.stdout).contains("last-session"));
 }
 
 #[test]
@@ -157,6 +279,428 @@ fn test_max_iterations_exits_1() {
     assert_eq!(parsed["iterations"], 1);
 }
 
+#[test]
+fn test_agent_summary_is_recorded_in_progress_and_result() {
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+
+    setup_project(project.path(), "true", 1, "<promise>COMPLETE</promise>");
+    let mock_bin = create_mock_claude(
+        project.path(),
+        "Working...\n<rwl-summary>Added the lexer; the parser is next.</rwl-summary>",
+    );
+
+    let output = run_rwl(project.path(), &mock_bin, sessions.path());
+    assert_eq!(output.status.code(), Some(1));
+
+    let entries: Vec<_> = fs::read_dir(sessions.path()).unwrap().filter_map(|e| e.ok()).collect();
+    let session_dir = entries[0].path();
+    let content = fs::read_to_string(session_dir.join("result.json")).unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(parsed["summaries"][0]["iteration"], 1);
+    assert_eq!(
+        parsed["summaries"][0]["summary"],
+        "Added the lexer; the parser is next."
+    );
+
+    let progress = fs::read_to_string(project.path().join(".rwl/progress.txt")).unwrap();
+    assert!(
+        progress.contains("Summary: Added the lexer; the parser is next.\n"),
+        "{}",
+        progress
+    );
+}
+
+#[test]
+fn test_phased_plan_feeds_current_phase_and_tracks_completion() {
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+
+    setup_project(project.path(), "true", 1, "<promise>COMPLETE</promise>");
+    fs::write(
+        project.path().join("plan.md"),
+        "# Plan\n\n## Phase 1: Lexer\n\n- [ ] Tokenize\n\n## Phase 2: Parser\n\n- [ ] Parse\n",
+    )
+    .unwrap();
+    // The agent records its prompt, then ticks off phase 1.
+    let bin_dir = write_mock_agent(
+        project.path(),
+        "#!/bin/bash\nfor arg; do prompt=\"$arg\"; done\nprintf '%s' \"$prompt\" > prompt-seen.txt\n\
+         sed -i 's/- \\[ \\] Tokenize/- [x] Tokenize/' plan.md\necho working\n",
+    );
+
+    let output = run_rwl(project.path(), &bin_dir.display().to_string(), sessions.path());
+    assert_eq!(output.status.code(), Some(1));
+
+    let prompt = fs::read_to_string(project.path().join("prompt-seen.txt")).unwrap();
+    assert!(
+        prompt.contains("Work ONLY on phase 1:\n\n## Phase 1: Lexer\n"),
+        "{}",
+        prompt
+    );
+    assert!(!prompt.contains("- [ ] Parse"), "{}", prompt);
+
+    let entries: Vec<_> = fs::read_dir(sessions.path()).unwrap().filter_map(|e| e.ok()).collect();
+    let content = fs::read_to_string(entries[0].path().join("result.json")).unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(parsed["phases"][0]["title"], "Phase 1: Lexer");
+    assert_eq!(parsed["phases"][0]["complete"], true);
+    assert_eq!(parsed["phases"][1]["complete"], false);
+
+    let status = Command::new(rwl_binary())
+        .arg("status")
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&status.stdout);
+    assert!(stdout.contains("Phases: 1/2 complete"), "{}", stdout);
+    assert!(stdout.contains("Phase 2: Parser (0/1)"), "{}", stdout);
+}
+
+#[test]
+fn test_phase_checks_gate_advancement_and_completion() {
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+
+    // The project-wide validation only passes once phase 2 is done; phase 1
+    // is validated by its own command instead.
+    setup_project(project.path(), "test -f parser.done", 4, "<promise>COMPLETE</promise>");
+    fs::write(
+        project.path().join("plan.md"),
+        "# Plan\n\n\
+         ## Phase 1: Lexer\n\n- [ ] Tokenize\n\nValidation: `test -f lexer.done`\n\n\
+         ## Phase 2: Parser\n\nSignal: `<promise>PARSER</promise>`\n",
+    )
+    .unwrap();
+    // Phase 1: tick the item, pass its validation and claim the whole plan
+    // done too early. Phase 2: emit its signal and the completion promise.
+    let mock_bin = create_case_claude(
+        project.path(),
+        "*'Work ONLY on phase 1'*)\n\
+           sed -i 's/- \\[ \\] Tokenize/- [x] Tokenize/' plan.md; touch lexer.done\n\
+           echo '<promise>COMPLETE</promise>';;\n\
+         *'Work ONLY on phase 2'*)\n\
+           touch parser.done; echo '<promise>PARSER</promise>'; echo '<promise>COMPLETE</promise>';;\n",
+    );
+
+    let output = run_rwl(project.path(), &mock_bin, sessions.path());
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "stdout: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let entries: Vec<_> = fs::read_dir(sessions.path()).unwrap().filter_map(|e| e.ok()).collect();
+    let session_dir = entries[0].path();
+    let content = fs::read_to_string(session_dir.join("result.json")).unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(parsed["iterations"], 2);
+    assert_eq!(parsed["phases"][0]["complete"], true);
+    assert_eq!(parsed["phases"][1]["complete"], true);
+    let validation =
+        |n: u32| fs::read_to_string(session_dir.join(format!("iterations/{:03}/validation.log", n))).unwrap();
+    assert!(validation(1).starts_with("$ test -f lexer.done\n"), "{}", validation(1));
+    assert!(
+        validation(2).starts_with("$ test -f parser.done\n"),
+        "{}",
+        validation(2)
+    );
+
+    let log = fs::read_to_string(project.path().join(".rwl/progress.jsonl")).unwrap();
+    let records: Vec<serde_json::Value> = log.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(records[1]["phase_check"]["phase"], "Phase 1: Lexer");
+    assert_eq!(records[1]["phase_check"]["passed"], true);
+    assert_eq!(records[1]["validation_passed"], true);
+    assert_eq!(records[1]["summary"], "Completion promise rejected: plan phases remain");
+    assert_eq!(records[2]["phase_check"]["phase"], "Phase 2: Parser");
+    assert_eq!(records[2]["phase_check"]["passed"], true);
+}
+
+#[test]
+fn test_phase_checks_reject_promise_when_plan_is_deleted() {
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+
+    setup_project(project.path(), "true", 2, "<promise>COMPLETE</promise>");
+    fs::write(
+        project.path().join("plan.md"),
+        "# Plan\n\n## Phase 1: Lexer\n\nValidation: `true`\n\n## Phase 2: Parser\n\nValidation: `true`\n",
+    )
+    .unwrap();
+    // The agent deletes the plan, then claims the whole plan done.
+    let bin_dir = write_mock_agent(project.path(), "#!/bin/bash\nrm -f plan.md\necho '<promise>COMPLETE</promise>'\n");
+
+    let output = run_rwl(project.path(), &bin_dir.display().to_string(), sessions.path());
+    assert_eq!(
+        output.status.code(),
+        Some(1),
+        "stdout: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let log = fs::read_to_string(project.path().join(".rwl/progress.jsonl")).unwrap();
+    assert!(
+        log.contains("Completion promise rejected: plan phases remain"),
+        "{}",
+        log
+    );
+}
+
+#[test]
+fn test_require_checklist_rejects_promise_with_unchecked_items() {
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+    let signal = "<promise>COMPLETE</promise>";
+
+    setup_project(project.path(), "true", 2, signal);
+    let config_path = project.path().join(".rwl/rwl.yml");
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(
+        &config_path,
+        config.replace("loop:\n", "loop:\n  require-checklist: true\n"),
+    )
+    .unwrap();
+    fs::write(
+        project.path().join("plan.md"),
+        "# Plan\n\n- [x] Parse input\n- [ ] Write docs\n",
+    )
+    .unwrap();
+    // The agent claims completion without ever ticking the last item.
+    let mock_bin = create_mock_claude(project.path(), signal);
+
+    let output = run_rwl(project.path(), &mock_bin, sessions.path());
+    assert_eq!(
+        output.status.code(),
+        Some(1),
+        "stdout: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let log = fs::read_to_string(project.path().join(".rwl/progress.jsonl")).unwrap();
+    let records: Vec<serde_json::Value> = log.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[1]["unchecked"][0], "Write docs (line 4)");
+    assert_eq!(
+        records[1]["summary"],
+        "Completion promise rejected: 1 plan item(s) unchecked"
+    );
+    let progress = fs::read_to_string(project.path().join(".rwl/progress.txt")).unwrap();
+    assert!(progress.contains("  - [ ] Write docs (line 4)"), "{}", progress);
+}
+
+#[test]
+fn test_plan_lint_exit_codes() {
+    let project = TempDir::new().unwrap();
+    setup_project(project.path(), "true", 1, "<promise>COMPLETE</promise>");
+    fs::create_dir_all(project.path().join("src")).unwrap();
+    let lint = |plan: &str| {
+        fs::write(project.path().join("plan.md"), plan).unwrap();
+        Command::new(rwl_binary())
+            .args(["plan", "lint", "--plan", "plan.md"])
+            .current_dir(project.path())
+            .output()
+            .expect("Failed to run rwl")
+    };
+
+    let clean = lint("# Plan\n\n## Phase 1: Lexer\n\n- [ ] Add `src/lexer.rs`\n\nValidation: `true`\n");
+    let stdout = String::from_utf8_lossy(&clean.stdout);
+    assert_eq!(clean.status.code(), Some(0), "{}", stdout);
+    assert!(
+        stdout.contains("plan.md:5: warning: `src/lexer.rs` does not exist yet"),
+        "{}",
+        stdout
+    );
+
+    let bad = lint(
+        "# Plan\n\n## Phase 1: Config\n\n- [ ] Tune `.rwl/rwl.yml`\n\n\
+         Validation: `no-such-validator-xyz --all`\n",
+    );
+    let stdout = String::from_utf8_lossy(&bad.stdout);
+    assert_eq!(bad.status.code(), Some(1), "{}", stdout);
+    assert!(
+        stdout.contains("plan.md:5: error: step targets protected path `.rwl/rwl.yml`"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("`no-such-validator-xyz` not found on PATH"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("2 error(s)"), "{}", stdout);
+
+    let missing = Command::new(rwl_binary())
+        .args(["plan", "lint", "--plan", "missing.md"])
+        .current_dir(project.path())
+        .output()
+        .expect("Failed to run rwl");
+    assert_eq!(missing.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&missing.stdout).contains("cannot read the plan"));
+}
+
+fn read_batch(sessions: &Path) -> serde_json::Value {
+    let batch = fs::read_dir(sessions)
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .find(|p| p.file_name().unwrap().to_string_lossy().starts_with("batch-"))
+        .expect("no batch summary");
+    serde_json::from_str(&fs::read_to_string(batch).unwrap()).unwrap()
+}
+
+#[test]
+fn test_plan_queue_stops_on_first_failure() {
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+    setup_project(project.path(), "true", 1, "<promise>COMPLETE</promise>");
+    for plan in ["first.md", "second.md", "third.md"] {
+        fs::write(project.path().join(plan), "# Plan\nDo it.\n").unwrap();
+    }
+    let mock_bin = create_case_claude(
+        project.path(),
+        "*second.md*) echo 'still working';;\n*) echo '<promise>COMPLETE</promise>';;\n",
+    );
+
+    let path = format!("{}:{}", mock_bin, std::env::var("PATH").unwrap_or_default());
+    let output = Command::new(rwl_binary())
+        .args(["run", "--plan", "first.md", "--plan", "second.md", "--plan", "third.md"])
+        .args(["--session-path", &sessions.path().display().to_string()])
+        .args(["--unsafe", "--stop-on-failure"])
+        .current_dir(project.path())
+        .env("PATH", path)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(stdout.contains("Queue: 1/3 plans complete"), "{}", stdout);
+
+    let batch = read_batch(sessions.path());
+    assert_eq!(batch["outcome"], "max-iterations");
+    assert_eq!(batch["exit_code"], 1);
+    assert_eq!(batch["stop_on_failure"], true);
+    let outcomes: Vec<&str> = batch["plans"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["outcome"].as_str().unwrap())
+        .collect();
+    assert_eq!(outcomes, vec!["complete", "max-iterations", "skipped"]);
+    assert_eq!(batch["plans"][0]["plan"], "first.md");
+
+    // Each plan that ran has its own session and result.json.
+    for plan in &batch["plans"].as_array().unwrap()[..2] {
+        let session = Path::new(plan["session_dir"].as_str().unwrap());
+        assert!(session.join("result.json").exists(), "{}", session.display());
+    }
+    assert_ne!(batch["plans"][0]["session_dir"], batch["plans"][1]["session_dir"]);
+}
+
+#[test]
+fn test_batch_chains_worktrees() {
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+    setup_project(project.path(), "true", 1, "<promise>COMPLETE</promise>");
+    let config_path = project.path().join(".rwl/rwl.yml");
+    let config = fs::read_to_string(&config_path)
+        .unwrap()
+        .replace("auto_commit: false", "auto_commit: true");
+    fs::write(&config_path, config).unwrap();
+    fs::write(project.path().join(".gitignore"), "mock-bin/\n").unwrap();
+    fs::write(project.path().join("first.md"), "# Plan\nWrite a.txt.\n").unwrap();
+    fs::write(project.path().join("second.md"), "# Plan\nWrite b.txt.\n").unwrap();
+    fs::write(
+        project.path().join("plans.txt"),
+        "# Parser work\nfirst.md\n\nsecond.md\n",
+    )
+    .unwrap();
+    init_git_repo(project.path());
+    // The second plan only completes on top of the first plan's work.
+    let mock_bin = create_case_claude(
+        project.path(),
+        "*first.md*) echo a > a.txt; echo '<promise>COMPLETE</promise>';;\n\
+         *second.md*) test -f a.txt && echo b > b.txt && echo '<promise>COMPLETE</promise>';;\n",
+    );
+
+    let path = format!("{}:{}", mock_bin, std::env::var("PATH").unwrap_or_default());
+    let output = Command::new(rwl_binary())
+        .args(["batch", "plans.txt", "--chain"])
+        .args(["--session-path", &sessions.path().display().to_string()])
+        .current_dir(project.path())
+        .env("PATH", path)
+        .output()
+        .unwrap();
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "stdout: {}\nstderr: {}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let batch = read_batch(sessions.path());
+    assert_eq!(batch["outcome"], "complete");
+    assert_eq!(batch["chain"], true);
+    let second = batch["plans"][1]["branch"].as_str().unwrap();
+    let show = |file: &str| {
+        Command::new("git")
+            .args(["show", &format!("{}:{}", second, file)])
+            .current_dir(project.path())
+            .output()
+            .unwrap()
+    };
+    assert!(show("a.txt").status.success());
+    assert!(show("b.txt").status.success());
+}
+
+#[test]
+fn test_batch_chain_skips_incomplete_branches() {
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+    setup_project(project.path(), "true", 1, "<promise>COMPLETE</promise>");
+    let config_path = project.path().join(".rwl/rwl.yml");
+    let config = fs::read_to_string(&config_path)
+        .unwrap()
+        .replace("auto_commit: false", "auto_commit: true");
+    fs::write(&config_path, config).unwrap();
+    fs::write(project.path().join(".gitignore"), "mock-bin/\n").unwrap();
+    fs::write(project.path().join("first.md"), "# Plan\nWrite a.txt.\n").unwrap();
+    fs::write(project.path().join("second.md"), "# Plan\nWrite b.txt.\n").unwrap();
+    fs::write(project.path().join("plans.txt"), "first.md\nsecond.md\n").unwrap();
+    init_git_repo(project.path());
+    // The first plan commits a.txt but never completes; the second only
+    // completes when it does not start from that unfinished work.
+    let mock_bin = create_case_claude(
+        project.path(),
+        "*first.md*) echo a > a.txt;;\n\
+         *second.md*) test -f a.txt || { echo b > b.txt; echo '<promise>COMPLETE</promise>'; };;\n",
+    );
+
+    let path = format!("{}:{}", mock_bin, std::env::var("PATH").unwrap_or_default());
+    let output = Command::new(rwl_binary())
+        .args(["batch", "plans.txt", "--chain"])
+        .args(["--session-path", &sessions.path().display().to_string()])
+        .current_dir(project.path())
+        .env("PATH", path)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    let batch = read_batch(sessions.path());
+    assert_eq!(batch["plans"][0]["outcome"], "max-iterations", "{}", stdout);
+    assert!(batch["plans"][0]["branch"].is_string(), "{}", stdout);
+    assert_eq!(batch["plans"][1]["outcome"], "complete", "{}", stdout);
+    let second = batch["plans"][1]["branch"].as_str().unwrap();
+    let show = |file: &str| {
+        Command::new("git")
+            .args(["show", &format!("{}:{}", second, file)])
+            .current_dir(project.path())
+            .output()
+            .unwrap()
+    };
+    assert!(!show("a.txt").status.success());
+    assert!(show("b.txt").status.success());
+}
+
 #[test]
 fn test_uncontained_bypass_without_unsafe_refuses_exit_4() {
     // Non-git temp dir (isolation degrades to none) + permission bypass + no
@@ -198,6 +742,8 @@ fn test_uncontained_bypass_without_unsafe_refuses_exit_4() {
     );
     let stderr = String::from_utf8_lossy(&output.stderr);
     assert!(stderr.contains("--unsafe"), "refusal should name --unsafe: {}", stderr);
+    // A refused run leaves no session for `rwl status` to report.
+    assert!(!project.path().join(".rwl/last-session").exists());
 }
 
 #[test]
@@ -294,3 +840,1183 @@ fn test_validation_failure_reflected_in_result() {
     assert_eq!(parsed["outcome"], "max-iterations");
     assert_eq!(parsed["validation_passed"], false);
 }
+
+#[test]
+fn test_command_backend_drives_loop() {
+    // `llm.command` replaces the built-in claude invocation entirely: no mock
+    // `claude` on PATH, the templated argv reads the prompt from
+    // {{prompt_file}} and emits the completion signal.
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+
+    let rwl_dir = project.path().join(".rwl");
+    fs::create_dir_all(&rwl_dir).unwrap();
+    let config = r#"loop:
+  max_iterations: 2
+  iteration_timeout_minutes: 1
+  sleep_between_secs: 0
+  completion_signal: "<promise>COMPLETE</promise>"
+validation:
+  command: "true"
+quality_gates: []
+llm:
+  model: "local-7b"
+  dangerously_skip_permissions: true
+  command: ["sh", "-c", "grep -q '<promise>COMPLETE</promise>' {{prompt_file}} && echo '<promise>COMPLETE</promise>'"]
+git:
+  auto_commit: false
+"#;
+    fs::write(rwl_dir.join("rwl.yml"), config).unwrap();
+    fs::write(project.path().join("plan.md"), "# Test Plan\nDo nothing.").unwrap();
+
+    let bin = rwl_binary();
+    let output = Command::new(&bin)
+        .args([
+            "run",
+            "--plan",
+            "plan.md",
+            "--session-path",
+            &sessions.path().display().to_string(),
+            "--unsafe",
+        ])
+        .current_dir(project.path())
+        .output()
+        .expect("Failed to run rwl");
+
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "Expected exit 0, got {:?}\nstdout: {}\nstderr: {}",
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let entries: Vec<_> = fs::read_dir(sessions.path()).unwrap().filter_map(|e| e.ok()).collect();
+    let session_dir = entries[0].path();
+    assert!(session_dir.join("iterations/001/prompt.md").exists());
+}
+
+#[test]
+fn test_stream_json_records_usage_in_result() {
+    // Opt-in stream-json mode: the mock claude emits a non-JSON preamble, an
+    // assistant event carrying the promise, and a result event with spend.
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+
+    let rwl_dir = project.path().join(".rwl");
+    fs::create_dir_all(&rwl_dir).unwrap();
+    let config = r#"loop:
+  max_iterations: 2
+  iteration_timeout_minutes: 1
+  sleep_between_secs: 0
+  completion_signal: "<promise>COMPLETE</promise>"
+validation:
+  command: "true"
+quality_gates: []
+llm:
+  model: "sonnet"
+  dangerously_skip_permissions: true
+  output-format: stream-json
+git:
+  auto_commit: false
+"#;
+    fs::write(rwl_dir.join("rwl.yml"), config).unwrap();
+    fs::write(project.path().join("plan.md"), "# Test Plan\nDo nothing.").unwrap();
+
+    let mock_bin = create_mock_stream_json_claude(project.path(), "done\\n<promise>COMPLETE</promise>", 0.041);
+
+    let output = run_rwl(project.path(), &mock_bin, sessions.path());
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "Expected exit 0, got {:?}\nstdout: {}\nstderr: {}",
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let entries: Vec<_> = fs::read_dir(sessions.path()).unwrap().filter_map(|e| e.ok()).collect();
+    let session_dir = entries[0].path();
+    let content = fs::read_to_string(session_dir.join("result.json")).unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(parsed["usage"]["total"]["cost_usd"], 0.041);
+    assert_eq!(parsed["usage"]["iterations"][0]["iteration"], 1);
+    assert_eq!(parsed["usage"]["iterations"][0]["input_tokens"], 100);
+
+    let log = fs::read_to_string(session_dir.join("session.log")).unwrap();
+    assert!(log.contains("$0.0410"), "session.log should record the cost: {}", log);
+}
+
+#[test]
+fn test_cost_cap_exits_5_naming_the_cap() {
+    // Each mock iteration reports $0.60 and never completes; with a $1.00 cap
+    // the spend recorded after iteration 2 trips the budget at the top of
+    // iteration 3.
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+
+    let rwl_dir = project.path().join(".rwl");
+    fs::create_dir_all(&rwl_dir).unwrap();
+    let config = r#"loop:
+  max_iterations: 5
+  iteration_timeout_minutes: 1
+  sleep_between_secs: 0
+  completion_signal: "<promise>COMPLETE</promise>"
+validation:
+  command: "true"
+quality_gates: []
+llm:
+  model: "sonnet"
+  dangerously_skip_permissions: true
+  output-format: stream-json
+git:
+  auto_commit: false
+budget:
+  max-cost-usd: 1.0
+"#;
+    fs::write(rwl_dir.join("rwl.yml"), config).unwrap();
+    fs::write(project.path().join("plan.md"), "# Test Plan\nDo nothing.").unwrap();
+
+    let mock_bin = create_mock_stream_json_claude(project.path(), "still working", 0.6);
+    let output = run_rwl(project.path(), &mock_bin, sessions.path());
+
+    assert_eq!(
+        output.status.code(),
+        Some(5),
+        "Expected exit 5 (budget exceeded), got {:?}\nstdout: {}\nstderr: {}",
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let entries: Vec<_> = fs::read_dir(sessions.path()).unwrap().filter_map(|e| e.ok()).collect();
+    let session_dir = entries[0].path();
+    let content = fs::read_to_string(session_dir.join("result.json")).unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(parsed["outcome"], "budget-exceeded");
+    assert_eq!(parsed["iterations"], 2);
+    assert!(parsed["error"].as_str().unwrap().contains("max-cost-usd"));
+}
+
+#[test]
+fn test_retried_attempt_usage_counts_toward_cost_cap() {
+    // The first attempt reports $0.60 and fails, the retry reports another
+    // $0.60 and succeeds. Both count: the $1.00 cap ends the run after one
+    // iteration instead of two.
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+
+    let rwl_dir = project.path().join(".rwl");
+    fs::create_dir_all(&rwl_dir).unwrap();
+    let config = r#"loop:
+  max_iterations: 5
+  iteration_timeout_minutes: 1
+  sleep_between_secs: 0
+  completion_signal: "<promise>COMPLETE</promise>"
+validation:
+  command: "true"
+quality_gates: []
+llm:
+  model: "sonnet"
+  dangerously_skip_permissions: true
+  output-format: stream-json
+  retry:
+    max-attempts: 3
+    backoff-secs: 0
+git:
+  auto_commit: false
+budget:
+  max-cost-usd: 1.0
+"#;
+    fs::write(rwl_dir.join("rwl.yml"), config).unwrap();
+    fs::write(project.path().join("plan.md"), "# Test Plan\nDo nothing.").unwrap();
+
+    let marker = project.path().join("called-once");
+    let bin_dir = write_mock_agent(
+        project.path(),
+        &format!(
+            r#"#!/bin/bash
+echo '{{"type":"result","subtype":"success","total_cost_usd":0.6,"usage":{{"input_tokens":100,"output_tokens":20}}}}'
+if [ ! -f '{marker}' ]; then
+  touch '{marker}'
+  exit 1
+fi
+"#,
+            marker = marker.display()
+        ),
+    );
+
+    let output = run_rwl(project.path(), &bin_dir.display().to_string(), sessions.path());
+    assert_eq!(
+        output.status.code(),
+        Some(5),
+        "stdout: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let entries: Vec<_> = fs::read_dir(sessions.path()).unwrap().filter_map(|e| e.ok()).collect();
+    let content = fs::read_to_string(entries[0].path().join("result.json")).unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(parsed["outcome"], "budget-exceeded");
+    assert_eq!(parsed["iterations"], 1);
+    assert_eq!(parsed["retries"], 1);
+    assert_eq!(parsed["usage"]["total"]["cost_usd"], 1.2);
+    assert_eq!(parsed["usage"]["iterations"].as_array().unwrap().len(), 1);
+    assert_eq!(parsed["usage"]["iterations"][0]["input_tokens"], 200);
+}
+
+#[test]
+fn test_rate_limited_attempt_is_retried_within_iteration() {
+    // The mock claude fails its first call with a rate-limit message and
+    // succeeds on the second; with `llm.retry` the same iteration completes.
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+
+    let rwl_dir = project.path().join(".rwl");
+    fs::create_dir_all(&rwl_dir).unwrap();
+    let config = r#"loop:
+  max_iterations: 1
+  iteration_timeout_minutes: 1
+  sleep_between_secs: 0
+  completion_signal: "<promise>COMPLETE</promise>"
+validation:
+  command: "true"
+quality_gates: []
+llm:
+  model: "sonnet"
+  dangerously_skip_permissions: true
+  retry:
+    max-attempts: 3
+    backoff-secs: 0
+  rate-limit:
+    max-wait-minutes: 0
+git:
+  auto_commit: false
+"#;
+    fs::write(rwl_dir.join("rwl.yml"), config).unwrap();
+    fs::write(project.path().join("plan.md"), "# Test Plan\nDo nothing.").unwrap();
+
+    let marker = project.path().join("called-once");
+    let bin_dir = write_mock_agent(
+        project.path(),
+        &format!(
+            r#"#!/bin/bash
+if [ ! -f '{marker}' ]; then
+  touch '{marker}'
+  echo 'API Error: Rate limit reached' >&2
+  exit 1
+fi
+echo '<promise>COMPLETE</promise>'
+"#,
+            marker = marker.display()
+        ),
+    );
+
+    let output = run_rwl(project.path(), &bin_dir.display().to_string(), sessions.path());
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "Expected exit 0, got {:?}\nstdout: {}\nstderr: {}",
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let entries: Vec<_> = fs::read_dir(sessions.path()).unwrap().filter_map(|e| e.ok()).collect();
+    let session_dir = entries[0].path();
+    let content = fs::read_to_string(session_dir.join("result.json")).unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(parsed["iterations"], 1);
+    assert_eq!(parsed["retries"], 1);
+
+    let log = fs::read_to_string(session_dir.join("session.log")).unwrap();
+    assert!(log.contains("RETRY: iteration 1 attempt 1 failed (rate-limit: exit code 1)"));
+}
+
+#[test]
+fn test_rate_limit_pause_and_retry_keep_every_run() {
+    // Call 1 is rate-limited with a reset already due (a pause of the 30s
+    // margin), call 2 exits non-zero and is retried, call 3 completes. Every
+    // run keeps its own output files and counts in `agent_attempts`.
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+
+    let rwl_dir = project.path().join(".rwl");
+    fs::create_dir_all(&rwl_dir).unwrap();
+    let config = r#"loop:
+  max_iterations: 1
+  iteration_timeout_minutes: 1
+  sleep_between_secs: 0
+  completion_signal: "<promise>COMPLETE</promise>"
+validation:
+  command: "true"
+quality_gates: []
+llm:
+  model: "sonnet"
+  dangerously_skip_permissions: true
+  retry:
+    max-attempts: 2
+    backoff-secs: 0
+git:
+  auto_commit: false
+"#;
+    fs::write(rwl_dir.join("rwl.yml"), config).unwrap();
+    fs::write(project.path().join("plan.md"), "# Test Plan\nDo nothing.").unwrap();
+
+    let calls = project.path().join("calls");
+    let bin_dir = write_mock_agent(
+        project.path(),
+        &format!(
+            r#"#!/bin/bash
+echo x >> '{calls}'
+case $(wc -l < '{calls}') in
+  1) echo 'usage limit reached, resets in 0 seconds'; exit 1;;
+  2) echo 'compile error'; exit 1;;
+esac
+echo '<promise>COMPLETE</promise>'
+"#,
+            calls = calls.display()
+        ),
+    );
+
+    let output = run_rwl(project.path(), &bin_dir.display().to_string(), sessions.path());
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "stdout: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let entries: Vec<_> = fs::read_dir(sessions.path()).unwrap().filter_map(|e| e.ok()).collect();
+    let iteration_dir = entries[0].path().join("iterations/001");
+    let read = |name: &str| fs::read_to_string(iteration_dir.join(name)).unwrap();
+    assert!(read("agent.attempt-1.stdout").contains("usage limit reached"));
+    assert!(read("agent.attempt-2.stdout").contains("compile error"));
+    assert!(read("agent.stdout").contains("<promise>COMPLETE</promise>"));
+    let meta: serde_json::Value = serde_json::from_str(&read("meta.json")).unwrap();
+    assert_eq!(meta["agent_attempts"], 3);
+}
+
+#[test]
+fn test_rate_limit_pause_is_bounded_by_budget() {
+    // The mock claude is always usage-limited with a reset an hour out. The
+    // pause would be an hour, but the run's 1-minute wall-clock budget is
+    // pre-aged to ~3s left: the pause is clamped to that, recorded, and the
+    // budget then ends the run without a second iteration.
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+
+    let rwl_dir = project.path().join(".rwl");
+    fs::create_dir_all(&rwl_dir).unwrap();
+    let config = r#"loop:
+  max_iterations: 5
+  iteration_timeout_minutes: 1
+  sleep_between_secs: 0
+  completion_signal: "<promise>COMPLETE</promise>"
+validation:
+  command: "true"
+quality_gates: []
+llm:
+  model: "sonnet"
+  dangerously_skip_permissions: true
+git:
+  auto_commit: false
+budget:
+  max-total-minutes: 1
+"#;
+    fs::write(rwl_dir.join("rwl.yml"), config).unwrap();
+    fs::write(project.path().join("plan.md"), "# Test Plan\nDo nothing.").unwrap();
+
+    let bin_dir = write_mock_agent(
+        project.path(),
+        "#!/bin/bash\necho \"Claude AI usage limit reached|$(( $(date +%s) + 3600 ))\"\nexit 1\n",
+    );
+
+    let bin = rwl_binary();
+    let current_path = std::env::var("PATH").unwrap_or_default();
+    let output = Command::new(&bin)
+        .args([
+            "run",
+            "--plan",
+            "plan.md",
+            "--session-path",
+            &sessions.path().display().to_string(),
+            "--unsafe",
+        ])
+        .current_dir(project.path())
+        .env("PATH", format!("{}:{}", bin_dir.display(), current_path))
+        .env("RWL_BUDGET_PREAGE_SECS", "57")
+        .output()
+        .expect("Failed to run rwl");
+
+    assert_eq!(
+        output.status.code(),
+        Some(5),
+        "Expected exit 5, got {:?}\nstdout: {}\nstderr: {}",
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let entries: Vec<_> = fs::read_dir(sessions.path()).unwrap().filter_map(|e| e.ok()).collect();
+    let session_dir = entries[0].path();
+    let content = fs::read_to_string(session_dir.join("result.json")).unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(parsed["iterations"], 1);
+    let pauses = parsed["rate_limit_pauses"].as_array().unwrap();
+    assert_eq!(pauses.len(), 1);
+    assert!(pauses[0]["waited_secs"].as_u64().unwrap() <= 5);
+    assert!(pauses[0]["reset_at"].is_string());
+
+    let log = fs::read_to_string(session_dir.join("session.log")).unwrap();
+    assert!(log.contains("RATE LIMIT: iteration 1 paused"));
+}
+
+#[test]
+fn test_escalation_climbs_to_next_model_when_stuck() {
+    // Validation always fails; with `escalate-after: 2` the third iteration
+    // runs on the second rung, and the mock sees that model on its argv.
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+
+    let rwl_dir = project.path().join(".rwl");
+    fs::create_dir_all(&rwl_dir).unwrap();
+    let config = r#"loop:
+  max_iterations: 3
+  iteration_timeout_minutes: 1
+  sleep_between_secs: 0
+  completion_signal: "<promise>COMPLETE</promise>"
+validation:
+  command: "false"
+quality_gates: []
+llm:
+  model: "sonnet"
+  dangerously_skip_permissions: true
+  escalation: [haiku, opus]
+  escalate-after: 2
+git:
+  auto_commit: false
+"#;
+    fs::write(rwl_dir.join("rwl.yml"), config).unwrap();
+    fs::write(project.path().join("plan.md"), "# Test Plan\nDo nothing.").unwrap();
+
+    let seen = project.path().join("models-seen");
+    let bin_dir = write_mock_agent(
+        project.path(),
+        &format!("#!/bin/bash\necho \"$3\" >> '{}'\necho 'working'\n", seen.display()),
+    );
+
+    let output = run_rwl(project.path(), &bin_dir.display().to_string(), sessions.path());
+    assert_eq!(output.status.code(), Some(1), "Expected exit 1 (max iterations)");
+
+    assert_eq!(fs::read_to_string(&seen).unwrap(), "haiku\nhaiku\nopus\n");
+
+    let entries: Vec<_> = fs::read_dir(sessions.path()).unwrap().filter_map(|e| e.ok()).collect();
+    let session_dir = entries[0].path();
+    let content = fs::read_to_string(session_dir.join("result.json")).unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&content).unwrap();
+    let models: Vec<&str> = parsed["models"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["model"].as_str().unwrap())
+        .collect();
+    assert_eq!(models, vec!["haiku", "haiku", "opus"]);
+
+    let progress = fs::read_to_string(project.path().join(".rwl/progress.txt")).unwrap();
+    assert!(progress.contains("Model: opus"));
+    let log = fs::read_to_string(session_dir.join("session.log")).unwrap();
+    assert!(log.contains("ESCALATE: after iteration 2 -> model opus"));
+}
+
+#[test]
+fn test_live_model_edit_applies_without_escalation() {
+    // No `llm.escalation`: an edit of `llm.model` during the run is picked up
+    // by the next iteration rather than pinned to the model at start-up.
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+    setup_project(project.path(), "false", 2, "<promise>COMPLETE</promise>");
+
+    let config = project.path().join(".rwl/rwl.yml");
+    let seen = project.path().join("models-seen");
+    let bin_dir = write_mock_agent(
+        project.path(),
+        &format!(
+            "#!/bin/bash\necho \"$3\" >> '{}'\nsed -i 's/^  model: .*/  model: opus/' '{}'\necho 'working'\n",
+            seen.display(),
+            config.display()
+        ),
+    );
+
+    let output = run_rwl(project.path(), &bin_dir.display().to_string(), sessions.path());
+    assert_eq!(output.status.code(), Some(1), "Expected exit 1 (max iterations)");
+    assert_eq!(fs::read_to_string(&seen).unwrap(), "sonnet\nopus\n");
+}
+
+#[test]
+fn test_stdin_prompt_transport_keeps_prompt_off_argv() {
+    // The mock claude completes only if it received no positional prompt and
+    // the prompt arrived on stdin.
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+    setup_project(project.path(), "true", 2, "<promise>COMPLETE</promise>");
+    let config_path = project.path().join(".rwl").join("rwl.yml");
+    let config = fs::read_to_string(&config_path)
+        .unwrap()
+        .replace("llm:\n", "llm:\n  prompt-transport: stdin\n");
+    fs::write(&config_path, config).unwrap();
+
+    let bin_dir = write_mock_agent(
+        project.path(),
+        "#!/bin/bash\nprompt=$(cat)\n[ \"$#\" -eq 4 ] && grep -q 'promise' <<< \"$prompt\" && echo '<promise>COMPLETE</promise>'\n",
+    );
+
+    let output = run_rwl(project.path(), &bin_dir.display().to_string(), sessions.path());
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "Expected exit 0, got {:?}\nstdout: {}\nstderr: {}",
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let entries: Vec<_> = fs::read_dir(sessions.path()).unwrap().filter_map(|e| e.ok()).collect();
+    let prompt = fs::read_to_string(entries[0].path().join("iterations/001/prompt.md")).unwrap();
+    assert!(prompt.contains("<promise>COMPLETE</promise>"));
+}
+
+#[test]
+fn test_leftover_agent_children_are_reaped_and_logged() {
+    // The mock claude completes but leaves a background server running; it
+    // must be stopped with the agent's process group and named in session.log.
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+    setup_project(project.path(), "true", 1, "<promise>COMPLETE</promise>");
+
+    let pid_file = project.path().join("server.pid");
+    let bin_dir = write_mock_agent(
+        project.path(),
+        &format!(
+            "#!/bin/bash\nsleep 300 >/dev/null 2>&1 &\necho $! > '{}'\necho '<promise>COMPLETE</promise>'\n",
+            pid_file.display()
+        ),
+    );
+
+    let output = run_rwl(project.path(), &bin_dir.display().to_string(), sessions.path());
+    assert_eq!(output.status.code(), Some(0));
+
+    let pid = fs::read_to_string(&pid_file).unwrap().trim().to_string();
+    let alive = Command::new("ps").args(["-o", "stat=", "-p", &pid]).output().unwrap();
+    let stat = String::from_utf8_lossy(&alive.stdout);
+    assert!(
+        stat.trim().is_empty() || stat.trim().starts_with('Z'),
+        "leftover process {} still running: {}",
+        pid,
+        stat
+    );
+
+    let entries: Vec<_> = fs::read_dir(sessions.path()).unwrap().filter_map(|e| e.ok()).collect();
+    let log = fs::read_to_string(entries[0].path().join("session.log")).unwrap();
+    assert!(log.contains("PROCESS GROUP: stopped 1 process(es) left running after exit"));
+    assert!(log.contains("sleep 300"));
+}
+
+/// Start `rwl run` against a mock claude that hangs mid-iteration, send it
+/// `signals` (one `kill -<SIG>` each, 300ms apart) once the agent is running,
+/// and return the exit code, how long the exit took, and `result.json`.
+#[cfg(unix)]
+fn interrupt_mid_iteration(
+    agent_secs: u32,
+    sleep_between_secs: u32,
+    signals: &[&str],
+) -> (Option<i32>, std::time::Duration, serde_json::Value) {
+    use std::process::Stdio;
+    use std::time::{Duration, Instant};
+
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+    setup_project(project.path(), "true", 3, "<promise>COMPLETE</promise>");
+    let config = project.path().join(".rwl/rwl.yml");
+    let yaml = fs::read_to_string(&config).unwrap().replace(
+        "sleep_between_secs: 0",
+        &format!("sleep_between_secs: {}", sleep_between_secs),
+    );
+    fs::write(&config, yaml).unwrap();
+
+    let started_file = project.path().join("agent.started");
+    let bin_dir = write_mock_agent(
+        project.path(),
+        &format!(
+            "#!/bin/bash\necho 'working'\ntouch '{}'\nsleep {}\n",
+            started_file.display(),
+            agent_secs
+        ),
+    );
+
+    let bin = rwl_binary();
+    let path = format!("{}:{}", bin_dir.display(), std::env::var("PATH").unwrap_or_default());
+    let mut child = Command::new(&bin)
+        .args([
+            "run",
+            "--plan",
+            "plan.md",
+            "--session-path",
+            &sessions.path().display().to_string(),
+            "--unsafe",
+        ])
+        .current_dir(project.path())
+        .env("PATH", path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while !started_file.exists() {
+        assert!(Instant::now() < deadline, "mock agent never started");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    // Let a short agent finish, so the signal lands between iterations.
+    std::thread::sleep(Duration::from_secs(1));
+    let signalled = Instant::now();
+    for signal in signals {
+        Command::new("kill")
+            .args([&format!("-{}", signal), &child.id().to_string()])
+            .status()
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(300));
+    }
+
+    let code = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status.code();
+        }
+        if Instant::now() >= deadline + Duration::from_secs(30) {
+            let _ = child.kill();
+            panic!("rwl did not exit after {:?}", signals);
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    let elapsed = signalled.elapsed();
+
+    let entries: Vec<_> = fs::read_dir(sessions.path()).unwrap().filter_map(|e| e.ok()).collect();
+    let content = fs::read_to_string(entries[0].path().join("result.json")).unwrap();
+    (code, elapsed, serde_json::from_str(&content).unwrap())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_sigterm_stops_agent_and_writes_result_json() {
+    let (code, elapsed, result) = interrupt_mid_iteration(300, 0, &["TERM"]);
+    assert_eq!(code, Some(2));
+    assert!(elapsed.as_secs() < 20, "took {:?}", elapsed);
+    assert_eq!(result["outcome"], "stopped");
+    assert_eq!(result["iterations"], 0);
+    assert!(
+        result["error"].as_str().unwrap().contains("SIGTERM"),
+        "result: {}",
+        result
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_second_ctrl_c_aborts_running_agent() {
+    let (code, elapsed, result) = interrupt_mid_iteration(300, 0, &["INT", "INT"]);
+    assert_eq!(code, Some(2));
+    assert!(elapsed.as_secs() < 20, "took {:?}", elapsed);
+    assert_eq!(result["outcome"], "stopped");
+    assert!(
+        result["error"].as_str().unwrap().contains("second Ctrl-C"),
+        "result: {}",
+        result
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_sigterm_cuts_short_the_sleep_between_iterations() {
+    let (code, elapsed, result) = interrupt_mid_iteration(0, 300, &["TERM"]);
+    assert_eq!(code, Some(2));
+    assert!(elapsed.as_secs() < 20, "took {:?}", elapsed);
+    assert_eq!(result["outcome"], "stopped");
+}
+
+#[test]
+fn test_iteration_artifacts_are_written_per_iteration() {
+    // A git project with auto-commit on: the agent adds a file, validation and
+    // one quality gate run, and each lands in session_dir/iterations/001/.
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+    let signal = "<promise>COMPLETE</promise>";
+    setup_project(project.path(), "echo validated", 1, signal);
+    let config_path = project.path().join(".rwl/rwl.yml");
+    let config = fs::read_to_string(&config_path)
+        .unwrap()
+        .replace(
+            "quality_gates: []",
+            "quality_gates:\n  - name: Lint Check\n    command: \"echo lint-ok\"",
+        )
+        .replace("auto_commit: false", "auto_commit: true");
+    fs::write(&config_path, config).unwrap();
+    init_git_repo(project.path());
+
+    let bin_dir = write_mock_agent(
+        project.path(),
+        &format!(
+            "#!/bin/bash\necho 'new feature' > feature.txt\necho 'agent complaint' >&2\necho '{}'\n",
+            signal
+        ),
+    );
+    fs::write(project.path().join(".gitignore"), "mock-bin/\n").unwrap();
+
+    let bin = rwl_binary();
+    let path = format!("{}:{}", bin_dir.display(), std::env::var("PATH").unwrap_or_default());
+    let output = Command::new(&bin)
+        .args([
+            "run",
+            "--plan",
+            "plan.md",
+            "--session-path",
+            &sessions.path().display().to_string(),
+            "--isolation",
+            "none",
+            "--unsafe",
+        ])
+        .current_dir(project.path())
+        .env("PATH", path)
+        .output()
+        .unwrap();
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let entries: Vec<_> = fs::read_dir(sessions.path()).unwrap().filter_map(|e| e.ok()).collect();
+    let dir = entries[0].path().join("iterations/001");
+    let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap_or_else(|_| panic!("{} missing", name));
+    assert!(read("prompt.md").contains("plan.md"));
+    assert!(read("agent.stdout").contains(signal));
+    assert!(read("agent.stderr").contains("agent complaint"));
+    assert!(read("validation.log").contains("validated"));
+    assert!(read("gate-lint-check.log").contains("lint-ok"));
+    assert!(read("diff.patch").contains("+new feature"));
+
+    let meta: serde_json::Value = serde_json::from_str(&read("meta.json")).unwrap();
+    assert_eq!(meta["iteration"], 1);
+    assert_eq!(meta["agent_exit_code"], 0);
+    assert_eq!(meta["validation_exit_code"], 0);
+    assert_eq!(meta["promise_found"], true);
+    assert_eq!(meta["quality_gates"][0]["passed"], true);
+
+    let log = fs::read_to_string(entries[0].path().join("session.log")).unwrap();
+    assert!(log.contains("output: iterations/001/agent.stdout"));
+    assert!(!log.contains("agent complaint"), "agent output belongs in agent.stderr");
+}
+
+#[test]
+fn test_project_prompt_md_replaces_built_in_template() {
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+    let signal = "<promise>COMPLETE</promise>";
+    setup_project(project.path(), "true", 1, signal);
+    fs::write(
+        project.path().join(".rwl/PROMPT.md"),
+        "TEAM PROMPT for {{plan_path}}, finish with {{completion_signal}}\n{{> standards}}\n\
+         {{#if-iteration-gt 1}}retrying{{else}}first pass{{/if-iteration-gt}}\n",
+    )
+    .unwrap();
+    fs::create_dir_all(project.path().join(".rwl/partials")).unwrap();
+    fs::write(project.path().join(".rwl/partials/standards.md"), "Shared standards.").unwrap();
+    let mock_bin = create_mock_claude(project.path(), signal);
+
+    let output = run_rwl(project.path(), &mock_bin, sessions.path());
+    assert_eq!(output.status.code(), Some(0));
+
+    let entries: Vec<_> = fs::read_dir(sessions.path()).unwrap().filter_map(|e| e.ok()).collect();
+    let prompt = fs::read_to_string(entries[0].path().join("iterations/001/prompt.md")).unwrap();
+    assert!(prompt.starts_with("TEAM PROMPT for "), "prompt: {}", prompt);
+    assert!(prompt.contains(signal));
+    assert!(prompt.contains("Shared standards."), "prompt: {}", prompt);
+    assert!(prompt.contains("first pass"), "prompt: {}", prompt);
+}
+
+#[test]
+fn test_prompt_template_syntax_error_fails_before_loop() {
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+    setup_project(project.path(), "true", 1, "<promise>COMPLETE</promise>");
+    fs::write(
+        project.path().join(".rwl/PROMPT.md"),
+        "ok\n{{#if progress}}x{{/each}}\n",
+    )
+    .unwrap();
+    let mock_bin = create_mock_claude(project.path(), "<promise>COMPLETE</promise>");
+
+    let output = run_rwl(project.path(), &mock_bin, sessions.path());
+    assert_eq!(output.status.code(), Some(4));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("PROMPT.md:2:"), "stderr: {}", stderr);
+    // Nothing ran: no session was started.
+    assert_eq!(fs::read_dir(sessions.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn test_prompt_variables_carry_loop_state() {
+    // Two failing iterations in a git project with auto-commit: the second
+    // prompt knows its number, the previous diffstat and the recent commits.
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+    setup_project(project.path(), "false", 2, "<promise>COMPLETE</promise>");
+    let config_path = project.path().join(".rwl/rwl.yml");
+    let config = fs::read_to_string(&config_path)
+        .unwrap()
+        .replace("auto_commit: false", "auto_commit: true");
+    fs::write(&config_path, config).unwrap();
+    fs::write(
+        project.path().join(".rwl/PROMPT.md"),
+        "iteration {{iteration}} of {{max_iterations}}\n{{last_diffstat}}\n\
+         {{#each recent_commits}}commit: {{this}}\n{{/each}}",
+    )
+    .unwrap();
+    fs::write(project.path().join(".gitignore"), "mock-bin/\n").unwrap();
+    init_git_repo(project.path());
+
+    let bin_dir = write_mock_agent(project.path(), "#!/bin/bash\necho \"line $RANDOM\" >> notes.txt\necho working\n");
+
+    let bin = rwl_binary();
+    let path = format!("{}:{}", bin_dir.display(), std::env::var("PATH").unwrap_or_default());
+    let output = Command::new(&bin)
+        .args([
+            "run",
+            "--plan",
+            "plan.md",
+            "--session-path",
+            &sessions.path().display().to_string(),
+            "--isolation",
+            "none",
+            "--unsafe",
+        ])
+        .current_dir(project.path())
+        .env("PATH", path)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    let entries: Vec<_> = fs::read_dir(sessions.path()).unwrap().filter_map(|e| e.ok()).collect();
+    let first = fs::read_to_string(entries[0].path().join("iterations/001/prompt.md")).unwrap();
+    assert!(first.starts_with("iteration 1 of 2\n"), "{}", first);
+    assert!(!first.contains("notes.txt"));
+
+    let second = fs::read_to_string(entries[0].path().join("iterations/002/prompt.md")).unwrap();
+    assert!(second.starts_with("iteration 2 of 2\n"), "{}", second);
+    assert!(second.contains("notes.txt | 1 +"), "{}", second);
+    assert!(second.contains("commit: "), "{}", second);
+    assert!(second.contains("initial"), "{}", second);
+}
+
+#[test]
+fn test_prompt_command_renders_without_running() {
+    let project = TempDir::new().unwrap();
+    setup_project(project.path(), "false", 5, "<promise>COMPLETE</promise>");
+    let before: Vec<_> = fs::read_dir(project.path().join(".rwl")).unwrap().collect();
+
+    let output = Command::new(rwl_binary())
+        .args(["prompt", "--plan", "plan.md", "--model", "opus", "-m", "7"])
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        stdout.contains("=== Prompt (iteration 1, template: built-in) ==="),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("This is iteration 1 of 7."), "{}", stdout);
+    assert!(stdout.contains("<promise>COMPLETE</promise>"), "{}", stdout);
+    assert!(
+        stdout.contains("claude --print --model opus --dangerously-skip-permissions <prompt>"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("=== Protected paths ===\n- .git/\n- .rwl/\n"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("max_iterations: 7"), "{}", stdout);
+    assert!(stdout.contains("model: opus"), "{}", stdout);
+
+    // Nothing was run or written.
+    let after: Vec<_> = fs::read_dir(project.path().join(".rwl")).unwrap().collect();
+    assert_eq!(before.len(), after.len());
+    assert!(!project.path().join(".rwl/progress.txt").exists());
+}
+
+#[test]
+fn test_prompt_command_continues_an_existing_session() {
+    let project = TempDir::new().unwrap();
+    setup_project(project.path(), "false", 5, "<promise>COMPLETE</promise>");
+    fs::write(
+        project.path().join(".rwl/PROMPT.md"),
+        "iteration {{iteration}}\n{{progress}}\n{{#each failed_gates}}failed: {{this}}\n{{/each}}",
+    )
+    .unwrap();
+
+    // A worktree session: the progress log is in the session's worktree.
+    let session = TempDir::new().unwrap();
+    fs::create_dir_all(session.path().join("worktree/.rwl")).unwrap();
+    fs::write(
+        session.path().join("worktree/.rwl/progress.jsonl"),
+        r#"{"kind": "iteration", "iteration": 1, "started": "2026-01-01T00:00:00Z",
+            "finished": "2026-01-01T00:00:00Z", "validation_passed": false, "summary": "Fixed the parser."}"#
+            .replace('\n', "")
+            + "\n",
+    )
+    .unwrap();
+    let iteration_dir = session.path().join("iterations/001");
+    fs::create_dir_all(&iteration_dir).unwrap();
+    fs::write(
+        iteration_dir.join("meta.json"),
+        r#"{"iteration": 1, "model": "haiku", "started": "", "finished": "", "duration_secs": 0,
+            "agent_attempts": 1, "agent_exit_code": 0, "agent_secs": 1,
+            "quality_gates": [{"name": "clippy", "passed": false, "log": "gate-clippy.log"},
+                              {"name": "fmt", "passed": true, "log": "gate-fmt.log"}]}"#,
+    )
+    .unwrap();
+
+    let report = project.path().join("prompt-report.md");
+    let output = Command::new(rwl_binary())
+        .args(["prompt", "--plan", "plan.md", "--session"])
+        .arg(session.path())
+        .arg("--output")
+        .arg(&report)
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let report = fs::read_to_string(&report).unwrap();
+    assert!(
+        report.contains("iteration 2\n## Iteration 1\n") && report.contains("Summary: Fixed the parser.\n"),
+        "{}",
+        report
+    );
+    assert!(report.contains("failed: clippy\n"), "{}", report);
+    assert!(!report.contains("failed: fmt"), "{}", report);
+    assert!(report.contains("--model haiku"), "{}", report);
+    assert!(report.contains("=== Prompt (iteration 2, template: "), "{}", report);
+}
+
+#[test]
+fn test_prompt_command_reads_the_session_worktree_plan() {
+    // The agent ticked phase 1 in the worktree's copy of the plan; the
+    // project's copy is untouched. The next iteration works on phase 2.
+    let project = TempDir::new().unwrap();
+    setup_project(project.path(), "false", 5, "<promise>COMPLETE</promise>");
+    fs::write(
+        project.path().join(".rwl/PROMPT.md"),
+        "plan: {{plan_path}}\nphase {{phase.number}}: {{phase.title}}\n",
+    )
+    .unwrap();
+    let plan = "# Plan\n\n## Phase 1: Lexer\n\n- [ ] Tokenize\n\n## Phase 2: Parser\n\n- [ ] Parse\n";
+    fs::write(project.path().join("plan.md"), plan).unwrap();
+
+    let session = TempDir::new().unwrap();
+    let worktree = session.path().join("worktree");
+    fs::create_dir_all(worktree.join(".rwl")).unwrap();
+    fs::write(worktree.join("plan.md"), plan.replace("[ ] Tokenize", "[x] Tokenize")).unwrap();
+
+    let output = Command::new(rwl_binary())
+        .args(["prompt", "--plan", "plan.md", "--session"])
+        .arg(session.path())
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let report = String::from_utf8_lossy(&output.stdout);
+    assert!(report.contains("phase 2: Phase 2: Parser\n"), "{}", report);
+    let plan_path = worktree.canonicalize().unwrap().join("plan.md");
+    assert!(
+        report.contains(&format!("plan: {}\n", plan_path.display())),
+        "{}",
+        report
+    );
+}
+
+#[test]
+fn test_progress_log_is_in_worktree_and_protected() {
+    // A worktree run: the agent sees the progress log at .rwl/progress.txt in
+    // its worktree, its edits to it are undone, and it is never committed.
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+    setup_project(project.path(), "false", 2, "<promise>COMPLETE</promise>");
+    let config_path = project.path().join(".rwl/rwl.yml");
+    let config = fs::read_to_string(&config_path)
+        .unwrap()
+        .replace("auto_commit: false", "auto_commit: true");
+    fs::write(&config_path, config).unwrap();
+    fs::write(project.path().join(".gitignore"), "mock-bin/\n").unwrap();
+    init_git_repo(project.path());
+
+    let bin_dir = write_mock_agent(
+        project.path(),
+        "#!/bin/bash\n\
+         cat .rwl/progress.txt > \"seen-$RANDOM.txt\"\n\
+         echo 'All done, trust me' >> .rwl/progress.txt\n\
+         echo working\n",
+    );
+
+    let path = format!("{}:{}", bin_dir.display(), std::env::var("PATH").unwrap_or_default());
+    let output = Command::new(rwl_binary())
+        .args([
+            "run",
+            "--plan",
+            "plan.md",
+            "--session-path",
+            &sessions.path().display().to_string(),
+            "--unsafe",
+        ])
+        .current_dir(project.path())
+        .env("PATH", path)
+        .output()
+        .unwrap();
+    assert_eq!(
+        output.status.code(),
+        Some(1),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let entries: Vec<_> = fs::read_dir(sessions.path()).unwrap().filter_map(|e| e.ok()).collect();
+    let worktree = entries[0].path().join("worktree");
+    let progress = fs::read_to_string(worktree.join(".rwl/progress.txt")).unwrap();
+    assert!(progress.starts_with("# RWL Progress Log"), "{}", progress);
+    assert!(progress.contains("## Iteration 2"), "{}", progress);
+    assert!(!progress.contains("trust me"), "{}", progress);
+    assert!(progress.contains(".rwl/progress.txt"), "{}", progress);
+
+    // The second agent run read the log rwl wrote after the first.
+    let seen: Vec<String> = fs::read_dir(&worktree)
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().starts_with("seen-"))
+        .map(|e| fs::read_to_string(e.path()).unwrap())
+        .collect();
+    assert!(seen.iter().any(|s| s.contains("## Iteration 1")), "{:?}", seen);
+
+    let tracked = Command::new("git")
+        .args(["ls-files", ".rwl"])
+        .current_dir(&worktree)
+        .output()
+        .unwrap();
+    assert!(!String::from_utf8_lossy(&tracked.stdout).contains("progress.txt"));
+    assert!(!project.path().join(".rwl/progress.txt").exists());
+}
+
+#[test]
+fn test_last_session_survives_protected_path_guard_in_place() {
+    // `isolation: none`: rwl notes the session in the project's .rwl/, which
+    // is protected; the guard must leave that note alone.
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+    setup_project(project.path(), "false", 2, "<promise>COMPLETE</promise>");
+    fs::write(project.path().join(".gitignore"), "mock-bin/\n").unwrap();
+    init_git_repo(project.path());
+    let mock_bin = create_mock_claude(project.path(), "working");
+
+    let path = format!("{}:{}", mock_bin, std::env::var("PATH").unwrap_or_default());
+    let output = Command::new(rwl_binary())
+        .args([
+            "run",
+            "--plan",
+            "plan.md",
+            "--isolation",
+            "none",
+            "--session-path",
+            &sessions.path().display().to_string(),
+            "--unsafe",
+        ])
+        .current_dir(project.path())
+        .env("PATH", path)
+        .output()
+        .unwrap();
+    assert_eq!(
+        output.status.code(),
+        Some(1),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("last-session"), "{}", stdout);
+
+    let recorded = fs::read_to_string(project.path().join(".rwl/last-session")).unwrap();
+    let entries: Vec<_> = fs::read_dir(sessions.path()).unwrap().filter_map(|e| e.ok()).collect();
+    assert_eq!(
+        Path::new(recorded.trim()),
+        entries[0].path().canonicalize().unwrap().as_path()
+    );
+}
+
+#[test]
+fn test_progress_summarize_folds_old_entries_into_digest() {
+    let project = TempDir::new().unwrap();
+    let sessions = TempDir::new().unwrap();
+    setup_project(project.path(), "false", 4, "<promise>COMPLETE</promise>");
+    let config_path = project.path().join(".rwl/rwl.yml");
+    let mut config = fs::read_to_string(&config_path).unwrap();
+    config
+        .push_str("progress:\n  max-bytes: 400\n  strategy: summarize\n  keep-iterations: 1\n  summary-model: haiku\n");
+    fs::write(&config_path, config).unwrap();
+
+    // The summary call is recognised by its prompt and answered with a digest.
+    let bin_dir = write_mock_agent(
+        project.path(),
+        "#!/bin/bash\n\
+         for arg in \"$@\"; do\n\
+           case \"$arg\" in *'compacting the progress log'*) echo 'DIGEST: lexer keeps failing'; exit 0;; esac\n\
+         done\n\
+         echo working\n",
+    );
+
+    let output = run_rwl(project.path(), &bin_dir.display().to_string(), sessions.path());
+    assert_eq!(
+        output.status.code(),
+        Some(1),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let entries: Vec<_> = fs::read_dir(sessions.path()).unwrap().filter_map(|e| e.ok()).collect();
+    let session_dir = entries[0].path();
+    let last = fs::read_to_string(session_dir.join("iterations/004/prompt.md")).unwrap();
+    assert!(last.contains("## Digest of iterations 1-"), "{}", last);
+    assert!(last.contains("DIGEST: lexer keeps failing"), "{}", last);
+    assert!(last.contains("## Iteration 3\n"), "{}", last);
+    assert!(!last.contains("## Iteration 1\n"), "{}", last);
+    assert!(session_dir.join("progress-digest.json").exists());
+
+    // The log on disk keeps the full history.
+    let progress = fs::read_to_string(project.path().join(".rwl/progress.txt")).unwrap();
+    for i in 1..=4 {
+        assert!(progress.contains(&format!("## Iteration {}\n", i)), "{}", progress);
+    }
+}